@@ -1,7 +1,3 @@
-#![feature(test)]
-
-extern crate test;
-
 use flash_lso::read::Reader;
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
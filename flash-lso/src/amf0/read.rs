@@ -3,11 +3,12 @@ use crate::amf0::type_marker::TypeMarker;
 
 #[cfg(feature = "amf3")]
 use crate::amf3;
-use crate::nom_utils::{take_str, AMFResult};
+use crate::errors::Diagnostic;
+use crate::nom_utils::{parse_body_recover, take_str, AMFResult, BodyDecoder};
 use crate::types::{ClassDefinition, Element, ObjectId, Reference, Value};
 use crate::PADDING;
 use nom::bytes::complete::{tag, take};
-use nom::combinator::{map, map_res};
+use nom::combinator::map;
 use nom::error::{make_error, ErrorKind};
 use nom::multi::{many0, many_m_n};
 use nom::number::complete::{be_f64, be_u16, be_u32, be_u8};
@@ -30,40 +31,9 @@ fn parse_element_bool(i: &[u8]) -> AMFResult<'_, Rc<Value>> {
     Ok((i, Rc::new(Value::Bool(v > 0))))
 }
 
-fn parse_element_string(i: &[u8]) -> AMFResult<'_, Rc<Value>> {
-    let (i, v) = parse_string(i)?;
-    Ok((i, Rc::new(Value::String(v.to_string()))))
-}
-
-fn parse_element_date(i: &[u8]) -> AMFResult<'_, Rc<Value>> {
-    let (i, millis) = be_f64(i)?;
-    let (i, time_zone) = be_u16(i)?;
-
-    Ok((i, Rc::new(Value::Date(millis, Some(time_zone)))))
-}
-
-fn parse_long_string_internal(i: &[u8]) -> AMFResult<'_, &str> {
-    let (i, length) = be_u32(i)?;
-    map_res(take(length), std::str::from_utf8)(i)
-}
-
-fn parse_element_long_string(i: &[u8]) -> AMFResult<'_, Rc<Value>> {
-    let (i, str) = parse_long_string_internal(i)?;
-    Ok((i, Rc::new(Value::String(str.to_string()))))
-}
-
-fn parse_element_xml(i: &[u8]) -> AMFResult<'_, Rc<Value>> {
-    let (i, content) = parse_long_string_internal(i)?;
-    Ok((i, Rc::new(Value::XML(content.to_string(), true))))
-}
-
-fn read_type_marker(i: &[u8]) -> AMFResult<'_, TypeMarker> {
-    let (i, type_) = be_u8(i)?;
-    Ok((
-        i,
-        TypeMarker::try_from(type_).unwrap_or(TypeMarker::Unsupported),
-    ))
-}
+/// The range of valid timezone offsets in minutes, from UTC+14 to UTC-12
+/// These are in the form of `Date.getTimezoneOffset`, so are positive west of UTC
+const TIMEZONE_RANGE: std::ops::RangeInclusive<i16> = -14 * 60..=12 * 60;
 
 /// Handles decoding AMF0
 #[derive(Default)]
@@ -72,10 +42,72 @@ pub struct AMF0Decoder {
     cache: Vec<Rc<Value>>,
 
     #[cfg(feature = "amf3")]
-    amf3_decoder: amf3::read::AMF3Decoder,
+    pub(crate) amf3_decoder: amf3::read::AMF3Decoder,
+
+    /// If true, data that doesn't conform to the specification will be rejected rather than read on a best-effort basis
+    /// This also applies to any embedded AMF3 data
+    pub strict: bool,
 }
 
 impl AMF0Decoder {
+    fn read_type_marker<'a>(&self, i: &'a [u8]) -> AMFResult<'a, TypeMarker> {
+        let (i, type_) = be_u8(i)?;
+        match TypeMarker::try_from(type_) {
+            Ok(type_) => Ok((i, type_)),
+            Err(_) if self.strict => Err(Err::Error(crate::errors::Error::UnsupportedType(type_))),
+            Err(_) => Ok((i, TypeMarker::Unsupported)),
+        }
+    }
+
+    /// Read `length` bytes as a UTF-8 string
+    /// In strict mode invalid UTF-8 is reported as `Error::InvalidString` rather than a generic parse error
+    fn take_str<'a>(&self, i: &'a [u8], length: u32) -> AMFResult<'a, &'a str> {
+        let (k, bytes) = take(length)(i)?;
+        match std::str::from_utf8(bytes) {
+            Ok(s) => Ok((k, s)),
+            Err(_) if self.strict => Err(Err::Error(crate::errors::Error::InvalidString)),
+            Err(_) => Err(Err::Error(make_error(i, ErrorKind::MapRes))),
+        }
+    }
+
+    fn parse_string<'a>(&self, i: &'a [u8]) -> AMFResult<'a, &'a str> {
+        let (i, length) = be_u16(i)?;
+        self.take_str(i, length.into())
+    }
+
+    fn parse_long_string<'a>(&self, i: &'a [u8]) -> AMFResult<'a, &'a str> {
+        let (i, length) = be_u32(i)?;
+        self.take_str(i, length)
+    }
+
+    fn parse_element_string<'a>(&self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
+        let (i, v) = self.parse_string(i)?;
+        Ok((i, Rc::new(Value::String(v.to_string()))))
+    }
+
+    fn parse_element_long_string<'a>(&self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
+        let (i, str) = self.parse_long_string(i)?;
+        Ok((i, Rc::new(Value::String(str.to_string()))))
+    }
+
+    fn parse_element_xml<'a>(&self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
+        let (i, content) = self.parse_long_string(i)?;
+        Ok((i, Rc::new(Value::XML(content.to_string(), true))))
+    }
+
+    fn parse_element_date<'a>(&self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
+        let (i, millis) = be_f64(i)?;
+        let (i, time_zone) = be_u16(i)?;
+
+        if self.strict && !TIMEZONE_RANGE.contains(&(time_zone as i16)) {
+            return Err(Err::Error(crate::errors::Error::InvalidTimezone(
+                time_zone as i16,
+            )));
+        }
+
+        Ok((i, Rc::new(Value::Date(millis, Some(time_zone)))))
+    }
+
     fn parse_element_reference<'a>(&self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
         let (i, reference_index) = be_u16(i)?;
 
@@ -98,7 +130,7 @@ impl AMF0Decoder {
     }
 
     fn parse_element_typed_object<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
-        let (i, name) = parse_string(i)?;
+        let (i, name) = self.parse_string(i)?;
 
         map(
             |i| self.parse_array_element(i),
@@ -148,8 +180,8 @@ impl AMF0Decoder {
 
         let mut i = i;
         loop {
-            let (k, _) = parse_string(i)?;
-            let (k, next_type) = read_type_marker(k)?;
+            let (k, _) = self.parse_string(i)?;
+            let (k, next_type) = self.read_type_marker(k)?;
             if next_type == TypeMarker::ObjectEnd {
                 i = k;
                 break;
@@ -167,6 +199,7 @@ impl AMF0Decoder {
     fn parse_element_amf3<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
         #[cfg(feature = "amf3")]
         {
+            self.amf3_decoder.strict = self.strict;
             let (i, x) = self.amf3_decoder.parse_single_element(i)?;
             Ok((i, Rc::new(Value::AMF3(x))))
         }
@@ -179,7 +212,7 @@ impl AMF0Decoder {
     /// Parse a single AMF0 element
     pub fn parse_single_element<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
         // Get the type of the next element
-        let (i, type_) = read_type_marker(i)?;
+        let (i, type_) = self.read_type_marker(i)?;
 
         let cache_idx = self.cache.len();
         self.cache.push(Rc::new(Value::Undefined));
@@ -187,7 +220,7 @@ impl AMF0Decoder {
         let (i, v) = match type_ {
             TypeMarker::Number => parse_element_number(i),
             TypeMarker::Boolean => parse_element_bool(i),
-            TypeMarker::String => parse_element_string(i),
+            TypeMarker::String => self.parse_element_string(i),
             TypeMarker::Object => {
                 let (i, v) = self.parse_element_object(i)?;
                 self.cache[cache_idx] = Rc::clone(&v);
@@ -209,16 +242,19 @@ impl AMF0Decoder {
                 self.cache[cache_idx] = Rc::clone(&v);
                 Ok((i, v))
             }
-            TypeMarker::Date => parse_element_date(i),
-            TypeMarker::LongString => parse_element_long_string(i),
+            TypeMarker::Date => self.parse_element_date(i),
+            TypeMarker::LongString => self.parse_element_long_string(i),
             TypeMarker::Unsupported => Ok((i, Rc::new(Value::Unsupported))),
-            TypeMarker::Xml => parse_element_xml(i),
+            TypeMarker::Xml => self.parse_element_xml(i),
             TypeMarker::TypedObject => {
                 let (i, v) = self.parse_element_typed_object(i)?;
                 self.cache[cache_idx] = Rc::clone(&v);
                 Ok((i, v))
             }
             TypeMarker::AMF3 => self.parse_element_amf3(i),
+            TypeMarker::MovieClip | TypeMarker::RecordSet if self.strict => {
+                Err(Err::Error(crate::errors::Error::ReservedType(type_ as u8)))
+            }
             TypeMarker::MovieClip | TypeMarker::RecordSet | TypeMarker::ObjectEnd => Err(
                 Err::Error(crate::errors::Error::UnsupportedType(type_ as u8)),
             ),
@@ -228,7 +264,7 @@ impl AMF0Decoder {
     }
 
     fn parse_element<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Element> {
-        let (i, name) = parse_string(i)?;
+        let (i, name) = self.parse_string(i)?;
 
         map(
             |i| self.parse_single_element(i),
@@ -251,6 +287,13 @@ impl AMF0Decoder {
         many0(|i| self.parse_element_and_padding(i))(i)
     }
 
+    /// Parse a sequence of `PADDING` delimited `Values`, skipping over any that can't be decoded
+    ///
+    /// Returns every element that could be read, along with a `Diagnostic` for each skipped region of the input
+    pub fn parse_body_recover(&mut self, i: &[u8]) -> (Vec<Element>, Vec<Diagnostic>) {
+        parse_body_recover(self, i)
+    }
+
     /// Convert the given value into a reference, if possible
     /// This reference is only valid for values sourced from this decoder and will only reference values decoded by it
     pub fn as_reference(&self, v: &Value) -> Option<Reference> {
@@ -259,4 +302,26 @@ impl AMF0Decoder {
             .position(|cv| *cv == Rc::new(v.clone()))
             .map(|r| Reference(r as _))
     }
+
+    /// Get the value that the given reference points to
+    /// This is only valid for references read by this decoder, `None` is returned if the reference is out of range
+    pub fn resolve_reference(&self, reference: Reference) -> Option<Rc<Value>> {
+        self.cache.get(reference.0 as usize).cloned()
+    }
+}
+
+impl BodyDecoder for AMF0Decoder {
+    type State = usize;
+
+    fn save_state(&self) -> Self::State {
+        self.cache.len()
+    }
+
+    fn restore_state(&mut self, state: &Self::State) {
+        self.cache.truncate(*state);
+    }
+
+    fn parse_root_element<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Element> {
+        self.parse_element_and_padding(i)
+    }
 }
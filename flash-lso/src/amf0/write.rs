@@ -1,13 +1,12 @@
 /// Support for encoding AMF0
-use crate::types::{Element, Reference, Value};
+use crate::types::{array_length, Element, Reference, Value};
 use crate::PADDING;
 use std::io::Write;
 
 use crate::amf0::type_marker::TypeMarker;
 use crate::nom_utils::write_string;
-use crate::write::WriteExt;
+use crate::write::{ByteCounter, WriteExt};
 use std::io::Result;
-use std::ops::Deref;
 use std::rc::Rc;
 
 #[cfg(feature = "amf3")]
@@ -139,8 +138,8 @@ fn write_mixed_array<'a, 'b: 'a, W: Write + 'a>(
     elements: &'b [Element],
     length: u32,
 ) -> Result<()> {
-    //TODO: what is the u16 padding
-    //TODO: sometimes array length is ignored (u32) sometimes its: elements.len() as u32
+    // Never declare a length that would leave items past the end of the array
+    let length = length.max(array_length(dense, elements));
 
     write_type_marker(writer, TypeMarker::MixedArrayStart)?;
     writer.write_u32(length)?;
@@ -150,6 +149,7 @@ fn write_mixed_array<'a, 'b: 'a, W: Write + 'a>(
     for element in elements {
         write_element(writer, element)?
     }
+    // An empty key followed by the object end marker
     writer.write_u16(0)?;
     write_type_marker(writer, TypeMarker::ObjectEnd)?;
     Ok(())
@@ -157,10 +157,12 @@ fn write_mixed_array<'a, 'b: 'a, W: Write + 'a>(
 
 pub(crate) fn write_value<'a, 'b: 'a, W: Write + 'a>(
     writer: &mut W,
-    element: &'b Rc<Value>,
+    element: &'b Value,
 ) -> Result<()> {
-    match element.deref() {
+    match element {
         Value::Number(n) => write_number_element(writer, *n),
+        // AMF0 has no integer type
+        Value::Integer(i) => write_number_element(writer, f64::from(*i)),
         Value::Bool(b) => write_bool_element(writer, *b),
         Value::String(s) => {
             if s.len() > 65535 {
@@ -185,6 +187,7 @@ pub(crate) fn write_value<'a, 'b: 'a, W: Write + 'a>(
         Value::ECMAArray(_id, dense, elems, elems_length) => {
             write_mixed_array(writer, dense, elems, *elems_length)
         }
+        Value::MixedArray(_id, dense, elems) => write_mixed_array(writer, dense, elems, 0),
         #[cfg(feature = "amf3")]
         Value::AMF3(e) => {
             write_type_marker(writer, TypeMarker::AMF3)?;
@@ -222,3 +225,24 @@ pub(crate) fn write_body<'a, 'b: 'a, W: Write + 'a>(
     }
     Ok(())
 }
+
+/// Get the number of bytes needed to encode the given value as AMF0, fails if the value can't be written
+pub fn value_length(value: &Value) -> Result<usize> {
+    let mut counter = ByteCounter::default();
+    write_value(&mut counter, value)?;
+    Ok(counter.0)
+}
+
+/// Get the number of bytes needed to encode the given element (name and value) as AMF0, see `value_length`
+pub fn element_length(element: &Element) -> Result<usize> {
+    let mut counter = ByteCounter::default();
+    write_element(&mut counter, element)?;
+    Ok(counter.0)
+}
+
+/// Get the number of bytes needed to encode the given elements as an AMF0 body, see `value_length`
+pub fn body_length(elements: &[Element]) -> Result<usize> {
+    let mut counter = ByteCounter::default();
+    write_body(&mut counter, elements)?;
+    Ok(counter.0)
+}
@@ -48,6 +48,7 @@ impl<'a> ObjWriter<'a> for Amf0Writer {
             (
                 Some(ObjectWriter {
                     elements: Vec::new(),
+                    class_def: None,
                     parent: self,
                 }),
                 r,
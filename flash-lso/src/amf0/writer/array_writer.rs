@@ -43,6 +43,7 @@ impl<'a, 'b> ObjWriter<'a> for ArrayWriter<'a, 'b> {
             (
                 Some(ObjectWriter {
                     elements: Vec::new(),
+                    class_def: None,
                     parent: self,
                 }),
                 r,
@@ -93,6 +94,8 @@ impl<'a, 'b> ObjWriter<'a> for ArrayWriter<'a, 'b> {
 impl<'a, 'b> ArrayWriter<'a, 'b> {
     /// Finalize this array, adding it to it's parent
     /// If this is not called, the array will not be added
+    ///
+    /// `length` is the declared length of the array, see `Value::ECMAArray` for how it is written
     pub fn commit<T: AsRef<str>>(self, name: T, length: u32) {
         self.parent.add_element(
             name.as_ref(),
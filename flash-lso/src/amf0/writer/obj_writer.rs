@@ -1,4 +1,4 @@
-use crate::types::{Reference, Value};
+use crate::types::{ClassDefinition, Reference, Value};
 
 use super::{ArrayWriter, CacheKey, ObjectWriter};
 
@@ -19,6 +19,29 @@ pub trait ObjWriter<'a> {
         'a: 'c,
         'a: 'd;
 
+    /// Create a writer that can serialize a typed object, only the name of `class_def` is used in AMF0
+    ///
+    /// If an object with the same `cache_key` has already been written, then this will return `None` for the Writer and the existing reference
+    /// If this key is unique, then both a Writer and a Reference will be returned
+    fn typed_object<'c: 'a, 'd>(
+        &'d mut self,
+        cache_key: CacheKey,
+        class_def: ClassDefinition,
+    ) -> (Option<ObjectWriter<'d, 'c>>, Reference)
+    where
+        'a: 'c,
+        'a: 'd,
+    {
+        let (writer, reference) = self.object(cache_key);
+        (
+            writer.map(|writer| ObjectWriter {
+                class_def: Some(class_def),
+                ..writer
+            }),
+            reference,
+        )
+    }
+
     /// Create a writer that can serialize an array
     ///
     /// If an object with the same `cache_key` has already been written, then this will return `None` for the Writer and the existing reference
@@ -30,7 +53,6 @@ pub trait ObjWriter<'a> {
     where
         'a: 'c,
         'a: 'd;
-    //Typed objects can also be sent cached
 
     /// Write a string
     fn string(&mut self, name: &str, s: &str) {
@@ -43,8 +65,10 @@ pub trait ObjWriter<'a> {
     }
 
     /// Write a reference
+    ///
+    /// Like every other value, the reference itself takes up an index in the reference table
     fn reference(&mut self, name: &str, v: Reference) {
-        self.add_element(name, Value::Reference(v), false);
+        self.add_element(name, Value::Reference(v), true);
     }
 
     /// Write an undefined
@@ -1,6 +1,6 @@
 use std::rc::Rc;
 
-use crate::types::{Element, ObjectId, Reference, Value};
+use crate::types::{ClassDefinition, Element, ObjectId, Reference, Value};
 
 use super::{ArrayWriter, CacheKey, ObjWriter};
 
@@ -9,6 +9,9 @@ pub struct ObjectWriter<'a, 'b> {
     /// The elements of this object
     pub(crate) elements: Vec<Element>,
 
+    /// The class of this object, if it is a typed object
+    pub(crate) class_def: Option<ClassDefinition>,
+
     /// The parent of this writer
     pub(crate) parent: &'a mut dyn ObjWriter<'b>,
 }
@@ -42,6 +45,7 @@ impl<'a, 'b> ObjWriter<'a> for ObjectWriter<'a, 'b> {
             (
                 Some(ObjectWriter {
                     elements: Vec::new(),
+                    class_def: None,
                     parent: self,
                 }),
                 r,
@@ -92,11 +96,13 @@ impl<'a, 'b> ObjWriter<'a> for ObjectWriter<'a, 'b> {
 impl<'a, 'b> ObjectWriter<'a, 'b> {
     /// Finalize this object, adding it to it's parent
     /// If this is not called, the object will not be added
+    ///
+    /// The reference for this object was taken from the root when the writer was created, before any of its children,
+    /// so references stay in the order that they are read back in at any level of nesting
     pub fn commit<T: AsRef<str>>(self, name: T) {
-        //TODO: this doent work for multi level nesting
         self.parent.add_element(
             name.as_ref(),
-            Value::Object(ObjectId::INVALID, self.elements, None),
+            Value::Object(ObjectId::INVALID, self.elements, self.class_def),
             false,
         );
     }
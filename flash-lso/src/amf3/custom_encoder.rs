@@ -6,9 +6,14 @@ use crate::types::Element;
 use crate::types::*;
 
 use crate::nom_utils::AMFResult;
+use crate::read::Reader;
+use crate::write::Writer;
+use std::collections::HashMap;
 use std::rc::Rc;
 
 /// A trait to define encoding for custom types for use with Externalized objects
+///
+/// Prefer implementing `ExternalCodec`, which also provides the matching decoder
 pub trait CustomEncoder {
     /// This should implement the encoding of a given set of external elements for the given class definition
     /// Access to the AMF3Encoder is given to allow access to caches
@@ -21,7 +26,83 @@ pub trait CustomEncoder {
     ) -> Vec<u8>;
 }
 
-//TODO: combine with trait
 /// Type used for specifying a custom decoder for a AMF3 external type
+///
+/// Prefer implementing `ExternalCodec`, which also provides the matching encoder
 pub type ExternalDecoderFn =
     Rc<Box<dyn for<'a> Fn(&'a [u8], &mut AMF3Decoder) -> AMFResult<'a, Vec<Element>>>>;
+
+/// A trait to define both decoding and encoding of an externalized type
+///
+/// Codecs are registered into an `ExternalCodecRegistry`, which installs both directions at once
+pub trait ExternalCodec {
+    /// This should implement the decoding of the external data of an object into a set of elements
+    /// Access to the AMF3Decoder is given to allow reading nested values
+    fn decode<'a>(&self, i: &'a [u8], decoder: &mut AMF3Decoder) -> AMFResult<'a, Vec<Element>>;
+
+    /// This should implement the encoding of a given set of external elements for the given class definition
+    /// Access to the AMF3Encoder is given to allow access to caches, errors are returned by the writer
+    fn encode(
+        &self,
+        elements: &[Element],
+        class_def: &Option<ClassDefinition>,
+        encoder: &AMF3Encoder,
+    ) -> std::io::Result<Vec<u8>>;
+}
+
+/// A set of codecs for externalized types, keyed by class name
+#[derive(Default, Clone)]
+pub struct ExternalCodecRegistry {
+    codecs: HashMap<String, Rc<dyn ExternalCodec>>,
+}
+
+impl ExternalCodecRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a codec for the given class name, replacing any existing codec for that class
+    ///
+    /// The same codec may be registered for several class names, such as aliases
+    pub fn register(&mut self, class_name: impl Into<String>, codec: Rc<dyn ExternalCodec>) {
+        self.codecs.insert(class_name.into(), codec);
+    }
+
+    /// Check if a codec is registered for the given class name
+    pub fn contains(&self, class_name: &str) -> bool {
+        self.codecs.contains_key(class_name)
+    }
+
+    /// Get the codec registered for the given class name
+    pub fn get(&self, class_name: &str) -> Option<Rc<dyn ExternalCodec>> {
+        self.codecs.get(class_name).map(Rc::clone)
+    }
+
+    /// Register every codec of `other` into this registry, replacing any existing codecs for the same classes
+    pub fn extend(&mut self, other: &ExternalCodecRegistry) {
+        self.codecs
+            .extend(other.codecs.iter().map(|(k, v)| (k.clone(), Rc::clone(v))));
+    }
+
+    /// Install every codec into the given AMF3Decoder, only the decoding side is used
+    pub fn install_decoder(&self, decoder: &mut AMF3Decoder) {
+        decoder.codecs.extend(self);
+    }
+
+    /// Install every codec into the given AMF3Encoder, only the encoding side is used
+    pub fn install_encoder(&self, encoder: &mut AMF3Encoder) {
+        encoder.codecs.extend(self);
+    }
+
+    /// Install every codec into the given Reader, including the decoder used for AMF3 values embedded in AMF0
+    pub fn install_reader(&self, reader: &mut Reader) {
+        self.install_decoder(&mut reader.amf3_decoder);
+        self.install_decoder(&mut reader.amf0_decoder.amf3_decoder);
+    }
+
+    /// Install every codec into the given Writer
+    pub fn install_writer(&self, writer: &mut Writer) {
+        self.install_encoder(&mut writer.amf3_encoder);
+    }
+}
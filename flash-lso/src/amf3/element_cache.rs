@@ -32,6 +32,12 @@ impl<T: PartialEq + Clone + Debug> ElementCache<T> {
         }
     }
 
+    /// Replace the contents of this cache with the contents of another
+    #[inline]
+    pub(crate) fn replace(&self, other: Self) {
+        *self.cache.borrow_mut() = other.cache.into_inner();
+    }
+
     /// Retrieve the item at the given index from the cache
     #[inline]
     pub fn get_element(&self, index: usize) -> Option<T> {
@@ -10,3 +10,5 @@ pub mod read;
 mod type_marker;
 /// Writing of AMF3 data
 pub mod write;
+/// High-level writer that assists in generation of self-referential amf3 data
+pub mod writer;
@@ -1,14 +1,15 @@
-use crate::amf3::custom_encoder::ExternalDecoderFn;
+use crate::amf3::custom_encoder::{ExternalCodecRegistry, ExternalDecoderFn};
 use crate::amf3::type_marker::TypeMarker;
 
 use crate::amf3::length::Length;
-use crate::nom_utils::AMFResult;
+use crate::errors::{Diagnostic, Error};
+use crate::nom_utils::{parse_body_recover, AMFResult, BodyDecoder};
 use crate::types::*;
 use crate::types::{Element, Value};
 use crate::PADDING;
 use enumset::EnumSet;
 use nom::bytes::complete::{tag, take};
-use nom::combinator::{map, map_res};
+use nom::combinator::map;
 use nom::error::{make_error, ErrorKind};
 use nom::lib::std::collections::HashMap;
 use nom::multi::{many_m_n, separated_list0};
@@ -21,6 +22,25 @@ use std::rc::Rc;
 
 const REFERENCE_FLAG: u32 = 0x01;
 
+/// The name of the element holding the raw data of an external object captured by `UnknownExternalPolicy::Capture`
+pub const UNKNOWN_EXTERNAL_DATA: &str = "external_data";
+
+/// How an `AMF3Decoder` handles externalized objects with no registered decoder
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum UnknownExternalPolicy {
+    /// Fail with `Error::UnknownExternalClass`
+    #[default]
+    Error,
+
+    /// Capture the rest of the enclosing buffer as the data of the object
+    ///
+    /// The object is read as a `Value::Custom` with a single `UNKNOWN_EXTERNAL_DATA` element containing a
+    /// `Value::ByteArray` of the captured data, which `AMF3Encoder` writes back verbatim.
+    /// As the length of external data is unknown this only gives usable results if the object is the last value in
+    /// the buffer, when used with `parse_body` the terminating padding is left for the body
+    Capture,
+}
+
 #[cfg(fuzzing)]
 /// For fuzzing
 pub fn fuzz_read_int_signed(i: &[u8]) -> AMFResult<'_, i32> {
@@ -95,7 +115,7 @@ fn read_int(i: &[u8]) -> AMFResult<'_, u32> {
 
 #[cfg(test)]
 mod read_number_tests {
-    use crate::amf3::read::{read_int, read_int_signed};
+    use crate::amf3::read::{is_minimal_u29, read_int, read_int_signed};
 
     #[test]
     fn test_read_1byte_number() {
@@ -128,6 +148,33 @@ mod read_number_tests {
     fn read_neg_number_unsigned() {
         assert_eq!(536870915, read_int(&[192, 128, 128, 1]).unwrap().1);
     }
+
+    #[test]
+    fn test_minimal_u29() {
+        assert!(is_minimal_u29(&[0b00000000]));
+        assert!(is_minimal_u29(&[0b10000001, 0b00000000]));
+        assert!(!is_minimal_u29(&[0b10000000, 0b01111111]));
+        assert!(!is_minimal_u29(&[0b10000000, 0b10000001, 0b00000000]));
+        // 2^21 needs 4 bytes
+        assert!(is_minimal_u29(&[
+            0b10000000, 0b11000000, 0b10000000, 0b00000000
+        ]));
+        assert!(!is_minimal_u29(&[
+            0b10000000, 0b10111111, 0b11111111, 0b11111111
+        ]));
+    }
+}
+
+/// Check that the given bytes of a U29 use the smallest possible encoding
+fn is_minimal_u29(bytes: &[u8]) -> bool {
+    match bytes {
+        [_] => true,
+        // The first byte must contribute some bits to the value
+        [first, _] | [first, _, _] => first & 0b01111111 != 0,
+        // The value must not fit in 3 bytes (21 bits)
+        [first, second, _, _] => first & 0b01111111 != 0 || second & 0b01000000 != 0,
+        _ => false,
+    }
 }
 
 fn read_length(i: &[u8]) -> AMFResult<'_, Length> {
@@ -158,12 +205,38 @@ pub struct AMF3Decoder {
     /// The table used to cache repeated objects
     pub object_reference_table: Vec<Rc<Value>>,
 
-    /// Encoders used for handling externalized types
+    /// Codecs used for handling externalized types, see `ExternalCodecRegistry::install_decoder`
+    pub codecs: ExternalCodecRegistry,
+
+    /// Decoders used for handling externalized types, these are used for classes with no entry in `codecs`
+    #[deprecated(
+        since = "0.6.0",
+        note = "Register an `ExternalCodec` in an `ExternalCodecRegistry` so that the matching encoder is installed too"
+    )]
     pub external_decoders: HashMap<String, ExternalDecoderFn>,
 
     /// Tracks the id of the last object we have read, used to generate `ObjectId`s for `Amf3Reference`
     /// Not an `ObjectId` itself as they don't impl `Default`
     object_id: i64,
+
+    /// If true, data that doesn't conform to the specification will be rejected rather than read on a best-effort basis
+    pub strict: bool,
+
+    /// How externalized objects with no entry in `external_decoders` are handled
+    pub unknown_external: UnknownExternalPolicy,
+
+    /// If true, arrays are read as `Value::MixedArray` rather than `Value::StrictArray` or `Value::ECMAArray`
+    pub mixed_arrays: bool,
+
+    /// The length of the input to the outermost parse call, used to report the offset of errors
+    input_length: usize,
+
+    /// The number of parse calls currently in progress
+    depth: usize,
+
+    /// The number of bytes at the end of the input that belong to the enclosing body, these are left unconsumed when
+    /// capturing unknown external data
+    trailing: usize,
 }
 
 fn parse_element_number(i: &[u8]) -> AMFResult<'_, Rc<Value>> {
@@ -172,6 +245,26 @@ fn parse_element_number(i: &[u8]) -> AMFResult<'_, Rc<Value>> {
 }
 
 impl AMF3Decoder {
+    /// In strict mode, check that the U29 read from `i`, leaving `rest`, used the smallest possible encoding
+    fn check_u29<'a>(&self, i: &'a [u8], rest: &'a [u8]) -> Result<(), Err<Error<'a>>> {
+        if self.strict && !is_minimal_u29(&i[..i.len() - rest.len()]) {
+            return Err(Err::Error(Error::OverlongInteger));
+        }
+        Ok(())
+    }
+
+    fn read_length<'a>(&self, i: &'a [u8]) -> AMFResult<'a, Length> {
+        let (j, length) = read_length(i)?;
+        self.check_u29(i, j)?;
+        Ok((j, length))
+    }
+
+    fn parse_element_int<'a>(&self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
+        let (j, value) = parse_element_int(i)?;
+        self.check_u29(i, j)?;
+        Ok((j, value))
+    }
+
     fn parse_element_string<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
         let (i, s) = map(|i| self.parse_string(i), Value::String)(i)?;
         Ok((i, Rc::new(s)))
@@ -183,10 +276,21 @@ impl AMF3Decoder {
         self.parse_string(i)
     }
 
+    /// Convert bytes read at `i` to a string
+    /// In strict mode invalid UTF-8 is reported as `Error::InvalidString` rather than a generic parse error
+    fn utf8_string<'a>(&self, i: &'a [u8], bytes: Vec<u8>) -> Result<String, Err<Error<'a>>> {
+        String::from_utf8(bytes).map_err(|_| {
+            if self.strict {
+                Err::Error(Error::InvalidString)
+            } else {
+                Err::Error(make_error(i, ErrorKind::Alpha))
+            }
+        })
+    }
+
     fn parse_string<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, String> {
         let (i, bytes) = self.parse_byte_stream(i)?;
-        let bytes_str =
-            String::from_utf8(bytes).map_err(|_| Err::Error(make_error(i, ErrorKind::Alpha)))?;
+        let bytes_str = self.utf8_string(i, bytes)?;
         Ok((i, bytes_str))
     }
 
@@ -211,7 +315,7 @@ impl AMF3Decoder {
         let name_str = if name.is_empty() {
             "".to_string()
         } else {
-            String::from_utf8(name).map_err(|_| Err::Error(make_error(i, ErrorKind::Alpha)))?
+            self.utf8_string(i, name)?
         };
 
         let encoding = (length & 0x03) as u8;
@@ -229,6 +333,10 @@ impl AMF3Decoder {
         let is_external = encoding & 0b1 == 1;
         let is_dynamic = encoding & 0b10 == 0b10;
 
+        if self.strict && is_external && is_dynamic {
+            return Err(Err::Error(Error::DynamicExternal(name_str)));
+        }
+
         let mut attributes = EnumSet::empty();
 
         if is_external {
@@ -254,7 +362,7 @@ impl AMF3Decoder {
         mk_initial: impl FnOnce(&mut Self) -> Value,
         parser: impl FnOnce(&mut Self, &'a [u8], usize, usize) -> AMFResult<'a, Value>,
     ) -> AMFResult<'a, Rc<Value>> {
-        let (i, len) = read_length(i)?;
+        let (i, len) = self.read_length(i)?;
 
         match len {
             Length::Reference(index) => {
@@ -267,7 +375,7 @@ impl AMF3Decoder {
                         Ok((i, Value::Amf3ObjectReference(*id).into()))
                     }
                     Value::Dictionary(id, _, _) => Ok((i, Value::Amf3ObjectReference(*id).into())),
-                    Value::ECMAArray(id, _, _, _) => {
+                    Value::ECMAArray(id, _, _, _) | Value::MixedArray(id, _, _) => {
                         Ok((i, Value::Amf3ObjectReference(*id).into()))
                     }
                     Value::Object(id, _, _) => Ok((i, Value::Amf3ObjectReference(*id).into())),
@@ -308,7 +416,7 @@ impl AMF3Decoder {
     }
 
     fn parse_byte_stream<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Vec<u8>> {
-        let (i, len) = read_length(i)?;
+        let (i, len) = self.read_length(i)?;
 
         match len {
             Length::Size(len) => {
@@ -355,7 +463,9 @@ impl AMF3Decoder {
     }
 
     pub(crate) fn parse_element_object<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
-        let (i, mut length) = read_int(i)?;
+        let (j, mut length) = read_int(i)?;
+        self.check_u29(i, j)?;
+        let i = j;
 
         if length & REFERENCE_FLAG == 0 {
             let len_usize: usize = (length >> 1)
@@ -369,7 +479,9 @@ impl AMF3Decoder {
             return match ref_result.as_ref() {
                 Value::VectorObject(id, _, _, _) => Ok((i, Value::Amf3ObjectReference(*id).into())),
                 Value::Dictionary(id, _, _) => Ok((i, Value::Amf3ObjectReference(*id).into())),
-                Value::ECMAArray(id, _, _, _) => Ok((i, Value::Amf3ObjectReference(*id).into())),
+                Value::ECMAArray(id, _, _, _) | Value::MixedArray(id, _, _) => {
+                    Ok((i, Value::Amf3ObjectReference(*id).into()))
+                }
                 Value::Object(id, _, _) => Ok((i, Value::Amf3ObjectReference(*id).into())),
 
                 _ => Ok((i, Rc::clone(ref_result))),
@@ -399,27 +511,27 @@ impl AMF3Decoder {
         }
 
         let mut elements = Vec::new();
-        let external_elements;
 
         let mut i = i;
         if class_def.attributes.contains(Attribute::External) {
-            return if self.external_decoders.contains_key(&class_def.name) {
-                let decoder = Rc::clone(&self.external_decoders[&class_def.name]);
-                let (j, v) = decoder(i, self)?;
-                external_elements = v;
-                i = j;
-                //TODO: should it be possible to have both dynamic and external together
-                Ok((
-                    i,
-                    Rc::new(Value::Custom(
-                        external_elements,
-                        vec![],
-                        Some(class_def.clone()),
-                    )),
-                ))
+            #[allow(deprecated)]
+            let decoder = self.external_decoders.get(&class_def.name).map(Rc::clone);
+            let (j, external_elements) = if let Some(codec) = self.codecs.get(&class_def.name) {
+                codec.decode(i, self)?
+            } else if let Some(decoder) = decoder {
+                decoder(i, self)?
             } else {
-                Err(Err::Error(make_error(i, ErrorKind::Tag)))
+                return self.parse_unknown_external(i, class_def);
             };
+            // Dynamic external traits are only rejected in strict mode, see `parse_class_def`
+            return Ok((
+                j,
+                Rc::new(Value::Custom(
+                    external_elements,
+                    vec![],
+                    Some(class_def.clone()),
+                )),
+            ));
         }
 
         if class_def.attributes.contains(Attribute::Dynamic) {
@@ -429,8 +541,7 @@ impl AMF3Decoder {
             // Read dynamic
             let (mut j, mut attr) = self.parse_byte_stream(j)?;
             while !attr.is_empty() {
-                let attr_str = String::from_utf8(attr)
-                    .map_err(|_| Err::Error(make_error(i, ErrorKind::Alpha)))?;
+                let attr_str = self.utf8_string(i, attr)?;
                 let (k, val) = self.parse_single_element(j)?;
                 elements.push(Element {
                     name: attr_str,
@@ -472,6 +583,34 @@ impl AMF3Decoder {
         ))
     }
 
+    fn parse_unknown_external<'a>(
+        &mut self,
+        i: &'a [u8],
+        class_def: ClassDefinition,
+    ) -> AMFResult<'a, Rc<Value>> {
+        match self.unknown_external {
+            // A failure, so that the error isn't discarded by the combinators reading the enclosing body
+            UnknownExternalPolicy::Error => Err(Err::Failure(Error::UnknownExternalClass(
+                class_def.name,
+                self.input_length.saturating_sub(i.len()),
+            ))),
+            UnknownExternalPolicy::Capture => {
+                let (data, i) = i.split_at(i.len().saturating_sub(self.trailing));
+                Ok((
+                    i,
+                    Rc::new(Value::Custom(
+                        vec![Element::new(
+                            UNKNOWN_EXTERNAL_DATA,
+                            Rc::new(Value::ByteArray(data.to_vec())),
+                        )],
+                        vec![],
+                        Some(class_def),
+                    )),
+                ))
+            }
+        }
+    }
+
     fn parse_element_byte_array<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
         self.parse_reference_or_val(
             i,
@@ -602,6 +741,9 @@ impl AMF3Decoder {
                         )
                     };
 
+                    if this.mixed_arrays {
+                        return Ok((i, Value::MixedArray(*id, elements, Vec::new())));
+                    }
                     return Ok((i, Value::StrictArray(*id, elements)));
                 }
 
@@ -610,8 +752,7 @@ impl AMF3Decoder {
                 let mut i = i;
                 while !key.is_empty() {
                     let (j, e) = this.parse_single_element(i)?;
-                    let key_str = String::from_utf8(key)
-                        .map_err(|_| Err::Error(make_error(i, ErrorKind::Alpha)))?;
+                    let key_str = this.utf8_string(i, key)?;
 
                     elements.push(Element {
                         name: key_str,
@@ -626,7 +767,7 @@ impl AMF3Decoder {
                 let (i, el) =
                     many_m_n(length_usize, length_usize, |i| this.parse_single_element(i))(i)?;
 
-                let elements_len = elements.len() as u32;
+                let length = array_length(&el, &elements);
 
                 let id = if let Value::ECMAArray(id, _, _, _) =
                     this.object_reference_table.get(ofi).unwrap().as_ref()
@@ -639,7 +780,10 @@ impl AMF3Decoder {
                     )
                 };
 
-                Ok((i, Value::ECMAArray(*id, el, elements, elements_len)))
+                if this.mixed_arrays {
+                    return Ok((i, Value::MixedArray(*id, el, elements)));
+                }
+                Ok((i, Value::ECMAArray(*id, el, elements, length)))
             },
         )
     }
@@ -698,9 +842,10 @@ impl AMF3Decoder {
         self.parse_reference_or_val(
             i,
             |_| Value::XML("".to_string(), false),
-            |_this, i, len, _| {
-                let (i, data) = map_res(take(len as u32), std::str::from_utf8)(i)?;
-                Ok((i, Value::XML(data.into(), string)))
+            |this, i, len, _| {
+                let (j, data) = take(len as u32)(i)?;
+                let data = this.utf8_string(i, data.to_vec())?;
+                Ok((j, Value::XML(data, string)))
             },
         )
     }
@@ -710,12 +855,27 @@ impl AMF3Decoder {
         if let Ok(type_) = TypeMarker::try_from(type_) {
             Ok((i, type_))
         } else {
-            Err(Err::Error(crate::errors::Error::UnsupportedType(type_)))
+            Err(Err::Error(Error::UnsupportedType(type_)))
         }
     }
 
     /// Parse a single AMF3 element from the input
     pub fn parse_single_element<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
+        self.with_input(i, |this, i| this.parse_value(i))
+    }
+
+    /// Run `f` on `i`, tracking the length of the input if this is the outermost parse call
+    fn with_input<'a, T>(&mut self, i: &'a [u8], f: impl FnOnce(&mut Self, &'a [u8]) -> T) -> T {
+        if self.depth == 0 {
+            self.input_length = i.len();
+        }
+        self.depth += 1;
+        let result = f(self, i);
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_value<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
         let (i, type_) = self.read_type_marker(i)?;
 
         match type_ {
@@ -723,7 +883,7 @@ impl AMF3Decoder {
             TypeMarker::Null => Ok((i, Rc::new(Value::Null))),
             TypeMarker::False => Ok((i, Rc::new(Value::Bool(false)))),
             TypeMarker::True => Ok((i, Rc::new(Value::Bool(true)))),
-            TypeMarker::Integer => parse_element_int(i),
+            TypeMarker::Integer => self.parse_element_int(i),
             TypeMarker::Number => parse_element_number(i),
             TypeMarker::String => self.parse_element_string(i),
             TypeMarker::Xml => self.parse_element_xml(i, false),
@@ -754,8 +914,64 @@ impl AMF3Decoder {
 
     /// Parse an AMF3 body from a slice into a list of elements
     pub fn parse_body<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Vec<Element>> {
-        let (i, elements) = separated_list0(tag(PADDING), |i| self.parse_element(i))(i)?;
+        self.with_body_input(i, |this, i| {
+            let (i, elements) = separated_list0(tag(PADDING), |i| this.parse_element(i))(i)?;
+            let (i, _) = tag(PADDING)(i)?;
+            Ok((i, elements))
+        })
+    }
+
+    /// Run `f` on a body in `i`, reserving the terminating padding of the body
+    fn with_body_input<'a, T>(
+        &mut self,
+        i: &'a [u8],
+        f: impl FnOnce(&mut Self, &'a [u8]) -> T,
+    ) -> T {
+        let trailing = self.trailing;
+        self.trailing = PADDING.len();
+        let result = self.with_input(i, f);
+        self.trailing = trailing;
+        result
+    }
+
+    /// Parse an AMF3 body from a slice into a list of elements, skipping over any that can't be decoded
+    ///
+    /// Returns every element that could be read, along with a `Diagnostic` for each skipped region of the input
+    pub fn parse_body_recover(&mut self, i: &[u8]) -> (Vec<Element>, Vec<Diagnostic>) {
+        self.with_body_input(i, parse_body_recover)
+    }
+}
+
+/// The lengths of the reference tables and the last object id of an `AMF3Decoder`
+pub(crate) struct DecoderState {
+    strings: usize,
+    traits: usize,
+    objects: usize,
+    object_id: i64,
+}
+
+impl BodyDecoder for AMF3Decoder {
+    type State = DecoderState;
+
+    fn save_state(&self) -> Self::State {
+        DecoderState {
+            strings: self.string_reference_table.len(),
+            traits: self.trait_reference_table.len(),
+            objects: self.object_reference_table.len(),
+            object_id: self.object_id,
+        }
+    }
+
+    fn restore_state(&mut self, state: &Self::State) {
+        self.string_reference_table.truncate(state.strings);
+        self.trait_reference_table.truncate(state.traits);
+        self.object_reference_table.truncate(state.objects);
+        self.object_id = state.object_id;
+    }
+
+    fn parse_root_element<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Element> {
+        let (i, element) = self.parse_element(i)?;
         let (i, _) = tag(PADDING)(i)?;
-        Ok((i, elements))
+        Ok((i, element))
     }
 }
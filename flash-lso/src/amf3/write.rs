@@ -1,16 +1,17 @@
 //! Handles encoding AMF3
 
-use crate::amf3::custom_encoder::CustomEncoder;
+use crate::amf3::custom_encoder::{CustomEncoder, ExternalCodecRegistry};
 use crate::amf3::element_cache::ElementCache;
 use crate::amf3::length::Length;
+use crate::amf3::read::UNKNOWN_EXTERNAL_DATA;
 use crate::amf3::type_marker::TypeMarker;
 use crate::types::{Attribute, ClassDefinition, Element, ObjectId, Value};
-use crate::write::WriteExt;
+use crate::write::{ByteCounter, WriteExt};
 use crate::PADDING;
 use std::cell::RefCell;
 use std::collections::{BTreeMap, HashMap};
-use std::io::Result;
 use std::io::Write;
+use std::io::{Error, ErrorKind, Result};
 use std::ops::Deref;
 use std::rc::Rc;
 
@@ -26,13 +27,65 @@ pub struct AMF3Encoder {
     /// The table used to cache repeated objects
     object_reference_table: ElementCache<Value>,
 
-    /// Encoders used for handling externalized types
+    /// Codecs used for handling externalized types, see `ExternalCodecRegistry::install_encoder`
+    pub codecs: ExternalCodecRegistry,
+
+    /// Encoders used for handling externalized types, these are used for classes with no entry in `codecs`
+    #[deprecated(
+        since = "0.6.0",
+        note = "Register an `ExternalCodec` in an `ExternalCodecRegistry` so that the matching decoder is installed too"
+    )]
     pub external_encoders: HashMap<String, Box<dyn CustomEncoder>>,
 
     object_id_to_reference: RefCell<BTreeMap<ObjectId, (TypeMarker, usize)>>,
 }
 
+/// A copy of the reference tables of an `AMF3Encoder`
+struct ReferenceTables {
+    strings: ElementCache<Vec<u8>>,
+    traits: Vec<ClassDefinition>,
+    objects: ElementCache<Value>,
+    object_ids: BTreeMap<ObjectId, (TypeMarker, usize)>,
+}
+
 impl AMF3Encoder {
+    fn save_reference_tables(&self) -> ReferenceTables {
+        ReferenceTables {
+            strings: self.string_reference_table.clone(),
+            traits: self.trait_reference_table.borrow().clone(),
+            objects: self.object_reference_table.clone(),
+            object_ids: self.object_id_to_reference.borrow().clone(),
+        }
+    }
+
+    fn restore_reference_tables(&self, tables: ReferenceTables) {
+        self.string_reference_table.replace(tables.strings);
+        *self.trait_reference_table.borrow_mut() = tables.traits;
+        self.object_reference_table.replace(tables.objects);
+        *self.object_id_to_reference.borrow_mut() = tables.object_ids;
+    }
+
+    /// Get the number of bytes needed to encode the given value with this encoder in its current state
+    ///
+    /// This takes into account any strings, traits or objects that would be written as references,
+    /// the reference tables are left unchanged. Fails if the value can't be written
+    pub fn value_length(&self, value: &Value) -> Result<usize> {
+        let tables = self.save_reference_tables();
+        let mut counter = ByteCounter::default();
+        let result = self.write_value(&mut counter, value);
+        self.restore_reference_tables(tables);
+        result.map(|_| counter.0)
+    }
+
+    /// Get the number of bytes needed to encode the given elements as an AMF3 body, see `value_length`
+    pub fn body_length(&self, elements: &[Element]) -> Result<usize> {
+        let tables = self.save_reference_tables();
+        let mut counter = ByteCounter::default();
+        let result = self.write_body(&mut counter, elements);
+        self.restore_reference_tables(tables);
+        result.map(|_| counter.0)
+    }
+
     #[allow(clippy::unusual_byte_groupings)]
     pub(crate) fn write_int<'a, 'b: 'a, W: Write + 'a>(
         &self,
@@ -292,6 +345,35 @@ impl AMF3Encoder {
         Ok(())
     }
 
+    fn write_external_data<'a, 'b: 'a, W: Write + 'a>(
+        &'a self,
+        writer: &mut W,
+        elements: &'b [Element],
+        def: &'b ClassDefinition,
+    ) -> Result<()> {
+        if let Some(codec) = self.codecs.get(&def.name) {
+            return writer.write_all(&codec.encode(elements, &Some(def.clone()), self)?);
+        }
+        #[allow(deprecated)]
+        if let Some(encoder) = self.external_encoders.get(&def.name) {
+            return writer.write_all(&encoder.encode(elements, &Some(def.clone()), self));
+        }
+
+        // Data captured by `UnknownExternalPolicy::Capture` is written back verbatim
+        match elements {
+            [Element { name, value }] if name == UNKNOWN_EXTERNAL_DATA => {
+                if let Value::ByteArray(data) = value.deref() {
+                    return writer.write_all(data);
+                }
+            }
+            _ => {}
+        }
+        Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("No encoder for externalized class '{}'", def.name),
+        ))
+    }
+
     //TODO: conds should be common somehwere
     fn write_trait_reference<'a, 'b: 'a, W: Write + 'a>(
         &'a self,
@@ -306,15 +388,7 @@ impl AMF3Encoder {
 
         self.write_int(writer, size as i32)?;
         if def.attributes.contains(Attribute::External) {
-            if let Some(encoder) = self.external_encoders.get(&def.name) {
-                writer.write_all(&encoder.encode(
-                    custom_props.unwrap(),
-                    &Some(def.clone()),
-                    self,
-                ))?;
-            } else {
-                unimplemented!();
-            }
+            self.write_external_data(writer, custom_props.unwrap_or_default(), def)?;
         }
 
         if !def.attributes.contains(Attribute::External) {
@@ -385,15 +459,7 @@ impl AMF3Encoder {
         self.write_int(writer, size as i32)?;
         self.write_class_definition(writer, def)?;
         if def.attributes.contains(Attribute::External) {
-            if let Some(encoder) = self.external_encoders.get(&def.name) {
-                writer.write_all(&encoder.encode(
-                    custom_props.unwrap(),
-                    &Some(def.clone()),
-                    self,
-                ))?;
-            } else {
-                unimplemented!();
-            }
+            self.write_external_data(writer, custom_props.unwrap_or_default(), def)?;
         }
         if !def.attributes.contains(Attribute::External) {
             if def.attributes.is_empty() {
@@ -454,10 +520,10 @@ impl AMF3Encoder {
             self.write_object_reference(writer, had_object.as_position().unwrap() as u32)?;
         }
         if !had_object.is_reference() {
-            if has_trait.is_some() {
+            if let Some(has_trait) = has_trait {
                 self.write_trait_reference(
                     writer,
-                    has_trait.unwrap() as u32,
+                    has_trait as u32,
                     children,
                     custom_props,
                     &def2,
@@ -607,7 +673,7 @@ impl AMF3Encoder {
             }
             Value::Null => self.write_null_element(writer),
             Value::Undefined => self.write_undefined_element(writer),
-            Value::ECMAArray(id, dense, elements, _) => {
+            Value::ECMAArray(id, dense, elements, _) | Value::MixedArray(id, dense, elements) => {
                 self.object_reference_table.store(s.clone());
                 if let Length::Reference(r) = self.object_reference_table.to_length(s.clone(), 0) {
                     self.object_id_to_reference
@@ -0,0 +1,45 @@
+use std::{collections::BTreeMap, rc::Rc};
+
+use crate::types::{AMFVersion, Element, Lso, ObjectId, Value};
+
+use super::{CacheKey, ObjWriter};
+
+/// A writer for Amf3 encoded data
+#[derive(Default)]
+pub struct Amf3Writer {
+    /// The elements present at the root level
+    pub(crate) elements: Vec<Element>,
+
+    /// The id of the last object created
+    pub(crate) object_id: i64,
+
+    /// The reference cache, allows writing self-referential data
+    pub(crate) cache: BTreeMap<CacheKey, ObjectId>,
+}
+
+impl ObjWriter for Amf3Writer {
+    fn add_element(&mut self, name: &str, v: Value) {
+        self.elements.push(Element::new(name, Rc::new(v)))
+    }
+
+    fn make_id(&mut self) -> ObjectId {
+        // Ids are allocated in the same order as `AMF3Decoder`, so that reading the output produces the same ids
+        self.object_id += 1;
+        ObjectId(self.object_id)
+    }
+
+    fn cache_get(&mut self, cache_key: &CacheKey) -> Option<ObjectId> {
+        self.cache.get(cache_key).copied()
+    }
+
+    fn cache_add(&mut self, cache_key: CacheKey, id: ObjectId) {
+        self.cache.insert(cache_key, id);
+    }
+}
+
+impl Amf3Writer {
+    /// Produce an `Lso` with the given name
+    pub fn commit_lso(self, name: &str) -> Lso {
+        Lso::new(self.elements, name, AMFVersion::AMF3)
+    }
+}
@@ -0,0 +1,69 @@
+use std::rc::Rc;
+
+use crate::types::{array_length, Element, ObjectId, Value};
+
+use super::{CacheKey, ObjWriter};
+
+/// A writer for encoding the contents of an array
+///
+/// Named elements are written to the associative portion of the array, values added with `push` to the dense portion
+pub struct ArrayWriter<'a> {
+    /// The id of this array
+    pub(crate) id: ObjectId,
+
+    /// The dense portion of this array
+    pub(crate) dense: Vec<Rc<Value>>,
+
+    /// The associative portion of this array
+    pub(crate) elements: Vec<Element>,
+
+    /// The parent of this writer
+    pub(crate) parent: &'a mut dyn ObjWriter,
+}
+
+impl ObjWriter for ArrayWriter<'_> {
+    fn add_element(&mut self, name: &str, v: Value) {
+        self.elements.push(Element::new(name, Rc::new(v)));
+    }
+
+    fn make_id(&mut self) -> ObjectId {
+        self.parent.make_id()
+    }
+
+    fn cache_get(&mut self, cache_key: &CacheKey) -> Option<ObjectId> {
+        self.parent.cache_get(cache_key)
+    }
+
+    fn cache_add(&mut self, cache_key: CacheKey, id: ObjectId) {
+        self.parent.cache_add(cache_key, id);
+    }
+}
+
+impl<'a> ArrayWriter<'a> {
+    /// Add a value to the end of the dense portion of this array
+    pub fn push(&mut self, v: Value) {
+        self.dense.push(Rc::new(v));
+    }
+
+    /// Finalize this array, adding it to it's parent
+    /// If this is not called, the array will not be added
+    pub fn commit<T: AsRef<str>>(self, name: T) {
+        let (parent, value) = self.finish();
+        parent.add_element(name.as_ref(), value);
+    }
+
+    /// Finalize this array, returning it as a value, see the module documentation
+    pub fn into_value(self) -> Value {
+        self.finish().1
+    }
+
+    fn finish(self) -> (&'a mut dyn ObjWriter, Value) {
+        let value = if self.elements.is_empty() {
+            Value::StrictArray(self.id, self.dense)
+        } else {
+            let length = array_length(&self.dense, &self.elements);
+            Value::ECMAArray(self.id, self.dense, self.elements, length)
+        };
+        (self.parent, value)
+    }
+}
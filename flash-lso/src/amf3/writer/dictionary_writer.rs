@@ -0,0 +1,66 @@
+use std::rc::Rc;
+
+use crate::types::{ObjectId, Value};
+
+use super::{CacheKey, ObjWriter};
+
+/// A writer for encoding the contents of a dictionary
+///
+/// Named elements are written as entries with a string key, entries with any other key are added with `insert`
+pub struct DictionaryWriter<'a> {
+    /// The id of this dictionary
+    pub(crate) id: ObjectId,
+
+    /// The entries of this dictionary
+    pub(crate) entries: Vec<(Rc<Value>, Rc<Value>)>,
+
+    /// If the keys of this dictionary are weakly referenced
+    pub(crate) weak_keys: bool,
+
+    /// The parent of this writer
+    pub(crate) parent: &'a mut dyn ObjWriter,
+}
+
+impl ObjWriter for DictionaryWriter<'_> {
+    fn add_element(&mut self, name: &str, v: Value) {
+        self.insert(Value::String(name.to_string()), v);
+    }
+
+    fn make_id(&mut self) -> ObjectId {
+        self.parent.make_id()
+    }
+
+    fn cache_get(&mut self, cache_key: &CacheKey) -> Option<ObjectId> {
+        self.parent.cache_get(cache_key)
+    }
+
+    fn cache_add(&mut self, cache_key: CacheKey, id: ObjectId) {
+        self.parent.cache_add(cache_key, id);
+    }
+}
+
+impl<'a> DictionaryWriter<'a> {
+    /// Add an entry to this dictionary
+    pub fn insert(&mut self, key: Value, value: Value) {
+        self.entries.push((Rc::new(key), Rc::new(value)));
+    }
+
+    /// Finalize this dictionary, adding it to it's parent
+    /// If this is not called, the dictionary will not be added
+    pub fn commit<T: AsRef<str>>(self, name: T) {
+        let (parent, value) = self.finish();
+        parent.add_element(name.as_ref(), value);
+    }
+
+    /// Finalize this dictionary, returning it as a value, see the module documentation
+    pub fn into_value(self) -> Value {
+        self.finish().1
+    }
+
+    fn finish(self) -> (&'a mut dyn ObjWriter, Value) {
+        (
+            self.parent,
+            Value::Dictionary(self.id, self.entries, self.weak_keys),
+        )
+    }
+}
@@ -0,0 +1,19 @@
+//! Writers for building amf3 data
+//!
+//! Child writers are finalized with `commit`, which adds the value to the parent under a name, or `into_value`, which
+//! returns it for places that don't have a name, such as the dense portion of an array or the keys of a dictionary
+
+mod amf3_writer;
+mod array_writer;
+mod dictionary_writer;
+mod obj_writer;
+mod object_writer;
+mod vector_writer;
+
+pub use crate::amf0::writer::CacheKey;
+pub use amf3_writer::Amf3Writer;
+pub use array_writer::ArrayWriter;
+pub use dictionary_writer::DictionaryWriter;
+pub use obj_writer::ObjWriter;
+pub use object_writer::ObjectWriter;
+pub use vector_writer::VectorWriter;
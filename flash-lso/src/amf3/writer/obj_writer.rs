@@ -0,0 +1,227 @@
+use crate::types::{Attribute, ClassDefinition, Element, ObjectId, Value};
+
+use super::{ArrayWriter, CacheKey, DictionaryWriter, ObjectWriter, VectorWriter};
+
+/// Get the id for a new object with the given `cache_key`
+///
+/// Returns `Ok` with a newly allocated id if the key is unique, or `Err` with the existing id if it has been seen before
+fn allocate<W: ObjWriter + ?Sized>(
+    writer: &mut W,
+    cache_key: CacheKey,
+) -> Result<ObjectId, ObjectId> {
+    if let Some(existing_id) = writer.cache_get(&cache_key) {
+        Err(existing_id)
+    } else {
+        let id = writer.make_id();
+        writer.cache_add(cache_key, id);
+        Ok(id)
+    }
+}
+
+/// A trait of common functions between writers
+pub trait ObjWriter {
+    /// Add an element to this object
+    fn add_element(&mut self, name: &str, v: Value);
+
+    /// Allocate the id of a new object, from the root
+    fn make_id(&mut self) -> ObjectId;
+
+    /// Retrieve an id from the cache
+    fn cache_get(&mut self, cache_key: &CacheKey) -> Option<ObjectId>;
+
+    /// Add an id to the reference cache
+    fn cache_add(&mut self, cache_key: CacheKey, id: ObjectId);
+
+    /// Create a writer that can serialize an anonymous object
+    ///
+    /// If an object with the same `cache_key` has already been written, then this will return `None` for the Writer and the existing id
+    /// If this key is unique, then both a Writer and the id of the new object will be returned
+    fn object(&mut self, cache_key: CacheKey) -> (Option<ObjectWriter<'_>>, ObjectId)
+    where
+        Self: Sized,
+    {
+        self.typed_object(
+            cache_key,
+            ClassDefinition {
+                name: String::new(),
+                attributes: Attribute::Dynamic.into(),
+                static_properties: Vec::new(),
+            },
+        )
+    }
+
+    /// Create a writer that can serialize an object of the class described by `class_def`
+    ///
+    /// Elements named in the static properties of `class_def` are written as sealed members, if the class is dynamic
+    /// any other elements are written as dynamic members
+    ///
+    /// If an object with the same `cache_key` has already been written, then this will return `None` for the Writer and the existing id
+    /// If this key is unique, then both a Writer and the id of the new object will be returned
+    fn typed_object(
+        &mut self,
+        cache_key: CacheKey,
+        class_def: ClassDefinition,
+    ) -> (Option<ObjectWriter<'_>>, ObjectId)
+    where
+        Self: Sized,
+    {
+        match allocate(self, cache_key) {
+            Ok(id) => (
+                Some(ObjectWriter {
+                    id,
+                    elements: Vec::new(),
+                    class_def,
+                    parent: self,
+                }),
+                id,
+            ),
+            Err(id) => (None, id),
+        }
+    }
+
+    /// Create a writer that can serialize an array
+    ///
+    /// If an object with the same `cache_key` has already been written, then this will return `None` for the Writer and the existing id
+    /// If this key is unique, then both a Writer and the id of the new array will be returned
+    fn array(&mut self, cache_key: CacheKey) -> (Option<ArrayWriter<'_>>, ObjectId)
+    where
+        Self: Sized,
+    {
+        match allocate(self, cache_key) {
+            Ok(id) => (
+                Some(ArrayWriter {
+                    id,
+                    dense: Vec::new(),
+                    elements: Vec::new(),
+                    parent: self,
+                }),
+                id,
+            ),
+            Err(id) => (None, id),
+        }
+    }
+
+    /// Create a writer that can serialize a dictionary
+    ///
+    /// If an object with the same `cache_key` has already been written, then this will return `None` for the Writer and the existing id
+    /// If this key is unique, then both a Writer and the id of the new dictionary will be returned
+    fn dictionary(
+        &mut self,
+        cache_key: CacheKey,
+        weak_keys: bool,
+    ) -> (Option<DictionaryWriter<'_>>, ObjectId)
+    where
+        Self: Sized,
+    {
+        match allocate(self, cache_key) {
+            Ok(id) => (
+                Some(DictionaryWriter {
+                    id,
+                    entries: Vec::new(),
+                    weak_keys,
+                    parent: self,
+                }),
+                id,
+            ),
+            Err(id) => (None, id),
+        }
+    }
+
+    /// Create a writer that can serialize a vector of objects of the type `type_name`
+    ///
+    /// If an object with the same `cache_key` has already been written, then this will return `None` for the Writer and the existing id
+    /// If this key is unique, then both a Writer and the id of the new vector will be returned
+    fn object_vector(
+        &mut self,
+        cache_key: CacheKey,
+        type_name: &str,
+        fixed_length: bool,
+    ) -> (Option<VectorWriter<'_>>, ObjectId)
+    where
+        Self: Sized,
+    {
+        match allocate(self, cache_key) {
+            Ok(id) => (
+                Some(VectorWriter {
+                    id,
+                    items: Vec::new(),
+                    type_name: type_name.to_string(),
+                    fixed_length,
+                    parent: self,
+                }),
+                id,
+            ),
+            Err(id) => (None, id),
+        }
+    }
+
+    /// Write a string
+    fn string(&mut self, name: &str, s: &str) {
+        self.add_element(name, Value::String(s.to_string()));
+    }
+
+    /// Write an integer
+    fn integer(&mut self, name: &str, i: i32) {
+        self.add_element(name, Value::Integer(i));
+    }
+
+    /// Write a number
+    fn number(&mut self, name: &str, n: f64) {
+        self.add_element(name, Value::Number(n));
+    }
+
+    /// Write a reference to an object, array, dictionary or vector that has already been written
+    fn reference(&mut self, name: &str, id: ObjectId) {
+        self.add_element(name, Value::Amf3ObjectReference(id));
+    }
+
+    /// Write an undefined
+    fn undefined(&mut self, name: &str) {
+        self.add_element(name, Value::Undefined);
+    }
+
+    /// Write a null
+    fn null(&mut self, name: &str) {
+        self.add_element(name, Value::Null);
+    }
+
+    /// Write a bool
+    fn bool(&mut self, name: &str, v: bool) {
+        self.add_element(name, Value::Bool(v));
+    }
+
+    /// Write a date
+    fn date(&mut self, name: &str, ms: f64) {
+        self.add_element(name, Value::Date(ms, None));
+    }
+
+    /// Write a XML
+    fn xml(&mut self, name: &str, v: &str, s: bool) {
+        self.add_element(name, Value::XML(v.to_string(), s));
+    }
+
+    /// Write a byte array
+    fn byte_array(&mut self, name: &str, bytes: &[u8]) {
+        self.add_element(name, Value::ByteArray(bytes.to_vec()));
+    }
+
+    /// Write a vector of integers
+    fn vector_int(&mut self, name: &str, items: &[i32], fixed_length: bool) {
+        self.add_element(name, Value::VectorInt(items.to_vec(), fixed_length));
+    }
+
+    /// Write a vector of unsigned integers
+    fn vector_uint(&mut self, name: &str, items: &[u32], fixed_length: bool) {
+        self.add_element(name, Value::VectorUInt(items.to_vec(), fixed_length));
+    }
+
+    /// Write a vector of numbers
+    fn vector_double(&mut self, name: &str, items: &[f64], fixed_length: bool) {
+        self.add_element(name, Value::VectorDouble(items.to_vec(), fixed_length));
+    }
+
+    /// Write an externalized object, the elements are encoded by the `CustomEncoder` registered for the class
+    fn custom(&mut self, name: &str, elements: Vec<Element>, class_def: ClassDefinition) {
+        self.add_element(name, Value::Custom(elements, Vec::new(), Some(class_def)));
+    }
+}
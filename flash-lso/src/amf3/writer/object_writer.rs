@@ -0,0 +1,83 @@
+use std::rc::Rc;
+
+use crate::errors::Error;
+use crate::types::{Attribute, ClassDefinition, Element, ObjectId, Value};
+
+use super::{CacheKey, ObjWriter};
+
+/// A writer for encoding the contents of a child object
+pub struct ObjectWriter<'a> {
+    /// The id of this object
+    pub(crate) id: ObjectId,
+
+    /// The elements of this object
+    pub(crate) elements: Vec<Element>,
+
+    /// The class of this object
+    pub(crate) class_def: ClassDefinition,
+
+    /// The parent of this writer
+    pub(crate) parent: &'a mut dyn ObjWriter,
+}
+
+impl ObjWriter for ObjectWriter<'_> {
+    fn add_element(&mut self, name: &str, v: Value) {
+        self.elements.push(Element::new(name, Rc::new(v)));
+    }
+
+    fn make_id(&mut self) -> ObjectId {
+        self.parent.make_id()
+    }
+
+    fn cache_get(&mut self, cache_key: &CacheKey) -> Option<ObjectId> {
+        self.parent.cache_get(cache_key)
+    }
+
+    fn cache_add(&mut self, cache_key: CacheKey, id: ObjectId) {
+        self.parent.cache_add(cache_key, id);
+    }
+}
+
+impl<'a> ObjectWriter<'a> {
+    /// Finalize this object, adding it to it's parent
+    /// If this is not called, the object will not be added
+    ///
+    /// Static properties that weren't written are `undefined`. Fails if the class isn't dynamic and a member that isn't
+    /// a static property was written, the object is not added in that case
+    pub fn commit<T: AsRef<str>>(self, name: T) -> Result<(), Error<'static>> {
+        let (parent, value) = self.finish()?;
+        parent.add_element(name.as_ref(), value);
+        Ok(())
+    }
+
+    /// Finalize this object, returning it as a value, see `commit` and the module documentation
+    pub fn into_value(self) -> Result<Value, Error<'static>> {
+        Ok(self.finish()?.1)
+    }
+
+    fn finish(self) -> Result<(&'a mut dyn ObjWriter, Value), Error<'static>> {
+        let mut elements = self.elements;
+
+        // Static properties are read in the order of the class definition, every one must be present
+        let mut ordered = Vec::with_capacity(elements.len());
+        for property in &self.class_def.static_properties {
+            match elements.iter().position(|e| &e.name == property) {
+                Some(index) => ordered.push(elements.remove(index)),
+                None => ordered.push(Element::new(property.as_str(), Rc::new(Value::Undefined))),
+            }
+        }
+        if self.class_def.attributes.contains(Attribute::Dynamic) {
+            ordered.extend(elements);
+        } else if let Some(undeclared) = elements.into_iter().next() {
+            return Err(Error::UndeclaredMember(
+                self.class_def.name,
+                undeclared.name,
+            ));
+        }
+
+        Ok((
+            self.parent,
+            Value::Object(self.id, ordered, Some(self.class_def)),
+        ))
+    }
+}
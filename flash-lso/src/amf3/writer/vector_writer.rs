@@ -0,0 +1,69 @@
+use std::rc::Rc;
+
+use crate::types::{ObjectId, Value};
+
+use super::{CacheKey, ObjWriter};
+
+/// A writer for encoding the contents of a vector of objects
+///
+/// Vectors have no names, so the name of any element added is ignored and the value is appended to the vector
+pub struct VectorWriter<'a> {
+    /// The id of this vector
+    pub(crate) id: ObjectId,
+
+    /// The items in this vector
+    pub(crate) items: Vec<Rc<Value>>,
+
+    /// The name of the type of the items in this vector
+    pub(crate) type_name: String,
+
+    /// If this vector is of a fixed length
+    pub(crate) fixed_length: bool,
+
+    /// The parent of this writer
+    pub(crate) parent: &'a mut dyn ObjWriter,
+}
+
+impl ObjWriter for VectorWriter<'_> {
+    fn add_element(&mut self, _name: &str, v: Value) {
+        self.push(v);
+    }
+
+    fn make_id(&mut self) -> ObjectId {
+        self.parent.make_id()
+    }
+
+    fn cache_get(&mut self, cache_key: &CacheKey) -> Option<ObjectId> {
+        self.parent.cache_get(cache_key)
+    }
+
+    fn cache_add(&mut self, cache_key: CacheKey, id: ObjectId) {
+        self.parent.cache_add(cache_key, id);
+    }
+}
+
+impl<'a> VectorWriter<'a> {
+    /// Add a value to the end of this vector
+    pub fn push(&mut self, v: Value) {
+        self.items.push(Rc::new(v));
+    }
+
+    /// Finalize this vector, adding it to it's parent
+    /// If this is not called, the vector will not be added
+    pub fn commit<T: AsRef<str>>(self, name: T) {
+        let (parent, value) = self.finish();
+        parent.add_element(name.as_ref(), value);
+    }
+
+    /// Finalize this vector, returning it as a value, see the module documentation
+    pub fn into_value(self) -> Value {
+        self.finish().1
+    }
+
+    fn finish(self) -> (&'a mut dyn ObjWriter, Value) {
+        (
+            self.parent,
+            Value::VectorObject(self.id, self.items, self.type_name, self.fixed_length),
+        )
+    }
+}
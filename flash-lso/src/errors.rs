@@ -3,7 +3,6 @@ use thiserror::Error;
 
 // Allow the Nom variant to be large
 #[allow(variant_size_differences)]
-
 /// Enum for representing decoding errors
 #[derive(Error, Debug, Clone, Eq, PartialEq)]
 pub enum Error<'a> {
@@ -27,10 +26,77 @@ pub enum Error<'a> {
     #[error("Packet has too many headers or messages")]
     PacketTooLarge,
 
+    /// FLV tag data is larger than the 24 bit size field allows
+    #[error("Tag data is too large")]
+    TagTooLarge,
+
+    /// An RTMP command or shared object `SendMessage` event without a string name, or a command without a
+    /// numeric transaction id
+    #[error("Invalid RTMP command")]
+    InvalidCommand,
+
     /// Unable to find an object in the reference table
     #[error("Object not in reference table")]
     ObjectMissingFromReferenceTable(u64),
 
+    /// The data is shorter than the length declared in the LSO header
+    /// Format is (declared length, actual length), neither includes the header version or length fields
+    #[error("Truncated file, header declares {0} bytes but only {1} are present")]
+    TruncatedFile(u32, usize),
+
+    /// The data is longer than the length declared in the LSO header
+    /// Format is (declared length, actual length), neither includes the header version or length fields
+    #[error("Trailing data, header declares {0} bytes but {1} are present")]
+    TrailingData(u32, usize),
+
+    /// A type marker that is reserved and not supported by any AMF implementation (strict mode only)
+    #[error("Reserved type marker {0}")]
+    ReservedType(u8),
+
+    /// A U29 integer that was not encoded using the smallest possible number of bytes (strict mode only)
+    #[error("Over-long U29 encoding")]
+    OverlongInteger,
+
+    /// A class definition that is both externalizable and dynamic (strict mode only)
+    #[error("Class '{0}' is both externalizable and dynamic")]
+    DynamicExternal(String),
+
+    /// An externalized object with no registered decoder
+    /// Format is (class name, offset of the external data in the input to the decoder)
+    #[error("Unknown external class '{0}' at offset {1}")]
+    UnknownExternalClass(String, usize),
+
+    /// A member that isn't a static property of a class that isn't dynamic, so can't be written
+    /// Format is (class name, member name)
+    #[error("Member '{1}' is not declared by sealed class '{0}'")]
+    UndeclaredMember(String, String),
+
+    /// XML content with elements nested deeper than the given limit
+    #[error("XML elements are nested deeper than {0} levels")]
+    XmlTooDeep(usize),
+
+    /// A value that can't be converted to the requested type
+    /// Format is a description of the expected value
+    #[error("Value is not {0}")]
+    UnexpectedValue(&'static str),
+
+    /// A typed value with a class other than the one it is being converted to
+    /// Format is (expected class name, actual class name)
+    #[error("Expected an instance of '{0}', found '{1}'")]
+    UnexpectedClass(&'static str, String),
+
+    /// A member of a value with the wrong type for the field it is being converted to
+    #[error("Invalid value for member '{0}'")]
+    InvalidMember(String),
+
+    /// A string that is not valid UTF-8 (strict mode only)
+    #[error("String is not valid UTF-8")]
+    InvalidString,
+
+    /// A date with a timezone offset (in minutes) outside of the range of real timezones (strict mode only)
+    #[error("Invalid timezone offset {0}")]
+    InvalidTimezone(i16),
+
     /// An unknown IO error occured
     #[error("IO error: {0}")]
     IoError(String, std::io::ErrorKind),
@@ -51,3 +117,26 @@ impl<'a, E> FromExternalError<&'a [u8], E> for Error<'a> {
         Error::Nom(input, kind)
     }
 }
+
+impl Error<'_> {
+    /// Get a human readable description of this error
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Error::Nom(_, kind) => format!("{} ({:?})", self, kind),
+            _ => self.to_string(),
+        }
+    }
+}
+
+/// A region of the input that was skipped while recovering from a decoding error
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// The offset of the start of the skipped region in the input
+    pub offset: usize,
+
+    /// The number of bytes that were skipped
+    pub length: usize,
+
+    /// A description of the error that caused this region to be skipped
+    pub message: String,
+}
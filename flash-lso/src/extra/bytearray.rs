@@ -0,0 +1,579 @@
+//! Detection and decoding of AMF data nested inside of `Value::ByteArray`
+//!
+//! Games commonly store the output of one or more `ByteArray.writeObject` calls, optionally passed through
+//! `ByteArray.compress`, as a `ByteArray` in their saves. `decode` undoes both steps and `NestedAmf::to_bytes` redoes
+//! them after editing. Each value is decoded with its own reference tables, as Flash starts a new context for every
+//! call to `writeObject`
+//!
+//! The same data is also often stored Base64 encoded in a `Value::String`, `NestedValue` handles both cases. Nested
+//! values can themselves contain further nested data, which can be found by decoding the leaves of each value
+//!
+//! `NestedDecoder::unwrap` does this recursively, finding the nested data anywhere inside of a value or `Lso`. Saves
+//! are untrusted, so `NestedDecoder` also limits how large compressed data may decompress to
+
+use crate::amf0;
+use crate::amf0::read::AMF0Decoder;
+use crate::amf3::custom_encoder::ExternalCodecRegistry;
+use crate::amf3::read::AMF3Decoder;
+use crate::amf3::write::AMF3Encoder;
+use crate::base64;
+use crate::types::{Element, Lso, Value};
+use flate2::{Decompress, FlushDecompress, Status};
+use std::io::Write;
+use std::rc::Rc;
+
+/// The largest dictionary an LZMA header may declare, anything larger is assumed to not be LZMA data
+const MAX_LZMA_DICTIONARY: u32 = 1 << 28;
+
+/// The default for `NestedDecoder::max_decompressed_size`, 16 MiB
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 16 * 1024 * 1024;
+
+/// The deepest that nested data is searched for inside of other nested data by `NestedDecoder::unwrap`
+const MAX_NESTING_DEPTH: usize = 16;
+
+/// The compression algorithms supported by `ByteArray.compress`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Compression {
+    /// Uncompressed data
+    None,
+
+    /// `CompressionAlgorithm.ZLIB`, the default for `ByteArray.compress`
+    Zlib,
+
+    /// `CompressionAlgorithm.DEFLATE`, a raw deflate stream with no header
+    Deflate,
+
+    /// `CompressionAlgorithm.LZMA`, a `.lzma` header (properties, dictionary size and unpacked size) followed by the
+    /// compressed data
+    Lzma,
+}
+
+/// The encoding used by `ByteArray.writeObject`, set by `ByteArray.objectEncoding`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Encoding {
+    /// `ObjectEncoding.AMF0`
+    Amf0,
+
+    /// `ObjectEncoding.AMF3`, the default
+    Amf3,
+}
+
+/// A sequence of values decoded from the contents of a `ByteArray`, along with how they were stored
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NestedAmf {
+    /// The compression that was applied to the encoded values
+    pub compression: Compression,
+
+    /// The encoding of the values
+    pub encoding: Encoding,
+
+    /// The values, in the order they were written
+    pub values: Vec<Rc<Value>>,
+}
+
+/// How nested AMF data is stored in its parent value
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Container {
+    /// The data is the content of a `Value::ByteArray`
+    ByteArray,
+
+    /// The data is Base64 encoded in a `Value::String`
+    Base64,
+}
+
+/// AMF data nested inside of a `Value::ByteArray` or `Value::String`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NestedValue {
+    /// How the data is stored in its parent value
+    pub container: Container,
+
+    /// The decoded data
+    pub amf: NestedAmf,
+}
+
+fn is_zlib(data: &[u8]) -> bool {
+    match data {
+        // Deflate with a window of at most 32K and a valid header checksum
+        [cmf, flg, ..] => {
+            cmf & 0x0F == 8 && cmf >> 4 <= 7 && ((u16::from(*cmf) << 8) | u16::from(*flg)) % 31 == 0
+        }
+        _ => false,
+    }
+}
+
+fn is_lzma(data: &[u8]) -> bool {
+    // Properties byte, 4 byte dictionary size, 8 byte unpacked size
+    if data.len() < 13 {
+        return false;
+    }
+    let dictionary = u32::from_le_bytes([data[1], data[2], data[3], data[4]]);
+    data[0] < 9 * 5 * 5 && dictionary <= MAX_LZMA_DICTIONARY
+}
+
+/// A step from a value to one of its children
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PathSegment {
+    /// A named element of an `Lso`, an object, the associative portion of an array or the standard elements of a
+    /// `Value::Custom`
+    Member(String),
+
+    /// A custom element of a `Value::Custom`
+    CustomMember(String),
+
+    /// An item in the dense portion of an array or a vector, or a value decoded from nested data
+    Index(usize),
+
+    /// The key of the dictionary entry at the given index
+    Key(usize),
+
+    /// The value of the dictionary entry at the given index
+    Value(usize),
+}
+
+/// Nested data found by `NestedDecoder::unwrap`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UnwrappedValue {
+    /// The path from the searched value to the `Value::String` or `Value::ByteArray` containing the data
+    pub path: Vec<PathSegment>,
+
+    /// The decoded data
+    pub nested: NestedValue,
+
+    /// Nested data found inside of the decoded values, paths start with the `PathSegment::Index` of the value in
+    /// `nested.amf.values`
+    pub children: Vec<UnwrappedValue>,
+}
+
+/// Options for decoding nested AMF data
+#[derive(Clone)]
+pub struct NestedDecoder {
+    /// The codecs used for externalized classes
+    pub registry: ExternalCodecRegistry,
+
+    /// The largest size, in bytes, that compressed data may decompress to
+    ///
+    /// Data that decompresses to more than this is treated as not being compressed, so is not decoded
+    pub max_decompressed_size: usize,
+}
+
+impl Default for NestedDecoder {
+    fn default() -> Self {
+        Self::new(ExternalCodecRegistry::default())
+    }
+}
+
+/// A writer into a `Vec` that fails once more than `max_size` bytes have been written
+struct LimitedWriter {
+    out: Vec<u8>,
+    max_size: usize,
+}
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.out.len() + buf.len() > self.max_size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::OutOfMemory,
+                "Decompressed size limit exceeded",
+            ));
+        }
+        self.out.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Inflate a zlib or raw deflate stream of at most `max_size` bytes, the stream must end exactly at the end of the
+/// data
+fn inflate(data: &[u8], zlib_header: bool, max_size: usize) -> Option<Vec<u8>> {
+    let mut decompress = Decompress::new(zlib_header);
+    let mut out = Vec::with_capacity(data.len().saturating_mul(4).min(max_size));
+    loop {
+        let consumed = decompress.total_in() as usize;
+        let produced = decompress.total_out();
+        let status = decompress
+            .decompress_vec(&data[consumed..], &mut out, FlushDecompress::Finish)
+            .ok()?;
+        if out.len() > max_size {
+            return None;
+        }
+        match status {
+            Status::StreamEnd => break,
+            Status::Ok | Status::BufError => {
+                if out.len() == max_size {
+                    // The output is full, but the stream continues
+                    return None;
+                } else if out.len() == out.capacity() {
+                    let additional = out.capacity().max(1024).min(max_size - out.len());
+                    out.reserve_exact(additional);
+                } else if decompress.total_in() as usize == consumed
+                    && decompress.total_out() == produced
+                {
+                    // No progress with space available, the stream is truncated
+                    return None;
+                }
+            }
+        }
+    }
+    (decompress.total_in() as usize == data.len()).then_some(out)
+}
+
+fn deflate(data: &[u8], zlib_header: bool) -> Vec<u8> {
+    let level = flate2::Compression::default();
+    let mut out = Vec::new();
+    let result = if zlib_header {
+        let mut encoder = flate2::write::ZlibEncoder::new(&mut out, level);
+        encoder
+            .write_all(data)
+            .and_then(|_| encoder.finish().map(|_| ()))
+    } else {
+        let mut encoder = flate2::write::DeflateEncoder::new(&mut out, level);
+        encoder
+            .write_all(data)
+            .and_then(|_| encoder.finish().map(|_| ()))
+    };
+    result.expect("Writing to a Vec can't fail");
+    out
+}
+
+fn lzma_decompress(data: &[u8], max_size: usize) -> Option<Vec<u8>> {
+    let options = lzma_rs::decompress::Options {
+        memlimit: Some(max_size),
+        ..Default::default()
+    };
+    let mut out = LimitedWriter {
+        out: Vec::new(),
+        max_size,
+    };
+    lzma_rs::lzma_decompress_with_options(&mut &data[..], &mut out, &options).ok()?;
+    Some(out.out)
+}
+
+fn lzma_compress(data: &[u8]) -> Vec<u8> {
+    let options = lzma_rs::compress::Options {
+        unpacked_size: lzma_rs::compress::UnpackedSize::WriteToHeader(Some(data.len() as u64)),
+    };
+    let mut out = Vec::new();
+    lzma_rs::lzma_compress_with_options(&mut &data[..], &mut out, &options)
+        .expect("Writing to a Vec can't fail");
+    out
+}
+
+/// Detect the compression applied to the given data by its header
+///
+/// Raw deflate streams have no header, so data without a zlib or LZMA header is reported as `Deflate` if it inflates
+/// cleanly and `None` otherwise. Data that decompresses to more than `DEFAULT_MAX_DECOMPRESSED_SIZE` bytes is also
+/// reported as `None`
+pub fn detect_compression(data: &[u8]) -> Compression {
+    decompress(data).0
+}
+
+/// Decompress the given data to at most `DEFAULT_MAX_DECOMPRESSED_SIZE` bytes, see `decompress_limited`
+pub fn decompress(data: &[u8]) -> (Compression, Vec<u8>) {
+    decompress_limited(data, DEFAULT_MAX_DECOMPRESSED_SIZE)
+}
+
+/// Decompress the given data to at most `max_size` bytes, returns the detected compression and the decompressed data
+///
+/// Data that isn't compressed, or that decompresses to more than `max_size` bytes, is returned unchanged with
+/// `Compression::None`
+pub fn decompress_limited(data: &[u8], max_size: usize) -> (Compression, Vec<u8>) {
+    if is_zlib(data) {
+        if let Some(out) = inflate(data, true, max_size) {
+            return (Compression::Zlib, out);
+        }
+    }
+    if is_lzma(data) {
+        if let Some(out) = lzma_decompress(data, max_size) {
+            return (Compression::Lzma, out);
+        }
+    }
+    match inflate(data, false, max_size) {
+        Some(out) => (Compression::Deflate, out),
+        None => (Compression::None, data.to_vec()),
+    }
+}
+
+/// Compress the given data with the given algorithm, in the same format as `ByteArray.compress`
+pub fn compress(data: &[u8], compression: Compression) -> Vec<u8> {
+    match compression {
+        Compression::None => data.to_vec(),
+        Compression::Zlib => deflate(data, true),
+        Compression::Deflate => deflate(data, false),
+        Compression::Lzma => lzma_compress(data),
+    }
+}
+
+/// Check if the given value can contain other values
+fn is_container(value: &Value) -> bool {
+    match value {
+        Value::AMF3(value) => is_container(value),
+        Value::Object(..)
+        | Value::ECMAArray(..)
+        | Value::StrictArray(..)
+        | Value::MixedArray(..)
+        | Value::VectorObject(..)
+        | Value::Dictionary(..)
+        | Value::Custom(..) => true,
+        _ => false,
+    }
+}
+
+fn decode_amf3(data: &[u8], registry: &ExternalCodecRegistry) -> Option<Vec<Rc<Value>>> {
+    let mut values = Vec::new();
+    let mut i = data;
+    while !i.is_empty() {
+        let mut decoder = AMF3Decoder::default();
+        decoder.strict = true;
+        registry.install_decoder(&mut decoder);
+        let (rest, value) = decoder.parse_single_element(i).ok()?;
+        values.push(value);
+        i = rest;
+    }
+    Some(values)
+}
+
+fn decode_amf0(data: &[u8], registry: &ExternalCodecRegistry) -> Option<Vec<Rc<Value>>> {
+    let mut values = Vec::new();
+    let mut i = data;
+    while !i.is_empty() {
+        let mut decoder = AMF0Decoder::default();
+        decoder.strict = true;
+        decoder.amf3_decoder.strict = true;
+        registry.install_decoder(&mut decoder.amf3_decoder);
+        let (rest, value) = decoder.parse_single_element(i).ok()?;
+        values.push(value);
+        i = rest;
+    }
+    Some(values)
+}
+
+/// Try to decode the contents of a `ByteArray` as a sequence of AMF values, see `NestedDecoder::decode`
+pub fn decode(data: &[u8]) -> Option<NestedAmf> {
+    NestedDecoder::default().decode(data)
+}
+
+/// Try to decode the contents of a `ByteArray` as a sequence of AMF values, using the given codecs for externalized
+/// classes, see `NestedDecoder::decode`
+pub fn decode_with(data: &[u8], registry: &ExternalCodecRegistry) -> Option<NestedAmf> {
+    NestedDecoder::new(registry.clone()).decode(data)
+}
+
+impl NestedDecoder {
+    /// Create a decoder using the given codecs for externalized classes and the default limits
+    pub fn new(registry: ExternalCodecRegistry) -> Self {
+        Self {
+            registry,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+        }
+    }
+
+    /// Try to decode the contents of a `ByteArray` as a sequence of AMF values
+    ///
+    /// The data is decompressed if needed, then decoded as AMF3, falling back to AMF0. Values are decoded in strict
+    /// mode and only data that decodes entirely into at least one value is accepted. As short data can decode into a
+    /// few primitives by coincidence, uncompressed data must also contain an object, array, vector or dictionary
+    pub fn decode(&self, data: &[u8]) -> Option<NestedAmf> {
+        let (compression, data) = decompress_limited(data, self.max_decompressed_size);
+        if data.is_empty() {
+            return None;
+        }
+
+        let (encoding, values) = match decode_amf3(&data, &self.registry) {
+            Some(values) => (Encoding::Amf3, values),
+            None => (Encoding::Amf0, decode_amf0(&data, &self.registry)?),
+        };
+        if compression == Compression::None && !values.iter().any(|v| is_container(v)) {
+            return None;
+        }
+
+        Some(NestedAmf {
+            compression,
+            encoding,
+            values,
+        })
+    }
+
+    /// Try to decode AMF data nested inside of the given value
+    ///
+    /// The content of a `Value::ByteArray` is decoded with `decode`, as is a `Value::String` that is entirely padded
+    /// Base64. Other values never contain nested data
+    pub fn decode_value(&self, value: &Value) -> Option<NestedValue> {
+        let (container, amf) = match value {
+            Value::ByteArray(data) => (Container::ByteArray, self.decode(data)?),
+            Value::String(s) if !s.is_empty() && s.len() % 4 == 0 => {
+                let data = base64::decode(s)?;
+                (Container::Base64, self.decode(&data)?)
+            }
+            _ => return None,
+        };
+        Some(NestedValue { container, amf })
+    }
+
+    /// Recursively find and decode the nested data inside of every value in the body of `lso`, see `unwrap`
+    pub fn unwrap_lso(&self, lso: &Lso) -> Vec<UnwrappedValue> {
+        let mut out = Vec::new();
+        self.unwrap_elements(&lso.body, PathSegment::Member, &mut Vec::new(), 0, &mut out);
+        out
+    }
+
+    /// Recursively find and decode the nested data inside of `value`, including `value` itself
+    ///
+    /// The values decoded from nested data are searched as well, to a limited depth. The decoded data can be edited
+    /// and encoded with `NestedValue::to_value_with`, then written back to the location given by its path
+    pub fn unwrap(&self, value: &Value) -> Vec<UnwrappedValue> {
+        let mut out = Vec::new();
+        self.unwrap_into(value, &mut Vec::new(), 0, &mut out);
+        out
+    }
+
+    fn unwrap_elements(
+        &self,
+        elements: &[Element],
+        segment: fn(String) -> PathSegment,
+        path: &mut Vec<PathSegment>,
+        depth: usize,
+        out: &mut Vec<UnwrappedValue>,
+    ) {
+        for element in elements {
+            path.push(segment(element.name.clone()));
+            self.unwrap_into(&element.value, path, depth, out);
+            path.pop();
+        }
+    }
+
+    fn unwrap_items(
+        &self,
+        items: &[Rc<Value>],
+        segment: fn(usize) -> PathSegment,
+        path: &mut Vec<PathSegment>,
+        depth: usize,
+        out: &mut Vec<UnwrappedValue>,
+    ) {
+        for (index, item) in items.iter().enumerate() {
+            path.push(segment(index));
+            self.unwrap_into(item, path, depth, out);
+            path.pop();
+        }
+    }
+
+    fn unwrap_into(
+        &self,
+        value: &Value,
+        path: &mut Vec<PathSegment>,
+        depth: usize,
+        out: &mut Vec<UnwrappedValue>,
+    ) {
+        match value {
+            Value::String(_) | Value::ByteArray(_) if depth < MAX_NESTING_DEPTH => {
+                if let Some(nested) = self.decode_value(value) {
+                    let mut children = Vec::new();
+                    self.unwrap_items(
+                        &nested.amf.values,
+                        PathSegment::Index,
+                        &mut Vec::new(),
+                        depth + 1,
+                        &mut children,
+                    );
+                    out.push(UnwrappedValue {
+                        path: path.clone(),
+                        nested,
+                        children,
+                    });
+                }
+            }
+            Value::AMF3(value) => self.unwrap_into(value, path, depth, out),
+            Value::Object(_, elements, _) => {
+                self.unwrap_elements(elements, PathSegment::Member, path, depth, out)
+            }
+            Value::ECMAArray(_, dense, assoc, _) | Value::MixedArray(_, dense, assoc) => {
+                self.unwrap_items(dense, PathSegment::Index, path, depth, out);
+                self.unwrap_elements(assoc, PathSegment::Member, path, depth, out);
+            }
+            Value::StrictArray(_, items) | Value::VectorObject(_, items, _, _) => {
+                self.unwrap_items(items, PathSegment::Index, path, depth, out)
+            }
+            Value::Dictionary(_, entries, _) => {
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    path.push(PathSegment::Key(index));
+                    self.unwrap_into(key, path, depth, out);
+                    path.pop();
+                    path.push(PathSegment::Value(index));
+                    self.unwrap_into(value, path, depth, out);
+                    path.pop();
+                }
+            }
+            Value::Custom(custom, elements, _) => {
+                self.unwrap_elements(custom, PathSegment::CustomMember, path, depth, out);
+                self.unwrap_elements(elements, PathSegment::Member, path, depth, out);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl NestedAmf {
+    /// Encode and compress the values, giving the new contents of the `ByteArray`
+    pub fn to_bytes(&self) -> std::io::Result<Vec<u8>> {
+        self.to_bytes_with(&ExternalCodecRegistry::default())
+    }
+
+    /// Encode and compress the values using the given codecs for externalized classes, giving the new contents of
+    /// the `ByteArray`
+    pub fn to_bytes_with(&self, registry: &ExternalCodecRegistry) -> std::io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        for value in &self.values {
+            match self.encoding {
+                Encoding::Amf0 => amf0::write::write_value(&mut data, value)?,
+                Encoding::Amf3 => {
+                    let mut encoder = AMF3Encoder::default();
+                    registry.install_encoder(&mut encoder);
+                    encoder.write_value_element(&mut data, value)?;
+                }
+            }
+        }
+        Ok(compress(&data, self.compression))
+    }
+
+    /// Encode and compress the values, giving a new `Value::ByteArray`
+    pub fn to_value(&self) -> std::io::Result<Value> {
+        self.to_bytes().map(Value::ByteArray)
+    }
+}
+
+impl NestedValue {
+    /// Try to decode AMF data nested inside of the given value, see `NestedDecoder::decode_value`
+    pub fn decode(value: &Value) -> Option<Self> {
+        NestedDecoder::default().decode_value(value)
+    }
+
+    /// Try to decode AMF data nested inside of the given value, using the given codecs for externalized classes, see
+    /// `NestedDecoder::decode_value`
+    pub fn decode_with(value: &Value, registry: &ExternalCodecRegistry) -> Option<Self> {
+        NestedDecoder::new(registry.clone()).decode_value(value)
+    }
+
+    /// Encode the data, giving a new value to replace the original with
+    pub fn to_value(&self) -> std::io::Result<Value> {
+        self.to_value_with(&ExternalCodecRegistry::default())
+    }
+
+    /// Encode the data using the given codecs for externalized classes, giving a new value to replace the original
+    /// with
+    pub fn to_value_with(&self, registry: &ExternalCodecRegistry) -> std::io::Result<Value> {
+        let data = self.amf.to_bytes_with(registry)?;
+        Ok(match self.container {
+            Container::ByteArray => Value::ByteArray(data),
+            Container::Base64 => Value::String(base64::encode(&data)),
+        })
+    }
+}
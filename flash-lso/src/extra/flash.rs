@@ -0,0 +1,55 @@
+//! Codecs for commonly externalized ActionScript classes
+//!
+//! The collection and proxy classes of the Flex SDK externalize a single AMF3 value, in the same way as their
+//! `flex.messaging.io` equivalents, so they are registered as aliases of the codecs in `extra::flex`.
+//!
+//! Other common classes don't need a codec: the geometry classes of `flash.geom` are not externalizable, so are
+//! written as typed objects, and instances of classes extending `flash.utils.ByteArray` are written as a plain
+//! `Value::ByteArray`, the content of which can be decoded with `extra::bytearray`.
+
+use crate::amf3::custom_encoder::ExternalCodecRegistry;
+use crate::amf3::read::AMF3Decoder;
+use crate::amf3::write::AMF3Encoder;
+use crate::extra::flex;
+use std::rc::Rc;
+
+/// Register the codecs for common ActionScript classes into the given registry
+pub fn register_codecs(registry: &mut ExternalCodecRegistry) {
+    let collection = flex::array_collection_codec();
+    for name in ["mx.collections.ArrayCollection", "mx.collections.ArrayList"] {
+        registry.register(name, Rc::clone(&collection));
+    }
+
+    let proxy = flex::object_proxy_codec();
+    for name in [
+        "mx.utils.ObjectProxy",
+        "mx.data.utils.SerializationProxy",
+        "mx.data.ManagedObjectProxy",
+    ] {
+        registry.register(name, Rc::clone(&proxy));
+    }
+}
+
+/// Register the decoders for common ActionScript classes into the given AMF3Decoder
+#[deprecated(
+    since = "0.6.0",
+    note = "Use `register_codecs` with an `ExternalCodecRegistry`, which installs the decoders and encoders together"
+)]
+#[inline]
+pub fn register_decoders(decoder: &mut AMF3Decoder) {
+    let mut registry = ExternalCodecRegistry::new();
+    register_codecs(&mut registry);
+    registry.install_decoder(decoder);
+}
+
+/// Register the encoders for common ActionScript classes into the given AMF3Encoder
+#[deprecated(
+    since = "0.6.0",
+    note = "Use `register_codecs` with an `ExternalCodecRegistry`, which installs the decoders and encoders together"
+)]
+#[inline]
+pub fn register_encoders(encoder: &mut AMF3Encoder) {
+    let mut registry = ExternalCodecRegistry::new();
+    register_codecs(&mut registry);
+    registry.install_encoder(encoder);
+}
@@ -0,0 +1,597 @@
+//! Typed representations of flex messages
+//!
+//! These convert to and from the `Value::Custom` produced by the flex decoders, using the same element names
+
+use crate::errors::Error;
+use crate::extra::flex::uuid;
+use crate::types::{Attribute, ClassDefinition, Element, ObjectId, Value};
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+/// An id of a message or client, flex sends these either as a string or as 16 bytes
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Uuid {
+    /// The canonical string form, e.g. `CA5B3C0E-5A8B-4F4D-A5B6-0F7B2B1B8B6C`
+    String(String),
+
+    /// The compact byte form
+    Bytes(Vec<u8>),
+}
+
+impl Uuid {
+    /// Get the canonical string form of this id
+    ///
+    /// Returns None for byte ids that are not 16 bytes long
+    pub fn to_canonical_string(&self) -> Option<String> {
+        match self {
+            Uuid::String(s) => Some(s.clone()),
+            Uuid::Bytes(b) => uuid::bytes_to_string(b),
+        }
+    }
+
+    /// Get the compact byte form of this id
+    ///
+    /// Returns None for string ids that are not canonical UUIDs
+    pub fn to_bytes(&self) -> Option<[u8; uuid::UUID_LENGTH]> {
+        match self {
+            Uuid::String(s) => uuid::string_to_bytes(s),
+            Uuid::Bytes(b) => b.as_slice().try_into().ok(),
+        }
+    }
+}
+
+/// The operation performed by a `CommandMessage`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CommandOperation {
+    /// Subscribe to a destination
+    Subscribe,
+
+    /// Unsubscribe from a destination
+    Unsubscribe,
+
+    /// Poll for pending messages
+    Poll,
+
+    /// Sent by the server to synchronise a client
+    ClientSync,
+
+    /// Test connectivity to the server
+    ClientPing,
+
+    /// Request the endpoints of a cluster
+    ClusterRequest,
+
+    /// Authenticate with the server
+    Login,
+
+    /// Log out of the server
+    Logout,
+
+    /// Sent by the server when a subscription is no longer valid
+    SubscriptionInvalidate,
+
+    /// Subscribe to several destinations at once
+    MultiSubscribe,
+
+    /// Disconnect from the server
+    Disconnect,
+
+    /// Trigger a connection to the server
+    TriggerConnect,
+
+    /// Any other operation
+    Other(i32),
+}
+
+impl From<i32> for CommandOperation {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => Self::Subscribe,
+            1 => Self::Unsubscribe,
+            2 => Self::Poll,
+            4 => Self::ClientSync,
+            5 => Self::ClientPing,
+            7 => Self::ClusterRequest,
+            8 => Self::Login,
+            9 => Self::Logout,
+            10 => Self::SubscriptionInvalidate,
+            11 => Self::MultiSubscribe,
+            12 => Self::Disconnect,
+            13 => Self::TriggerConnect,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<CommandOperation> for i32 {
+    fn from(value: CommandOperation) -> Self {
+        match value {
+            CommandOperation::Subscribe => 0,
+            CommandOperation::Unsubscribe => 1,
+            CommandOperation::Poll => 2,
+            CommandOperation::ClientSync => 4,
+            CommandOperation::ClientPing => 5,
+            CommandOperation::ClusterRequest => 7,
+            CommandOperation::Login => 8,
+            CommandOperation::Logout => 9,
+            CommandOperation::SubscriptionInvalidate => 10,
+            CommandOperation::MultiSubscribe => 11,
+            CommandOperation::Disconnect => 12,
+            CommandOperation::TriggerConnect => 13,
+            CommandOperation::Other(value) => value,
+        }
+    }
+}
+
+/// Remove the element with the given name
+fn take(elements: &mut Vec<Element>, name: &str) -> Option<Rc<Value>> {
+    let index = elements.iter().position(|e| e.name == name)?;
+    Some(elements.remove(index).value)
+}
+
+fn take_string(elements: &mut Vec<Element>, name: &str) -> Result<Option<String>, Error<'static>> {
+    match take(elements, name).as_deref() {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        Some(_) => Err(Error::InvalidMember(name.to_string())),
+    }
+}
+
+fn take_number(elements: &mut Vec<Element>, name: &str) -> Result<Option<f64>, Error<'static>> {
+    match take(elements, name).as_deref() {
+        None => Ok(None),
+        Some(Value::Number(n)) => Ok(Some(*n)),
+        Some(Value::Integer(n)) => Ok(Some(*n as f64)),
+        Some(_) => Err(Error::InvalidMember(name.to_string())),
+    }
+}
+
+fn take_uuid(elements: &mut Vec<Element>, name: &str) -> Result<Option<Uuid>, Error<'static>> {
+    let string = take_string(elements, name)?.map(Uuid::String);
+    let bytes = match take(elements, &format!("{}_bytes", name)).as_deref() {
+        None | Some(Value::Null) => None,
+        Some(Value::ByteArray(bytes)) => Some(Uuid::Bytes(bytes.clone())),
+        Some(_) => return Err(Error::InvalidMember(format!("{}_bytes", name))),
+    };
+    Ok(string.or(bytes))
+}
+
+fn push_string(elements: &mut Vec<Element>, name: &str, value: Option<String>) {
+    if let Some(value) = value {
+        elements.push(Element::new(name, Rc::new(Value::String(value))));
+    }
+}
+
+fn push_number(elements: &mut Vec<Element>, name: &str, value: Option<f64>) {
+    if let Some(value) = value {
+        elements.push(Element::new(name, Rc::new(Value::Number(value))));
+    }
+}
+
+fn push_uuid(elements: &mut Vec<Element>, name: &str, value: Option<Uuid>) {
+    match value {
+        Some(Uuid::String(s)) => push_string(elements, name, Some(s)),
+        Some(Uuid::Bytes(b)) => elements.push(Element::new(
+            format!("{}_bytes", name),
+            Rc::new(Value::ByteArray(b)),
+        )),
+        None => {}
+    }
+}
+
+/// The names of properties in the standard (sealed) form of messages, and the names used by the decoders
+const SEALED_NAMES: &[(&str, &str)] = &[
+    ("clientId", "client_id"),
+    ("messageId", "message_id"),
+    ("timeToLive", "ttl"),
+    ("correlationId", "correlation_id"),
+    ("faultCode", "fault_code"),
+    ("faultString", "fault_string"),
+    ("faultDetail", "fault_detail"),
+    ("rootCause", "root_cause"),
+    ("extendedData", "extended_data"),
+];
+
+/// Get the elements of either the externalized (`Value::Custom`) or standard (`Value::Object`) form of a message,
+/// the class of which must be one of `class_names`
+fn custom_elements(
+    value: &Value,
+    class_names: &[&'static str],
+) -> Result<Vec<Element>, Error<'static>> {
+    let class_def = match value {
+        Value::Custom(_, _, Some(class_def)) | Value::Object(_, _, Some(class_def)) => class_def,
+        _ => return Err(Error::UnexpectedValue("a flex message")),
+    };
+    if !class_names.contains(&class_def.name.as_str()) {
+        return Err(Error::UnexpectedClass(
+            class_names[0],
+            class_def.name.clone(),
+        ));
+    }
+
+    match value {
+        Value::Custom(elements, _, _) => Ok(elements.clone()),
+        Value::Object(_, elements, _) => Ok(elements
+            .iter()
+            .map(|e| {
+                let name = SEALED_NAMES
+                    .iter()
+                    .find(|(sealed, _)| *sealed == e.name)
+                    .map_or(e.name.as_str(), |(_, name)| name);
+                Element::new(name, Rc::clone(&e.value))
+            })
+            .collect()),
+        _ => unreachable!("Only typed values have a class definition"),
+    }
+}
+
+fn custom_value(class_name: &str, elements: Vec<Element>) -> Value {
+    let mut class_def = ClassDefinition::default_with_name(class_name.to_string());
+    class_def.attributes.insert(Attribute::External);
+    Value::Custom(elements, vec![], Some(class_def))
+}
+
+/// Create the standard form of a message, a sealed `Value::Object` of the given class, from the elements of its
+/// externalized form
+///
+/// Byte ids only exist in the externalized form, so are written as strings
+fn sealed_value(class_name: &str, elements: Vec<Element>) -> Value {
+    let elements: Vec<Element> = elements
+        .into_iter()
+        .filter_map(|e| {
+            let (name, value) = match e.name.strip_suffix("_bytes") {
+                Some(name) => match e.value.as_ref() {
+                    Value::ByteArray(b) => (
+                        name.to_string(),
+                        Rc::new(Value::String(Uuid::Bytes(b.clone()).to_canonical_string()?)),
+                    ),
+                    _ => return None,
+                },
+                None => (e.name, e.value),
+            };
+            let name = SEALED_NAMES
+                .iter()
+                .find(|(_, custom)| *custom == name)
+                .map_or(name, |(sealed, _)| sealed.to_string());
+            Some(Element::new(name, value))
+        })
+        .collect();
+
+    let class_def = ClassDefinition {
+        static_properties: elements.iter().map(|e| e.name.clone()).collect(),
+        ..ClassDefinition::default_with_name(class_name.to_string())
+    };
+    Value::Object(ObjectId::INVALID, elements, Some(class_def))
+}
+
+/// Implement conversions to and from `Value::Custom` for a message type
+///
+/// Messages can also be converted to and from their standard form, a typed `Value::Object`. Values of any of the class
+/// names of the message type can be converted, the first is used when converting to a `Value`
+macro_rules! message_conversions {
+    ($name: ident, [$class: literal $(, $alias: literal)*], standard: $standard: literal) => {
+        impl $name {
+            /// The class name used when converting this message to a `Value`
+            pub const CLASS_NAME: &'static str = $class;
+
+            /// The class name of the standard form of this message
+            pub const STANDARD_CLASS_NAME: &'static str = $standard;
+
+            /// The class names of this message, including the standard form and any aliases
+            pub const CLASS_NAMES: &'static [&'static str] = &[$class $(, $alias)*, $standard];
+
+            /// Convert this message to its standard form, the sealed object sent by flex servers
+            pub fn into_standard_value(self) -> Value {
+                let mut elements = Vec::new();
+                self.into_elements(&mut elements);
+                sealed_value(Self::STANDARD_CLASS_NAME, elements)
+            }
+        }
+
+        impl TryFrom<&Value> for $name {
+            type Error = Error<'static>;
+
+            fn try_from(value: &Value) -> Result<Self, Self::Error> {
+                Self::from_elements(&mut custom_elements(value, Self::CLASS_NAMES)?)
+            }
+        }
+
+        impl From<$name> for Value {
+            fn from(message: $name) -> Self {
+                let mut elements = Vec::new();
+                message.into_elements(&mut elements);
+                custom_value($name::CLASS_NAME, elements)
+            }
+        }
+    };
+}
+
+/// The base of every flex message
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AbstractMessage {
+    /// The contents of the message
+    pub body: Option<Rc<Value>>,
+
+    /// The id of the client that sent the message
+    pub client_id: Option<Uuid>,
+
+    /// The destination the message is for
+    pub destination: Option<String>,
+
+    /// Any headers of the message, usually an anonymous object
+    pub headers: Option<Rc<Value>>,
+
+    /// The id of this message
+    pub message_id: Option<Uuid>,
+
+    /// The time the message was sent, in milliseconds since the unix epoch
+    pub timestamp: Option<f64>,
+
+    /// The time in milliseconds that the message is valid for, 0 if it doesn't expire
+    pub time_to_live: Option<f64>,
+
+    /// Any elements that are not part of the known fields, such as those added by later versions
+    pub extra: Vec<Element>,
+}
+
+impl AbstractMessage {
+    fn from_elements(elements: &mut Vec<Element>) -> Result<Self, Error<'static>> {
+        Ok(Self {
+            body: take(elements, "body"),
+            client_id: take_uuid(elements, "client_id")?,
+            destination: take_string(elements, "destination")?,
+            headers: take(elements, "headers"),
+            message_id: take_uuid(elements, "message_id")?,
+            timestamp: take_number(elements, "timestamp")?,
+            time_to_live: take_number(elements, "ttl")?,
+            extra: std::mem::take(elements),
+        })
+    }
+
+    fn into_elements(self, elements: &mut Vec<Element>) {
+        if let Some(body) = self.body {
+            elements.push(Element::new("body", body));
+        }
+        push_uuid(elements, "client_id", self.client_id);
+        push_string(elements, "destination", self.destination);
+        if let Some(headers) = self.headers {
+            elements.push(Element::new("headers", headers));
+        }
+        push_uuid(elements, "message_id", self.message_id);
+        push_number(elements, "timestamp", self.timestamp);
+        push_number(elements, "ttl", self.time_to_live);
+        elements.extend(self.extra);
+    }
+}
+
+message_conversions!(
+    AbstractMessage,
+    [
+        "flex.messaging.io.AbstractMessage"
+    ],
+    standard: "flex.messaging.messages.AbstractMessage"
+);
+
+/// A message that may be a response to another message
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AsyncMessage {
+    /// The fields common to all messages
+    pub base: AbstractMessage,
+
+    /// The id of the message this message is a response to
+    pub correlation_id: Option<Uuid>,
+}
+
+impl AsyncMessage {
+    fn from_elements(elements: &mut Vec<Element>) -> Result<Self, Error<'static>> {
+        let correlation_id = take_uuid(elements, "correlation_id")?;
+        Ok(Self {
+            base: AbstractMessage::from_elements(elements)?,
+            correlation_id,
+        })
+    }
+
+    fn into_elements(self, elements: &mut Vec<Element>) {
+        self.base.into_elements(elements);
+        push_uuid(elements, "correlation_id", self.correlation_id);
+    }
+}
+
+message_conversions!(
+    AsyncMessage,
+    [
+        "flex.messaging.io.AsyncMessage",
+        "flex.messaging.io.AsyncMessageExt",
+        "DSA"
+    ],
+    standard: "flex.messaging.messages.AsyncMessage"
+);
+
+/// A message sent in response to a successful message
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AcknowledgeMessage {
+    /// The fields common to all responses
+    pub base: AsyncMessage,
+}
+
+impl AcknowledgeMessage {
+    fn from_elements(elements: &mut Vec<Element>) -> Result<Self, Error<'static>> {
+        Ok(Self {
+            base: AsyncMessage::from_elements(elements)?,
+        })
+    }
+
+    fn into_elements(self, elements: &mut Vec<Element>) {
+        self.base.into_elements(elements);
+    }
+}
+
+message_conversions!(
+    AcknowledgeMessage,
+    [
+        "flex.messaging.io.AcknowledgeMessage",
+        "flex.messaging.io.AcknowledgeMessageExt",
+        "DSK"
+    ],
+    standard: "flex.messaging.messages.AcknowledgeMessage"
+);
+
+/// A message used to control the connection to the server, such as pings and logins
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandMessage {
+    /// The fields common to all responses
+    pub base: AsyncMessage,
+
+    /// The operation to perform
+    pub operation: Option<CommandOperation>,
+}
+
+impl CommandMessage {
+    fn from_elements(elements: &mut Vec<Element>) -> Result<Self, Error<'static>> {
+        let operation = match take(elements, "operation").as_deref() {
+            None => None,
+            Some(Value::Integer(i)) => Some(CommandOperation::from(*i)),
+            Some(Value::Number(n)) => Some(CommandOperation::from(*n as i32)),
+            Some(_) => return Err(Error::InvalidMember("operation".to_string())),
+        };
+        Ok(Self {
+            base: AsyncMessage::from_elements(elements)?,
+            operation,
+        })
+    }
+
+    fn into_elements(self, elements: &mut Vec<Element>) {
+        self.base.into_elements(elements);
+        if let Some(operation) = self.operation {
+            elements.push(Element::new(
+                "operation",
+                Rc::new(Value::Integer(operation.into())),
+            ));
+        }
+    }
+}
+
+message_conversions!(
+    CommandMessage,
+    [
+        "flex.messaging.io.CommandMessage",
+        "flex.messaging.io.CommandMessageExt",
+        "DSC"
+    ],
+    standard: "flex.messaging.messages.CommandMessage"
+);
+
+/// A message sent in response to a failed message
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorMessage {
+    /// The fields common to all responses
+    pub base: AcknowledgeMessage,
+
+    /// A code identifying the error, e.g. `Server.Processing`
+    pub fault_code: Option<String>,
+
+    /// A description of the error
+    pub fault_string: Option<String>,
+
+    /// Further details of the error
+    pub fault_detail: Option<String>,
+
+    /// The error that caused this error, if any
+    pub root_cause: Option<Rc<Value>>,
+
+    /// Any additional data about the error
+    pub extended_data: Option<Rc<Value>>,
+}
+
+impl ErrorMessage {
+    fn from_elements(elements: &mut Vec<Element>) -> Result<Self, Error<'static>> {
+        let fault_code = take_string(elements, "fault_code")?;
+        let fault_string = take_string(elements, "fault_string")?;
+        let fault_detail = take_string(elements, "fault_detail")?;
+        let root_cause = take(elements, "root_cause");
+        let extended_data = take(elements, "extended_data");
+        Ok(Self {
+            base: AcknowledgeMessage::from_elements(elements)?,
+            fault_code,
+            fault_string,
+            fault_detail,
+            root_cause,
+            extended_data,
+        })
+    }
+
+    fn into_elements(self, elements: &mut Vec<Element>) {
+        self.base.into_elements(elements);
+        push_string(elements, "fault_code", self.fault_code);
+        push_string(elements, "fault_string", self.fault_string);
+        push_string(elements, "fault_detail", self.fault_detail);
+        if let Some(root_cause) = self.root_cause {
+            elements.push(Element::new("root_cause", root_cause));
+        }
+        if let Some(extended_data) = self.extended_data {
+            elements.push(Element::new("extended_data", extended_data));
+        }
+    }
+}
+
+message_conversions!(
+    ErrorMessage,
+    [
+        "flex.messaging.io.ErrorMessage"
+    ],
+    standard: "flex.messaging.messages.ErrorMessage"
+);
+
+/// A message used to call a method on a remote object
+///
+/// Unlike the other messages this is never externalized, so it converts to and from a typed `Value::Object`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RemotingMessage {
+    /// The fields common to all messages, the body contains the arguments of the call
+    pub base: AbstractMessage,
+
+    /// The name of the method to call
+    pub operation: Option<String>,
+
+    /// The class of the remote object, usually unused
+    pub source: Option<String>,
+}
+
+impl RemotingMessage {
+    fn from_elements(elements: &mut Vec<Element>) -> Result<Self, Error<'static>> {
+        let operation = take_string(elements, "operation")?;
+        let source = take_string(elements, "source")?;
+        Ok(Self {
+            base: AbstractMessage::from_elements(elements)?,
+            operation,
+            source,
+        })
+    }
+
+    fn into_elements(self, elements: &mut Vec<Element>) {
+        self.base.into_elements(elements);
+        push_string(elements, "operation", self.operation);
+        push_string(elements, "source", self.source);
+    }
+}
+
+impl RemotingMessage {
+    /// The class name used when converting this message to a `Value`
+    pub const CLASS_NAME: &'static str = "flex.messaging.messages.RemotingMessage";
+}
+
+impl TryFrom<&Value> for RemotingMessage {
+    type Error = Error<'static>;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        Self::from_elements(&mut custom_elements(value, &[Self::CLASS_NAME])?)
+    }
+}
+
+impl From<RemotingMessage> for Value {
+    fn from(message: RemotingMessage) -> Self {
+        let mut elements = Vec::new();
+        message.into_elements(&mut elements);
+        sealed_value(RemotingMessage::CLASS_NAME, elements)
+    }
+}
@@ -1,3 +1,10 @@
+use crate::amf3::custom_encoder::{ExternalCodec, ExternalCodecRegistry};
+use crate::amf3::read::AMF3Decoder;
+use crate::amf3::write::AMF3Encoder;
+use crate::nom_utils::AMFResult;
+use crate::types::{ClassDefinition, Element};
+use std::rc::Rc;
+
 const NEXT_FLAG: u8 = 128;
 
 const BODY_FLAG: u8 = 1;
@@ -16,5 +23,115 @@ const CORRELATION_ID_BYTES_FLAG: u8 = 2;
 
 const OPERATION_FLAG: u8 = 1;
 
+pub mod messages;
 pub mod read;
+pub mod uuid;
 pub mod write;
+
+type DecodeFn = for<'a> fn(&'a [u8], &mut AMF3Decoder) -> AMFResult<'a, Vec<Element>>;
+
+/// Combines a flex decoder and encoder into a single codec
+struct FlexCodec<E> {
+    decode: DecodeFn,
+    encoder: E,
+}
+
+impl<E: write::FlexEncoder> ExternalCodec for FlexCodec<E> {
+    fn decode<'a>(&self, i: &'a [u8], decoder: &mut AMF3Decoder) -> AMFResult<'a, Vec<Element>> {
+        (self.decode)(i, decoder)
+    }
+
+    fn encode(
+        &self,
+        elements: &[Element],
+        _class_def: &Option<ClassDefinition>,
+        encoder: &AMF3Encoder,
+    ) -> std::io::Result<Vec<u8>> {
+        self.encoder.encode(elements, encoder)
+    }
+}
+
+fn codec<E: write::FlexEncoder + 'static>(decode: DecodeFn, encoder: E) -> Rc<dyn ExternalCodec> {
+    Rc::new(FlexCodec { decode, encoder })
+}
+
+/// The codec for `ArrayCollection` and `ArrayList`, which externalize their source array
+pub(crate) fn array_collection_codec() -> Rc<dyn ExternalCodec> {
+    codec(read::parse_array_collection, write::ArrayCollection)
+}
+
+/// The codec for `ObjectProxy` and its subclasses, which externalize the proxied object
+pub(crate) fn object_proxy_codec() -> Rc<dyn ExternalCodec> {
+    codec(read::parse_object_proxy, write::ObjectProxy)
+}
+
+/// Register the flex codecs into the given registry
+pub fn register_codecs(registry: &mut ExternalCodecRegistry) {
+    register_codecs_with_ids(registry, false);
+}
+
+/// Register the flex codecs into the given registry, writing message ids in the compact byte form
+///
+/// Only ids in the canonical UUID form are converted, any others are written as strings
+pub fn register_codecs_compact(registry: &mut ExternalCodecRegistry) {
+    register_codecs_with_ids(registry, true);
+}
+
+fn register_codecs_with_ids(registry: &mut ExternalCodecRegistry, compact_ids: bool) {
+    let abstract_message = codec(
+        read::parse_abstract_message,
+        write::AbstractMessage { compact_ids },
+    );
+    let async_message = codec(
+        read::parse_async_message,
+        write::AsyncMessage { compact_ids },
+    );
+    let acknowledge_message = codec(
+        read::parse_acknowledge_message,
+        write::AcknowledgeMessage { compact_ids },
+    );
+    let command_message = codec(
+        read::parse_command_message,
+        write::CommandMessage { compact_ids },
+    );
+    let array_collection = array_collection_codec();
+    let object_proxy = object_proxy_codec();
+
+    registry.register("flex.messaging.io.AbstractMessage", abstract_message);
+    for name in [
+        "flex.messaging.io.AsyncMessage",
+        "flex.messaging.io.AsyncMessageExt",
+        // The short alias used by BlazeDS for the small message form
+        "DSA",
+    ] {
+        registry.register(name, Rc::clone(&async_message));
+    }
+    for name in [
+        "flex.messaging.io.AcknowledgeMessage",
+        "flex.messaging.io.AcknowledgeMessageExt",
+        "flex.messaging.io.ErrorMessage",
+        "DSK",
+    ] {
+        registry.register(name, Rc::clone(&acknowledge_message));
+    }
+    for name in [
+        "flex.messaging.io.CommandMessage",
+        "flex.messaging.io.CommandMessageExt",
+        "DSC",
+    ] {
+        registry.register(name, Rc::clone(&command_message));
+    }
+    for name in [
+        "flex.messaging.io.ArrayCollection",
+        "flex.messaging.io.ArrayList",
+    ] {
+        registry.register(name, Rc::clone(&array_collection));
+    }
+    for name in [
+        "flex.messaging.io.ObjectProxy",
+        "flex.messaging.io.ManagedObjectProxy",
+        "flex.messaging.io.SerializationProxy",
+    ] {
+        registry.register(name, Rc::clone(&object_proxy));
+    }
+}
@@ -1,8 +1,9 @@
 //! Handles decoding of flex types
 
+use crate::amf3::custom_encoder::ExternalCodecRegistry;
 use crate::amf3::read::AMF3Decoder;
 use crate::extra::flex::{
-    BODY_FLAG, CLIENT_ID_BYTES_FLAG, CLIENT_ID_FLAG, CORRELATION_ID_BYTES_FLAG,
+    register_codecs, BODY_FLAG, CLIENT_ID_BYTES_FLAG, CLIENT_ID_FLAG, CORRELATION_ID_BYTES_FLAG,
     CORRELATION_ID_FLAG, DESTINATION_ID_FLAG, HEADERS_FLAG, MESSAGE_ID_BYTES_FLAG, MESSAGE_ID_FLAG,
     NEXT_FLAG, OPERATION_FLAG, TIMESTAMP_FLAG, TTL_FLAG,
 };
@@ -10,26 +11,27 @@ use crate::nom_utils::AMFResult;
 use crate::types::Element;
 use nom::number::complete::be_u8;
 
-use std::rc::Rc;
-
 fn parse_abstract_message_flags(i: &[u8]) -> AMFResult<'_, Vec<u8>> {
     let mut next_flag = true;
     let mut flags = Vec::new();
 
     let mut k = i;
     while next_flag {
-        let (i, flag) = be_u8(i)?;
+        let (j, flag) = be_u8(k)?;
         flags.push(flag);
         if flag & NEXT_FLAG == 0 {
             next_flag = false
         }
-        k = i;
+        k = j;
     }
 
     Ok((k, flags))
 }
 
-fn parse_abstract_message<'a>(i: &'a [u8], amf3: &mut AMF3Decoder) -> AMFResult<'a, Vec<Element>> {
+pub(crate) fn parse_abstract_message<'a>(
+    i: &'a [u8],
+    amf3: &mut AMF3Decoder,
+) -> AMFResult<'a, Vec<Element>> {
     let (i, flags) = parse_abstract_message_flags(i)?;
 
     let mut elements = Vec::new();
@@ -118,8 +120,8 @@ fn parse_abstract_message<'a>(i: &'a [u8], amf3: &mut AMF3Decoder) -> AMFResult<
         }
 
         if (flags >> reserved) != 0 {
-            for j in reserved..6 {
-                if (flags >> j) != 0 {
+            for j in reserved..7 {
+                if (flags >> j) & 1 != 0 {
                     let (jj, value) = amf3.parse_single_element(k)?;
                     elements.push(Element {
                         name: format!("children_{}", j),
@@ -131,10 +133,13 @@ fn parse_abstract_message<'a>(i: &'a [u8], amf3: &mut AMF3Decoder) -> AMFResult<
         }
     }
 
-    Ok((i, elements))
+    Ok((k, elements))
 }
 
-fn parse_async_message<'a>(i: &'a [u8], amf3: &mut AMF3Decoder) -> AMFResult<'a, Vec<Element>> {
+pub(crate) fn parse_async_message<'a>(
+    i: &'a [u8],
+    amf3: &mut AMF3Decoder,
+) -> AMFResult<'a, Vec<Element>> {
     let (i, msg) = parse_abstract_message(i, amf3)?;
 
     let (i, flags) = parse_abstract_message_flags(i)?;
@@ -165,7 +170,7 @@ fn parse_async_message<'a>(i: &'a [u8], amf3: &mut AMF3Decoder) -> AMFResult<'a,
         }
 
         if (flags >> reserved) != 0u8 {
-            for j in reserved..6 {
+            for j in reserved..7 {
                 if (flags >> j) & 1 != 0u8 {
                     let (jj, value) = amf3.parse_single_element(k)?;
                     elements.push(Element {
@@ -181,7 +186,7 @@ fn parse_async_message<'a>(i: &'a [u8], amf3: &mut AMF3Decoder) -> AMFResult<'a,
     Ok((k, elements))
 }
 
-fn parse_acknowledge_message<'a>(
+pub(crate) fn parse_acknowledge_message<'a>(
     i: &'a [u8],
     amf3: &mut AMF3Decoder,
 ) -> AMFResult<'a, Vec<Element>> {
@@ -194,7 +199,7 @@ fn parse_acknowledge_message<'a>(
     let mut k = i;
     for flags in flags.iter() {
         if *flags != 0 {
-            for j in 0..6 {
+            for j in 0..7 {
                 if (flags >> j) & 1 != 0 {
                     let (jj, value) = amf3.parse_single_element(k)?;
                     elements.push(Element {
@@ -210,7 +215,10 @@ fn parse_acknowledge_message<'a>(
     Ok((k, elements))
 }
 
-fn parse_command_message<'a>(i: &'a [u8], amf3: &mut AMF3Decoder) -> AMFResult<'a, Vec<Element>> {
+pub(crate) fn parse_command_message<'a>(
+    i: &'a [u8],
+    amf3: &mut AMF3Decoder,
+) -> AMFResult<'a, Vec<Element>> {
     let (i, msg) = parse_async_message(i, amf3)?;
 
     let (i, flags) = parse_abstract_message_flags(i)?;
@@ -223,7 +231,7 @@ fn parse_command_message<'a>(i: &'a [u8], amf3: &mut AMF3Decoder) -> AMFResult<'
 
         if pos == 0 {
             if (flags & OPERATION_FLAG) != 0 {
-                let (j, value) = amf3.parse_single_element(i)?;
+                let (j, value) = amf3.parse_single_element(k)?;
                 elements.push(Element {
                     name: "operation".to_string(),
                     value,
@@ -234,7 +242,7 @@ fn parse_command_message<'a>(i: &'a [u8], amf3: &mut AMF3Decoder) -> AMFResult<'
         }
 
         if (flags >> reserved) != 0 {
-            for j in reserved..6 {
+            for j in reserved..7 {
                 if (flags >> j) & 1 != 0 {
                     let (jj, value) = amf3.parse_single_element(k)?;
                     elements.push(Element {
@@ -251,7 +259,10 @@ fn parse_command_message<'a>(i: &'a [u8], amf3: &mut AMF3Decoder) -> AMFResult<'
 }
 
 // all arrays
-fn parse_array_collection<'a>(i: &'a [u8], amf3: &mut AMF3Decoder) -> AMFResult<'a, Vec<Element>> {
+pub(crate) fn parse_array_collection<'a>(
+    i: &'a [u8],
+    amf3: &mut AMF3Decoder,
+) -> AMFResult<'a, Vec<Element>> {
     let (i, value) = amf3.parse_single_element(i)?;
 
     let el = vec![Element {
@@ -263,7 +274,10 @@ fn parse_array_collection<'a>(i: &'a [u8], amf3: &mut AMF3Decoder) -> AMFResult<
 }
 
 // all proxies
-fn parse_object_proxy<'a>(i: &'a [u8], amf3: &mut AMF3Decoder) -> AMFResult<'a, Vec<Element>> {
+pub(crate) fn parse_object_proxy<'a>(
+    i: &'a [u8],
+    amf3: &mut AMF3Decoder,
+) -> AMFResult<'a, Vec<Element>> {
     let (i, value) = amf3.parse_single_element(i)?;
 
     let el = vec![Element {
@@ -275,59 +289,13 @@ fn parse_object_proxy<'a>(i: &'a [u8], amf3: &mut AMF3Decoder) -> AMFResult<'a,
 }
 
 /// Register the flex decoders into the given AMF3Decoder
+#[deprecated(
+    since = "0.6.0",
+    note = "Use `register_codecs` with an `ExternalCodecRegistry`, which installs the decoders and encoders together"
+)]
 #[inline]
 pub fn register_decoders(decoder: &mut AMF3Decoder) {
-    decoder.external_decoders.insert(
-        "flex.messaging.io.AbstractMessage".to_string(),
-        Rc::new(Box::new(parse_abstract_message)),
-    );
-    decoder.external_decoders.insert(
-        "flex.messaging.io.AsyncMessage".to_string(),
-        Rc::new(Box::new(parse_async_message)),
-    );
-    decoder.external_decoders.insert(
-        "flex.messaging.io.AsyncMessageExt".to_string(),
-        Rc::new(Box::new(parse_async_message)),
-    );
-    decoder.external_decoders.insert(
-        "flex.messaging.io.AcknowledgeMessage".to_string(),
-        Rc::new(Box::new(parse_acknowledge_message)),
-    );
-    decoder.external_decoders.insert(
-        "flex.messaging.io.AcknowledgeMessageExt".to_string(),
-        Rc::new(Box::new(parse_acknowledge_message)),
-    );
-    decoder.external_decoders.insert(
-        "flex.messaging.io.CommandMessage".to_string(),
-        Rc::new(Box::new(parse_command_message)),
-    );
-    decoder.external_decoders.insert(
-        "flex.messaging.io.CommandMessageExt".to_string(),
-        Rc::new(Box::new(parse_command_message)),
-    );
-    decoder.external_decoders.insert(
-        "flex.messaging.io.ErrorMessage".to_string(),
-        Rc::new(Box::new(parse_acknowledge_message)),
-    );
-
-    decoder.external_decoders.insert(
-        "flex.messaging.io.ArrayCollection".to_string(),
-        Rc::new(Box::new(parse_array_collection)),
-    );
-    decoder.external_decoders.insert(
-        "flex.messaging.io.ArrayList".to_string(),
-        Rc::new(Box::new(parse_array_collection)),
-    );
-    decoder.external_decoders.insert(
-        "flex.messaging.io.ObjectProxy".to_string(),
-        Rc::new(Box::new(parse_object_proxy)),
-    );
-    decoder.external_decoders.insert(
-        "flex.messaging.io.ManagedObjectProxy".to_string(),
-        Rc::new(Box::new(parse_object_proxy)),
-    );
-    decoder.external_decoders.insert(
-        "flex.messaging.io.SerializationProxy".to_string(),
-        Rc::new(Box::new(parse_object_proxy)),
-    );
+    let mut registry = ExternalCodecRegistry::new();
+    register_codecs(&mut registry);
+    registry.install_decoder(decoder);
 }
@@ -0,0 +1,46 @@
+//! Conversion of flex UUIDs between the canonical string form and the compact 16 byte form
+
+/// The number of bytes in a UUID
+pub const UUID_LENGTH: usize = 16;
+
+/// The offsets of the `-` separators in the canonical string form
+const SEPARATORS: [usize; 4] = [8, 13, 18, 23];
+
+/// Convert the 16 byte form of a UUID to the canonical form, e.g. `CA5B3C0E-5A8B-4F4D-A5B6-0F7B2B1B8B6C`
+///
+/// Flex uses upper case hex digits, returns None if the input is not 16 bytes long
+pub fn bytes_to_string(bytes: &[u8]) -> Option<String> {
+    if bytes.len() != UUID_LENGTH {
+        return None;
+    }
+
+    let mut out = String::with_capacity(36);
+    for (index, byte) in bytes.iter().enumerate() {
+        if matches!(index, 4 | 6 | 8 | 10) {
+            out.push('-');
+        }
+        out.push_str(&format!("{:02X}", byte));
+    }
+    Some(out)
+}
+
+/// Convert the canonical form of a UUID to the 16 byte form, hex digits may be upper or lower case
+///
+/// Returns None if the input is not a canonical UUID
+pub fn string_to_bytes(s: &str) -> Option<[u8; UUID_LENGTH]> {
+    if s.len() != 36 || SEPARATORS.iter().any(|&i| s.as_bytes()[i] != b'-') {
+        return None;
+    }
+
+    let digits: Vec<u8> = s.bytes().filter(|&c| c != b'-').collect();
+    if digits.len() != UUID_LENGTH * 2 || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+
+    let mut out = [0; UUID_LENGTH];
+    for (byte, pair) in out.iter_mut().zip(digits.chunks(2)) {
+        let pair = std::str::from_utf8(pair).ok()?;
+        *byte = u8::from_str_radix(pair, 16).ok()?;
+    }
+    Some(out)
+}
@@ -1,28 +1,39 @@
 //! Handles encoding of flex types
 
-use crate::amf3::custom_encoder::CustomEncoder;
+use crate::amf3::custom_encoder::ExternalCodecRegistry;
 use crate::amf3::write::AMF3Encoder;
+use crate::extra::flex::uuid;
 use crate::extra::flex::{
-    BODY_FLAG, CLIENT_ID_BYTES_FLAG, CLIENT_ID_FLAG, CORRELATION_ID_BYTES_FLAG,
-    CORRELATION_ID_FLAG, DESTINATION_ID_FLAG, HEADERS_FLAG, MESSAGE_ID_BYTES_FLAG, MESSAGE_ID_FLAG,
-    NEXT_FLAG, OPERATION_FLAG, TIMESTAMP_FLAG, TTL_FLAG,
+    register_codecs, register_codecs_compact, BODY_FLAG, CLIENT_ID_BYTES_FLAG, CLIENT_ID_FLAG,
+    CORRELATION_ID_BYTES_FLAG, CORRELATION_ID_FLAG, DESTINATION_ID_FLAG, HEADERS_FLAG,
+    MESSAGE_ID_BYTES_FLAG, MESSAGE_ID_FLAG, NEXT_FLAG, OPERATION_FLAG, TIMESTAMP_FLAG, TTL_FLAG,
 };
-use crate::types::{ClassDefinition, Element};
+use crate::types::{Element, Value};
 use crate::write::WriteExt;
-use std::io::Write;
+use std::io::{Error, ErrorKind, Result, Write};
+use std::rc::Rc;
 
-struct ArrayCollection;
+/// Encodes the external data of a flex type
+pub(crate) trait FlexEncoder {
+    /// Encode the given elements, returning an error if they can't be written
+    fn encode(&self, elements: &[Element], encoder: &AMF3Encoder) -> Result<Vec<u8>>;
+}
+
+/// Get the value wrapped by an ArrayCollection or ObjectProxy
+fn wrapped_value(elements: &[Element]) -> Result<&Rc<Value>> {
+    elements
+        .first()
+        .map(|e| &e.value)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "No wrapped value to write"))
+}
+
+pub(crate) struct ArrayCollection;
 
-impl CustomEncoder for ArrayCollection {
-    fn encode(
-        &self,
-        elements: &[Element],
-        _class_def: &Option<ClassDefinition>,
-        encoder: &AMF3Encoder,
-    ) -> Vec<u8> {
+impl FlexEncoder for ArrayCollection {
+    fn encode(&self, elements: &[Element], encoder: &AMF3Encoder) -> Result<Vec<u8>> {
         let mut v = Vec::new();
-        self.do_encode(&mut v, elements, encoder).unwrap();
-        v
+        self.do_encode(&mut v, elements, encoder)?;
+        Ok(v)
     }
 }
 
@@ -32,24 +43,18 @@ impl ArrayCollection {
         writer: &mut W,
         elements: &'b [Element],
         encoder: &'a AMF3Encoder,
-    ) -> std::io::Result<()> {
-        let data = elements.first().unwrap();
-        encoder.write_value_element(writer, &data.value)
+    ) -> Result<()> {
+        encoder.write_value_element(writer, wrapped_value(elements)?)
     }
 }
 
-struct ObjectProxy;
+pub(crate) struct ObjectProxy;
 
-impl CustomEncoder for ObjectProxy {
-    fn encode(
-        &self,
-        elements: &[Element],
-        _class_def: &Option<ClassDefinition>,
-        encoder: &AMF3Encoder,
-    ) -> Vec<u8> {
+impl FlexEncoder for ObjectProxy {
+    fn encode(&self, elements: &[Element], encoder: &AMF3Encoder) -> Result<Vec<u8>> {
         let mut v = Vec::new();
-        self.do_encode(&mut v, elements, encoder).unwrap();
-        v
+        self.do_encode(&mut v, elements, encoder)?;
+        Ok(v)
     }
 }
 
@@ -59,15 +64,36 @@ impl ObjectProxy {
         writer: &mut W,
         elements: &'b [Element],
         encoder: &'a AMF3Encoder,
-    ) -> std::io::Result<()> {
-        let data = elements.first().unwrap();
-        encoder.write_value_element(writer, &data.value)
+    ) -> Result<()> {
+        encoder.write_value_element(writer, wrapped_value(elements)?)
     }
 }
 
-fn write_flags<'a, 'b: 'a, W: Write + 'a>(writer: &mut W, flags: &'a [u8]) -> std::io::Result<()> {
+/// The names of the elements containing ids that have a compact byte form
+const ID_NAMES: [&str; 3] = ["client_id", "message_id", "correlation_id"];
+
+/// Replace any ids in their canonical string form with the equivalent compact byte form
+fn compact_ids(elements: &[Element]) -> Vec<Element> {
+    elements
+        .iter()
+        .map(|e| match e.value() {
+            Value::String(s) if ID_NAMES.contains(&e.name.as_str()) => {
+                match uuid::string_to_bytes(s) {
+                    Some(bytes) => Element::new(
+                        format!("{}_bytes", e.name),
+                        Rc::new(Value::ByteArray(bytes.to_vec())),
+                    ),
+                    None => e.clone(),
+                }
+            }
+            _ => e.clone(),
+        })
+        .collect()
+}
+
+fn write_flags<'a, 'b: 'a, W: Write + 'a>(writer: &mut W, flags: &'a [u8]) -> Result<()> {
     for (index, flag) in flags.iter().enumerate() {
-        if index == flags.len() {
+        if index == flags.len() - 1 {
             writer.write_u8(*flag & !NEXT_FLAG)?;
         } else {
             writer.write_u8(*flag | NEXT_FLAG)?;
@@ -76,18 +102,19 @@ fn write_flags<'a, 'b: 'a, W: Write + 'a>(writer: &mut W, flags: &'a [u8]) -> st
     Ok(())
 }
 
-struct AbstractMessage;
+pub(crate) struct AbstractMessage {
+    pub(crate) compact_ids: bool,
+}
 
-impl CustomEncoder for AbstractMessage {
-    fn encode(
-        &self,
-        elements: &[Element],
-        _class_def: &Option<ClassDefinition>,
-        encoder: &AMF3Encoder,
-    ) -> Vec<u8> {
+impl FlexEncoder for AbstractMessage {
+    fn encode(&self, elements: &[Element], encoder: &AMF3Encoder) -> Result<Vec<u8>> {
         let mut v = Vec::new();
-        write_abstract_message(&mut v, elements, encoder).unwrap();
-        v
+        if self.compact_ids {
+            write_abstract_message(&mut v, &compact_ids(elements), encoder)?;
+        } else {
+            write_abstract_message(&mut v, elements, encoder)?;
+        }
+        Ok(v)
     }
 }
 
@@ -95,7 +122,7 @@ fn write_abstract_message<'a, 'b: 'a, W: Write + 'a>(
     writer: &mut W,
     elements: &'b [Element],
     encoder: &'a AMF3Encoder,
-) -> std::io::Result<()> {
+) -> Result<()> {
     let mut flags = Vec::new();
     let mut new_elements = Vec::new();
     {
@@ -240,18 +267,19 @@ fn write_abstract_message<'a, 'b: 'a, W: Write + 'a>(
     Ok(())
 }
 
-struct AsyncMessage;
+pub(crate) struct AsyncMessage {
+    pub(crate) compact_ids: bool,
+}
 
-impl CustomEncoder for AsyncMessage {
-    fn encode(
-        &self,
-        elements: &[Element],
-        _class_def: &Option<ClassDefinition>,
-        encoder: &AMF3Encoder,
-    ) -> Vec<u8> {
+impl FlexEncoder for AsyncMessage {
+    fn encode(&self, elements: &[Element], encoder: &AMF3Encoder) -> Result<Vec<u8>> {
         let mut v = Vec::new();
-        write_async_message(&mut v, elements, encoder).unwrap();
-        v
+        if self.compact_ids {
+            write_async_message(&mut v, &compact_ids(elements), encoder)?;
+        } else {
+            write_async_message(&mut v, elements, encoder)?;
+        }
+        Ok(v)
     }
 }
 
@@ -259,7 +287,7 @@ fn write_async_message<'a, 'b: 'a, W: Write + 'a>(
     writer: &mut W,
     elements: &'b [Element],
     encoder: &'a AMF3Encoder,
-) -> std::io::Result<()> {
+) -> Result<()> {
     let mut flags = Vec::new();
     let mut new_elements = Vec::new();
     {
@@ -334,18 +362,19 @@ fn write_async_message<'a, 'b: 'a, W: Write + 'a>(
     Ok(())
 }
 
-struct AcknowledgeMessage;
+pub(crate) struct AcknowledgeMessage {
+    pub(crate) compact_ids: bool,
+}
 
-impl CustomEncoder for AcknowledgeMessage {
-    fn encode(
-        &self,
-        elements: &[Element],
-        _class_def: &Option<ClassDefinition>,
-        encoder: &AMF3Encoder,
-    ) -> Vec<u8> {
+impl FlexEncoder for AcknowledgeMessage {
+    fn encode(&self, elements: &[Element], encoder: &AMF3Encoder) -> Result<Vec<u8>> {
         let mut v = Vec::new();
-        write_acknowledge_message(&mut v, elements, encoder).unwrap();
-        v
+        if self.compact_ids {
+            write_acknowledge_message(&mut v, &compact_ids(elements), encoder)?;
+        } else {
+            write_acknowledge_message(&mut v, elements, encoder)?;
+        }
+        Ok(v)
     }
 }
 
@@ -353,7 +382,7 @@ fn write_acknowledge_message<'a, 'b: 'a, W: Write + 'a>(
     writer: &mut W,
     elements: &'b [Element],
     encoder: &'a AMF3Encoder,
-) -> std::io::Result<()> {
+) -> Result<()> {
     let mut flags = Vec::new();
     let mut new_elements = Vec::new();
     {
@@ -385,6 +414,11 @@ fn write_acknowledge_message<'a, 'b: 'a, W: Write + 'a>(
         }
     }
 
+    // The flags are always present, even if there are no children
+    if flags.is_empty() {
+        flags.push(0);
+    }
+
     write_async_message(writer, elements, encoder)?;
     write_flags(writer, &flags)?;
     for v in new_elements {
@@ -393,18 +427,19 @@ fn write_acknowledge_message<'a, 'b: 'a, W: Write + 'a>(
     Ok(())
 }
 
-struct CommandMessage;
+pub(crate) struct CommandMessage {
+    pub(crate) compact_ids: bool,
+}
 
-impl CustomEncoder for CommandMessage {
-    fn encode(
-        &self,
-        elements: &[Element],
-        _class_def: &Option<ClassDefinition>,
-        encoder: &AMF3Encoder,
-    ) -> Vec<u8> {
+impl FlexEncoder for CommandMessage {
+    fn encode(&self, elements: &[Element], encoder: &AMF3Encoder) -> Result<Vec<u8>> {
         let mut v = Vec::new();
-        write_command_message(&mut v, elements, encoder).unwrap();
-        v
+        if self.compact_ids {
+            write_command_message(&mut v, &compact_ids(elements), encoder)?;
+        } else {
+            write_command_message(&mut v, elements, encoder)?;
+        }
+        Ok(v)
     }
 }
 
@@ -412,7 +447,7 @@ fn write_command_message<'a, 'b: 'a, W: Write + 'a>(
     writer: &mut W,
     elements: &'b [Element],
     encoder: &'a AMF3Encoder,
-) -> std::io::Result<()> {
+) -> Result<()> {
     let mut flags = Vec::new();
     let mut new_elements = Vec::new();
     {
@@ -478,70 +513,27 @@ fn write_command_message<'a, 'b: 'a, W: Write + 'a>(
 }
 
 /// Register the flex encoders into the given AMF3Encoder
+#[deprecated(
+    since = "0.6.0",
+    note = "Use `register_codecs` with an `ExternalCodecRegistry`, which installs the decoders and encoders together"
+)]
 #[inline]
 pub fn register_encoders(encoder: &mut AMF3Encoder) {
-    encoder.external_encoders.insert(
-        "flex.messaging.io.ArrayCollection".to_string(),
-        Box::new(ArrayCollection {}),
-    );
-
-    encoder.external_encoders.insert(
-        "flex.messaging.io.ArrayList".to_string(),
-        Box::new(ArrayCollection {}),
-    );
-
-    encoder.external_encoders.insert(
-        "flex.messaging.io.AbstractMessage".to_string(),
-        Box::new(AbstractMessage {}),
-    );
-
-    encoder.external_encoders.insert(
-        "flex.messaging.io.AsyncMessage".to_string(),
-        Box::new(AsyncMessage {}),
-    );
-
-    encoder.external_encoders.insert(
-        "flex.messaging.io.AsyncMessageExt".to_string(),
-        Box::new(AsyncMessage {}),
-    );
-
-    encoder.external_encoders.insert(
-        "flex.messaging.io.AcknowledgeMessage".to_string(),
-        Box::new(AcknowledgeMessage {}),
-    );
-
-    encoder.external_encoders.insert(
-        "flex.messaging.io.AcknowledgeMessageExt".to_string(),
-        Box::new(AcknowledgeMessage {}),
-    );
-
-    encoder.external_encoders.insert(
-        "flex.messaging.io.CommandMessage".to_string(),
-        Box::new(CommandMessage {}),
-    );
-
-    encoder.external_encoders.insert(
-        "flex.messaging.io.CommandMessageExt".to_string(),
-        Box::new(CommandMessage {}),
-    );
-
-    encoder.external_encoders.insert(
-        "flex.messaging.io.ErrorMessage".to_string(),
-        Box::new(AcknowledgeMessage {}),
-    );
-
-    encoder.external_encoders.insert(
-        "flex.messaging.io.ObjectProxy".to_string(),
-        Box::new(ObjectProxy {}),
-    );
-
-    encoder.external_encoders.insert(
-        "flex.messaging.io.ManagedObjectProxy".to_string(),
-        Box::new(ObjectProxy {}),
-    );
-
-    encoder.external_encoders.insert(
-        "flex.messaging.io.SerializationProxy".to_string(),
-        Box::new(ObjectProxy {}),
-    );
+    let mut registry = ExternalCodecRegistry::new();
+    register_codecs(&mut registry);
+    registry.install_encoder(encoder);
+}
+
+/// Register the flex encoders into the given AMF3Encoder, writing message ids in the compact byte form
+///
+/// Only ids in the canonical UUID form are converted, any others are written as strings
+#[deprecated(
+    since = "0.6.0",
+    note = "Use `register_codecs_compact` with an `ExternalCodecRegistry`, which installs the decoders and encoders together"
+)]
+#[inline]
+pub fn register_encoders_compact(encoder: &mut AMF3Encoder) {
+    let mut registry = ExternalCodecRegistry::new();
+    register_codecs_compact(&mut registry);
+    registry.install_encoder(encoder);
 }
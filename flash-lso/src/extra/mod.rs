@@ -1,3 +1,15 @@
 /// Reading and Writing of flex types
 #[cfg(feature = "flex")]
 pub mod flex;
+
+/// Codecs for commonly externalized ActionScript classes
+#[cfg(feature = "flex")]
+pub mod flash;
+
+/// A lightweight DOM for XML content
+#[cfg(feature = "xml")]
+pub mod xml;
+
+/// Detection and decoding of compressed AMF data stored in a `ByteArray`
+#[cfg(feature = "bytearray")]
+pub mod bytearray;
@@ -0,0 +1,192 @@
+//! A lightweight DOM for the content of `Value::XML`
+//!
+//! Content is parsed with `read::parse` and written back with `XmlDocument::to_value`, text and attribute values are
+//! stored unescaped. Whitespace is kept as text nodes, but the rest of the markup is normalized the same way Flash
+//! normalizes it when serializing XML: attribute values are written in double quotes, character and entity references
+//! are replaced with the characters they represent unless they must be escaped (`&`, `<` and `>` in text, `&`, `<`,
+//! `"` and whitespace other than spaces in attributes) and elements without children are written as empty-element
+//! tags, so `<a></a>` is written as `<a/>`
+
+use crate::types::Value;
+
+/// Reading of XML content
+pub mod read;
+
+/// Writing of XML content
+pub mod write;
+
+/// A node in an XML document
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum XmlNode {
+    /// An element, with attributes and children
+    Element(XmlElement),
+
+    /// Character data
+    Text(String),
+
+    /// A CDATA section, the content is not escaped when written
+    CData(String),
+
+    /// A comment
+    Comment(String),
+
+    /// A processing instruction or XML declaration, the content between `<?` and `?>`
+    ProcessingInstruction(String),
+
+    /// A document type declaration, the content between `<!DOCTYPE` and `>`
+    Doctype(String),
+}
+
+/// An XML element
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct XmlElement {
+    /// The name of this element, including any namespace prefix
+    pub name: String,
+
+    /// The attributes of this element as (name, value), in the order they are written
+    pub attributes: Vec<(String, String)>,
+
+    /// The children of this element
+    pub children: Vec<XmlNode>,
+}
+
+/// The content of a `Value::XML`, a sequence of top level nodes
+///
+/// AS2 `XMLDocument` content can contain multiple top level elements, while AS3 `XML` has a single root element
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct XmlDocument {
+    /// The top level nodes of this document
+    pub children: Vec<XmlNode>,
+}
+
+impl XmlElement {
+    /// Create a new element with the given name, no attributes and no children
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Get the value of the attribute `name`
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Set the value of the attribute `name`, keeping its position if it already exists
+    pub fn set_attribute(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        let value = value.into();
+        match self.attributes.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => self.attributes.push((name, value)),
+        }
+    }
+
+    /// Remove the attribute `name`, returning its value if it existed
+    pub fn remove_attribute(&mut self, name: &str) -> Option<String> {
+        let index = self.attributes.iter().position(|(n, _)| n == name)?;
+        Some(self.attributes.remove(index).1)
+    }
+
+    /// Iterate over the child elements of this element
+    pub fn elements(&self) -> impl Iterator<Item = &XmlElement> {
+        self.children.iter().filter_map(|c| match c {
+            XmlNode::Element(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// Iterate mutably over the child elements of this element
+    pub fn elements_mut(&mut self) -> impl Iterator<Item = &mut XmlElement> {
+        self.children.iter_mut().filter_map(|c| match c {
+            XmlNode::Element(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// Iterate over the child elements named `name`
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.elements().filter(move |e| e.name == name)
+    }
+
+    /// Get the first child element named `name`
+    pub fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.elements().find(|e| e.name == name)
+    }
+
+    /// Get the first child element named `name` mutably
+    pub fn child_mut(&mut self, name: &str) -> Option<&mut XmlElement> {
+        self.elements_mut().find(|e| e.name == name)
+    }
+
+    /// Get the descendant found by following a `/` separated path of child element names
+    pub fn find(&self, path: &str) -> Option<&XmlElement> {
+        path.split('/')
+            .filter(|p| !p.is_empty())
+            .try_fold(self, |e, name| e.child(name))
+    }
+
+    /// Get the descendant found by following a `/` separated path of child element names mutably
+    pub fn find_mut(&mut self, path: &str) -> Option<&mut XmlElement> {
+        path.split('/')
+            .filter(|p| !p.is_empty())
+            .try_fold(self, |e, name| e.child_mut(name))
+    }
+
+    /// Get the text and CDATA content directly inside this element
+    pub fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|c| match c {
+                XmlNode::Text(t) | XmlNode::CData(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Replace the children of this element with the given text
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.children = vec![XmlNode::Text(text.into())];
+    }
+}
+
+impl XmlDocument {
+    /// Iterate over the top level elements of this document
+    pub fn elements(&self) -> impl Iterator<Item = &XmlElement> {
+        self.children.iter().filter_map(|c| match c {
+            XmlNode::Element(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// Get the first top level element of this document
+    pub fn root(&self) -> Option<&XmlElement> {
+        self.elements().next()
+    }
+
+    /// Get the first top level element of this document mutably
+    pub fn root_mut(&mut self) -> Option<&mut XmlElement> {
+        self.children.iter_mut().find_map(|c| match c {
+            XmlNode::Element(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// Convert this document to a `Value::XML`
+    ///
+    /// `is_string` selects between AS2 `XMLDocument` (true) and AS3 `XML` (false) content, which differ in how empty
+    /// elements are written
+    pub fn to_value(&self, is_string: bool) -> Value {
+        let content = if is_string {
+            write::to_legacy_string(self)
+        } else {
+            write::to_string(self)
+        };
+        Value::XML(content, is_string)
+    }
+}
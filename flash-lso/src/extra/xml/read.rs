@@ -0,0 +1,162 @@
+//! Handles reading of XML content
+
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_till, take_till1, take_until, take_while, take_while1};
+use nom::combinator::{all_consuming, map, map_res};
+use nom::multi::many0;
+use nom::sequence::delimited;
+
+use crate::errors::Error;
+use crate::extra::xml::{XmlDocument, XmlElement, XmlNode};
+use crate::nom_utils::AMFResult;
+
+/// The maximum depth elements can be nested to, deeper content is rejected rather than risking a stack overflow
+pub const MAX_DEPTH: usize = 128;
+
+fn is_whitespace(c: u8) -> bool {
+    matches!(c, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+fn is_name_char(c: u8) -> bool {
+    !is_whitespace(c) && !matches!(c, b'<' | b'>' | b'/' | b'=' | b'"' | b'\'' | b'?' | b'!')
+}
+
+/// Replace the predefined entities and character references in `s` with the characters they represent
+///
+/// Unknown or invalid references are left as they are
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "lt" => '<',
+                "gt" => '>',
+                "amp" => '&',
+                "quot" => '"',
+                "apos" => '\'',
+                reference => {
+                    let code = match reference.strip_prefix("#x") {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => reference.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn parse_str<'a>(
+    parser: impl FnMut(&'a [u8]) -> AMFResult<'a, &'a [u8]>,
+) -> impl FnMut(&'a [u8]) -> AMFResult<'a, String> {
+    map_res(parser, |s| std::str::from_utf8(s).map(str::to_string))
+}
+
+fn parse_name(i: &[u8]) -> AMFResult<'_, String> {
+    parse_str(take_while1(is_name_char))(i)
+}
+
+fn parse_attribute(i: &[u8]) -> AMFResult<'_, (String, String)> {
+    let (i, _) = take_while1(is_whitespace)(i)?;
+    let (i, name) = parse_name(i)?;
+    let (i, _) = take_while(is_whitespace)(i)?;
+    let (i, _) = tag("=")(i)?;
+    let (i, _) = take_while(is_whitespace)(i)?;
+    let (i, value) = parse_str(alt((
+        delimited(tag("\""), take_till(|c| c == b'"'), tag("\"")),
+        delimited(tag("'"), take_till(|c| c == b'\''), tag("'")),
+    )))(i)?;
+    Ok((i, (name, unescape(&value))))
+}
+
+fn parse_element(i: &[u8], depth: usize) -> AMFResult<'_, XmlElement> {
+    let (i, _) = tag("<")(i)?;
+    let (i, name) = parse_name(i)?;
+    if depth >= MAX_DEPTH {
+        return Err(nom::Err::Failure(Error::XmlTooDeep(MAX_DEPTH)));
+    }
+    let (i, attributes) = many0(parse_attribute)(i)?;
+    let (i, _) = take_while(is_whitespace)(i)?;
+
+    if let Ok((i, _)) = tag::<_, _, Error<'_>>("/>")(i) {
+        return Ok((
+            i,
+            XmlElement {
+                name,
+                attributes,
+                children: Vec::new(),
+            },
+        ));
+    }
+
+    let (i, _) = tag(">")(i)?;
+    let (i, children) = many0(|i| parse_node(i, depth + 1))(i)?;
+    let (j, _) = tag("</")(i)?;
+    let (j, close_name) = parse_name(j)?;
+    if close_name != name {
+        return Err(nom::Err::Error(Error::Nom(i, nom::error::ErrorKind::Tag)));
+    }
+    let (j, _) = take_while(is_whitespace)(j)?;
+    let (j, _) = tag(">")(j)?;
+
+    Ok((
+        j,
+        XmlElement {
+            name,
+            attributes,
+            children,
+        },
+    ))
+}
+
+fn parse_node(i: &[u8], depth: usize) -> AMFResult<'_, XmlNode> {
+    alt((
+        map(
+            parse_str(delimited(tag("<!--"), take_until("-->"), tag("-->"))),
+            XmlNode::Comment,
+        ),
+        map(
+            parse_str(delimited(tag("<![CDATA["), take_until("]]>"), tag("]]>"))),
+            XmlNode::CData,
+        ),
+        map(
+            parse_str(delimited(tag("<!DOCTYPE"), take_until(">"), tag(">"))),
+            XmlNode::Doctype,
+        ),
+        map(
+            parse_str(delimited(tag("<?"), take_until("?>"), tag("?>"))),
+            XmlNode::ProcessingInstruction,
+        ),
+        map(|i| parse_element(i, depth), XmlNode::Element),
+        map(parse_str(take_till1(|c| c == b'<')), |text| {
+            XmlNode::Text(unescape(&text))
+        }),
+    ))(i)
+}
+
+/// Parse the content of a `Value::XML` into a document
+///
+/// Document type declarations with an internal subset are not supported, content nested deeper than `MAX_DEPTH` fails
+/// with `Error::XmlTooDeep`
+pub fn parse(content: &str) -> Result<XmlDocument, nom::Err<Error<'_>>> {
+    let (_, children) = all_consuming(many0(|i| parse_node(i, 0)))(content.as_bytes())?;
+    Ok(XmlDocument { children })
+}
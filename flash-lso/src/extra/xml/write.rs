@@ -0,0 +1,176 @@
+//! Handles writing of XML content
+
+use crate::extra::xml::{XmlDocument, XmlElement, XmlNode};
+
+fn escape_text(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn escape_attribute(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            '\t' => out.push_str("&#x9;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Write the start of an element, up to but not including the closing `>` or `/>`
+fn write_start_tag(out: &mut String, element: &XmlElement) {
+    out.push('<');
+    out.push_str(&element.name);
+    for (name, value) in &element.attributes {
+        out.push(' ');
+        out.push_str(name);
+        out.push_str("=\"");
+        escape_attribute(out, value);
+        out.push('"');
+    }
+}
+
+/// Write a node that isn't an element
+fn write_leaf(out: &mut String, node: &XmlNode) {
+    match node {
+        XmlNode::Text(text) => escape_text(out, text),
+        XmlNode::CData(data) => {
+            out.push_str("<![CDATA[");
+            out.push_str(data);
+            out.push_str("]]>");
+        }
+        XmlNode::Comment(comment) => {
+            out.push_str("<!--");
+            out.push_str(comment);
+            out.push_str("-->");
+        }
+        XmlNode::ProcessingInstruction(pi) => {
+            out.push_str("<?");
+            out.push_str(pi);
+            out.push_str("?>");
+        }
+        XmlNode::Doctype(doctype) => {
+            out.push_str("<!DOCTYPE");
+            out.push_str(doctype);
+            out.push('>');
+        }
+        XmlNode::Element(_) => unreachable!("Elements are not leaves"),
+    }
+}
+
+fn write_node(out: &mut String, node: &XmlNode, empty_tag_end: &str) {
+    match node {
+        XmlNode::Element(element) => {
+            write_start_tag(out, element);
+            if element.children.is_empty() {
+                out.push_str(empty_tag_end);
+            } else {
+                out.push('>');
+                for child in &element.children {
+                    write_node(out, child, empty_tag_end);
+                }
+                out.push_str("</");
+                out.push_str(&element.name);
+                out.push('>');
+            }
+        }
+        _ => write_leaf(out, node),
+    }
+}
+
+fn is_whitespace_text(node: &XmlNode) -> bool {
+    matches!(node, XmlNode::Text(t) if t.trim().is_empty())
+}
+
+fn write_pretty_node(out: &mut String, node: &XmlNode, depth: usize) {
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+
+    match node {
+        XmlNode::Element(element) => {
+            write_start_tag(out, element);
+            let children: Vec<&XmlNode> = element
+                .children
+                .iter()
+                .filter(|c| !is_whitespace_text(c))
+                .collect();
+
+            match children.as_slice() {
+                [] => out.push_str("/>"),
+                [XmlNode::Text(text)] => {
+                    out.push('>');
+                    escape_text(out, text.trim());
+                    out.push_str("</");
+                    out.push_str(&element.name);
+                    out.push('>');
+                }
+                children => {
+                    out.push('>');
+                    for child in children {
+                        out.push('\n');
+                        write_pretty_node(out, child, depth + 1);
+                    }
+                    out.push('\n');
+                    for _ in 0..depth {
+                        out.push_str("  ");
+                    }
+                    out.push_str("</");
+                    out.push_str(&element.name);
+                    out.push('>');
+                }
+            }
+        }
+        XmlNode::Text(text) => escape_text(out, text.trim()),
+        _ => write_leaf(out, node),
+    }
+}
+
+/// Write a document as AS3 `XML` content, the same as `XML.toXMLString` with `prettyPrinting` disabled
+///
+/// Whitespace is written as it was read, the rest of the markup is normalized as described in the module documentation
+pub fn to_string(document: &XmlDocument) -> String {
+    let mut out = String::new();
+    for node in &document.children {
+        write_node(&mut out, node, "/>");
+    }
+    out
+}
+
+/// Write a document as AS2 `XMLDocument` content, the same as `XMLDocument.toString`
+pub fn to_legacy_string(document: &XmlDocument) -> String {
+    let mut out = String::new();
+    for node in &document.children {
+        write_node(&mut out, node, " />");
+    }
+    out
+}
+
+/// Write a document as AS3 `XML` content, the same as `XML.toXMLString` with the default `prettyPrinting`
+///
+/// Existing whitespace is replaced with a newline and an indent of two spaces per level
+pub fn to_pretty_string(document: &XmlDocument) -> String {
+    let mut out = String::new();
+    for (index, node) in document
+        .children
+        .iter()
+        .filter(|c| !is_whitespace_text(c))
+        .enumerate()
+    {
+        if index > 0 {
+            out.push('\n');
+        }
+        write_pretty_node(&mut out, node, 0);
+    }
+    out
+}
@@ -0,0 +1,184 @@
+use crate::types::{Element, Value};
+use std::rc::Rc;
+
+/// Reading of FLV files
+pub mod read;
+
+/// Writing of FLV files
+pub mod write;
+
+/// The tag type used for audio data
+pub const TAG_TYPE_AUDIO: u8 = 8;
+
+/// The tag type used for video data
+pub const TAG_TYPE_VIDEO: u8 = 9;
+
+/// The tag type used for script data
+pub const TAG_TYPE_SCRIPT: u8 = 18;
+
+/// The name of the script data tag containing the metadata of a file
+pub const ON_METADATA: &str = "onMetaData";
+
+/// The name of script data tags containing cue points
+pub const ON_CUE_POINT: &str = "onCuePoint";
+
+/// The header of an FLV file
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Header {
+    /// The version of the file format, this is always 1
+    pub version: u8,
+
+    /// True if the file contains audio tags
+    pub has_audio: bool,
+
+    /// True if the file contains video tags
+    pub has_video: bool,
+}
+
+/// The contents of a script data tag, a named AMF0 value
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptData {
+    /// The name of this data, e.g. `onMetaData` or `onCuePoint`
+    pub name: String,
+
+    /// The value, usually an `ECMAArray`
+    pub value: Rc<Value>,
+}
+
+impl ScriptData {
+    /// Create new script data with the given name and value
+    pub fn new(name: impl Into<String>, value: Rc<Value>) -> Self {
+        Self {
+            name: name.into(),
+            value,
+        }
+    }
+
+    /// Get the properties of the value, if it is an object or ECMA array
+    pub fn properties(&self) -> Option<&[Element]> {
+        match self.value.as_ref() {
+            Value::ECMAArray(_, _, elements, _) | Value::Object(_, elements, _) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// Set a property of the value, replacing any existing property with the same name
+    ///
+    /// The declared length of an ECMA array is the number of entries in FLV metadata, so it is raised to count a new
+    /// property but never reduced. Does nothing if the value is not an object or ECMA array
+    pub fn set_property(&mut self, name: &str, value: Rc<Value>) {
+        match Rc::make_mut(&mut self.value) {
+            Value::ECMAArray(_, _, elements, length) => {
+                set_element(elements, name, value);
+                *length = (*length).max(elements.len() as u32);
+            }
+            Value::Object(_, elements, _) => set_element(elements, name, value),
+            _ => {}
+        }
+    }
+}
+
+fn set_element(elements: &mut Vec<Element>, name: &str, value: Rc<Value>) {
+    if let Some(element) = elements.iter_mut().find(|e| e.name == name) {
+        element.value = value;
+    } else {
+        elements.push(Element::new(name, value));
+    }
+}
+
+/// The data contained in an FLV tag
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagData {
+    /// Decoded script data
+    Script(ScriptData),
+
+    /// Audio or video data, or script data that could not be decoded
+    Raw(Vec<u8>),
+}
+
+/// A single tag in an FLV file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    /// The type of this tag, see `TAG_TYPE_AUDIO`, `TAG_TYPE_VIDEO` and `TAG_TYPE_SCRIPT`
+    /// The upper bits are preserved, these are reserved or used to indicate encrypted tags
+    pub tag_type: u8,
+
+    /// The time this tag applies to in milliseconds
+    pub timestamp: u32,
+
+    /// The stream id, this is always 0
+    pub stream_id: u32,
+
+    /// The contents of this tag
+    pub data: TagData,
+}
+
+impl Tag {
+    /// Create a new script data tag
+    pub fn script(timestamp: u32, data: ScriptData) -> Self {
+        Self {
+            tag_type: TAG_TYPE_SCRIPT,
+            timestamp,
+            stream_id: 0,
+            data: TagData::Script(data),
+        }
+    }
+
+    /// Get the script data of this tag, if it has any
+    pub fn script_data(&self) -> Option<&ScriptData> {
+        match &self.data {
+            TagData::Script(data) => Some(data),
+            TagData::Raw(_) => None,
+        }
+    }
+}
+
+/// An FLV file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flv {
+    /// The header of this file
+    pub header: Header,
+
+    /// The tags in this file, in order
+    pub tags: Vec<Tag>,
+}
+
+impl Flv {
+    /// Get the `onMetaData` script data, if present
+    pub fn metadata(&self) -> Option<&ScriptData> {
+        self.tags
+            .iter()
+            .filter_map(Tag::script_data)
+            .find(|d| d.name == ON_METADATA)
+    }
+
+    /// Get the `onMetaData` script data, inserting an empty one as the first tag if not present
+    pub fn metadata_mut(&mut self) -> &mut ScriptData {
+        let position = self
+            .tags
+            .iter()
+            .position(|t| t.script_data().is_some_and(|d| d.name == ON_METADATA));
+
+        let position = position.unwrap_or_else(|| {
+            let empty = Value::ECMAArray(crate::types::ObjectId::INVALID, vec![], vec![], 0);
+            self.tags.insert(
+                0,
+                Tag::script(0, ScriptData::new(ON_METADATA, Rc::new(empty))),
+            );
+            0
+        });
+
+        match &mut self.tags[position].data {
+            TagData::Script(data) => data,
+            TagData::Raw(_) => unreachable!("Metadata tag is not script data"),
+        }
+    }
+
+    /// Get every `onCuePoint` script data, along with the timestamp of the tag that contains it
+    pub fn cue_points(&self) -> impl Iterator<Item = (u32, &ScriptData)> {
+        self.tags.iter().filter_map(|t| match &t.data {
+            TagData::Script(data) if data.name == ON_CUE_POINT => Some((t.timestamp, data)),
+            _ => None,
+        })
+    }
+}
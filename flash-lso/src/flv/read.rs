@@ -0,0 +1,108 @@
+//! Handles reading of FLV files
+
+use nom::bytes::complete::{tag, take};
+use nom::combinator::all_consuming;
+use nom::multi::many0;
+use nom::number::complete::{be_u24, be_u32, be_u8};
+
+use crate::amf0::read::AMF0Decoder;
+use crate::errors::Error;
+use crate::flv::{Flv, Header, ScriptData, Tag, TagData, TAG_TYPE_SCRIPT};
+use crate::nom_utils::AMFResult;
+use crate::types::Value;
+
+const SIGNATURE: &[u8] = b"FLV";
+
+/// The size of the header in version 1 files
+const HEADER_SIZE: u32 = 9;
+
+const FLAG_AUDIO: u8 = 0b100;
+const FLAG_VIDEO: u8 = 0b001;
+
+/// Set on tags that are encrypted
+const FLAG_FILTER: u8 = 0b100000;
+
+fn parse_header(i: &[u8]) -> AMFResult<'_, Header> {
+    let (i, _) = tag(SIGNATURE)(i)?;
+    let (i, version) = be_u8(i)?;
+    let (i, flags) = be_u8(i)?;
+    let (i, data_offset) = be_u32(i)?;
+
+    // Skip any extra header data added by later versions
+    let extra = data_offset
+        .checked_sub(HEADER_SIZE)
+        .ok_or(nom::Err::Error(Error::Nom(
+            i,
+            nom::error::ErrorKind::LengthValue,
+        )))?;
+    let (i, _) = take(extra)(i)?;
+
+    Ok((
+        i,
+        Header {
+            version,
+            has_audio: flags & FLAG_AUDIO != 0,
+            has_video: flags & FLAG_VIDEO != 0,
+        },
+    ))
+}
+
+/// Decode the contents of a script data tag, a name followed by a single value
+fn parse_script_data(i: &[u8]) -> Option<ScriptData> {
+    let mut decoder = AMF0Decoder::default();
+
+    let (i, name) = decoder.parse_single_element(i).ok()?;
+    let (_, value) = all_consuming(|i| decoder.parse_single_element(i))(i).ok()?;
+
+    match name.as_ref() {
+        Value::String(name) => Some(ScriptData::new(name.clone(), value)),
+        _ => None,
+    }
+}
+
+fn parse_tag(i: &[u8]) -> AMFResult<'_, Tag> {
+    let (i, tag_type) = be_u8(i)?;
+    let (i, data_size) = be_u24(i)?;
+    let (i, timestamp) = be_u24(i)?;
+    let (i, timestamp_extended) = be_u8(i)?;
+    let (i, stream_id) = be_u24(i)?;
+    let (i, data) = take(data_size)(i)?;
+    let (i, _previous_tag_size) = be_u32(i)?;
+
+    let is_script = tag_type & 0b11111 == TAG_TYPE_SCRIPT && tag_type & FLAG_FILTER == 0;
+    let data = match is_script.then(|| parse_script_data(data)).flatten() {
+        Some(script) => TagData::Script(script),
+        None => TagData::Raw(data.to_vec()),
+    };
+
+    Ok((
+        i,
+        Tag {
+            tag_type,
+            timestamp: ((timestamp_extended as u32) << 24) | timestamp,
+            stream_id,
+            data,
+        },
+    ))
+}
+
+/// Read a given buffer as an FLV file
+///
+/// Unlike parse, this function will not error if the entire slice isn't consumed
+/// and will return the data that was not parsed
+pub fn parse_incomplete(i: &[u8]) -> AMFResult<'_, Flv> {
+    let (i, header) = parse_header(i)?;
+    let (i, _previous_tag_size) = be_u32(i)?;
+    let (i, tags) = many0(parse_tag)(i)?;
+
+    Ok((i, Flv { header, tags }))
+}
+
+/// Read a given slice as an FLV file
+///
+/// This function will return an error if the slice could not be parsed or if the entire slice
+/// was not consumed
+pub fn parse(i: &[u8]) -> Result<Flv, nom::Err<Error<'_>>> {
+    let (_, flv) = all_consuming(parse_incomplete)(i)?;
+    Ok(flv)
+}
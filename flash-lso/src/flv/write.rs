@@ -0,0 +1,67 @@
+//! Handles writing of FLV files
+
+use crate::amf0;
+use crate::errors::Error;
+use crate::flv::{Flv, Tag, TagData};
+use crate::types::Value;
+use std::rc::Rc;
+
+/// The largest data size that can be stored in a tag (u24)
+const MAX_DATA_SIZE: usize = 0xFFFFFF;
+
+fn write_tag(tag: &Tag, out: &mut Vec<u8>) -> Result<(), Error<'static>> {
+    let mut data = vec![];
+    match &tag.data {
+        TagData::Script(script) => {
+            let name = Rc::new(Value::String(script.name.clone()));
+            amf0::write::write_value(&mut data, &name)
+                .and_then(|_| amf0::write::write_value(&mut data, &script.value))
+                .map_err(|e| Error::IoError(e.to_string(), e.kind()))?;
+        }
+        TagData::Raw(raw) => data.extend_from_slice(raw),
+    }
+
+    if data.len() > MAX_DATA_SIZE {
+        return Err(Error::TagTooLarge);
+    }
+
+    out.push(tag.tag_type);
+    out.extend(&(data.len() as u32).to_be_bytes()[1..]);
+    out.extend(&(tag.timestamp & 0xFFFFFF).to_be_bytes()[1..]);
+    out.push((tag.timestamp >> 24) as u8);
+    out.extend(&(tag.stream_id & 0xFFFFFF).to_be_bytes()[1..]);
+    out.append(&mut data);
+
+    Ok(())
+}
+
+/// Write the given FLV file to bytes
+///
+/// Files are always written with a 9 byte header
+pub fn write_to_bytes(flv: &Flv) -> Result<Vec<u8>, Error<'static>> {
+    let mut out = vec![];
+
+    out.extend(b"FLV");
+    out.push(flv.header.version);
+    let mut flags = 0;
+    if flv.header.has_audio {
+        flags |= 0b100;
+    }
+    if flv.header.has_video {
+        flags |= 0b001;
+    }
+    out.push(flags);
+    out.extend(9u32.to_be_bytes());
+
+    // The first previous tag size is always 0
+    out.extend(0u32.to_be_bytes());
+
+    for tag in &flv.tags {
+        let start = out.len();
+        write_tag(tag, &mut out)?;
+        // Previous tag size, includes the 11 byte tag header
+        out.extend(((out.len() - start) as u32).to_be_bytes());
+    }
+
+    Ok(out)
+}
@@ -41,6 +41,9 @@ pub mod errors;
 /// Private internal utils for reading
 mod nom_utils;
 
+/// Private base64 encoding and decoding
+mod base64;
+
 /// Reading of the Lso container format
 pub mod read;
 
@@ -55,3 +58,18 @@ pub mod extra;
 
 /// Reading and Writing of AMF Self Contained Packets
 pub mod packet;
+
+/// Reading and Writing of FLV script data tags
+pub mod flv;
+
+/// Reading and Writing of RTMP command messages
+pub mod rtmp;
+
+/// Reading and Writing of remote shared object messages
+pub mod rso;
+
+/// Calling and serving AMF Remoting gateways
+pub mod remoting;
+
+/// Locating Local Shared Objects stored on disk by Flash Player, AIR and Ruffle
+pub mod store;
@@ -1,4 +1,5 @@
-use crate::errors::Error;
+use crate::errors::{Diagnostic, Error};
+use crate::types::Element;
 use nom::bytes::complete::take;
 use nom::combinator::map_res;
 
@@ -20,3 +21,81 @@ pub(crate) fn write_string<'a, 'b: 'a, W: Write + 'a>(
 pub(crate) fn take_str(i: &[u8], length: u16) -> AMFResult<'_, &str> {
     map_res(take(length), std::str::from_utf8)(i)
 }
+
+/// A decoder for the root elements of an Lso body that can be rolled back after a failed read
+pub(crate) trait BodyDecoder {
+    /// The state needed to undo any changes made by a failed read
+    type State;
+
+    /// Save the current state of this decoder
+    fn save_state(&self) -> Self::State;
+
+    /// Undo any changes made since the given state was saved
+    fn restore_state(&mut self, state: &Self::State);
+
+    /// Parse a single root element and the padding that follows it
+    fn parse_root_element<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Element>;
+}
+
+pub(crate) fn describe_error(e: &nom::Err<Error<'_>>) -> String {
+    match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => e.describe(),
+        nom::Err::Incomplete(_) => "Incomplete data".to_string(),
+    }
+}
+
+/// Parse root elements until the end of the input, skipping over any elements that can't be decoded
+///
+/// After a failure, every following offset is tried until a non-empty named element can be read,
+/// any state changes made by failed attempts are rolled back
+pub(crate) fn parse_body_recover<D: BodyDecoder>(
+    decoder: &mut D,
+    i: &[u8],
+) -> (Vec<Element>, Vec<Diagnostic>) {
+    let mut elements = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    let mut offset = 0;
+    while offset < i.len() {
+        let error = match decoder.parse_root_element(&i[offset..]) {
+            Ok((rest, element)) => {
+                elements.push(element);
+                offset = i.len() - rest.len();
+                continue;
+            }
+            Err(e) => e,
+        };
+
+        // Look for the next offset that contains a valid element
+        let state = decoder.save_state();
+        let mut resync = None;
+        for candidate in offset + 1..i.len() {
+            match decoder.parse_root_element(&i[candidate..]) {
+                Ok((rest, element)) if !element.name.is_empty() => {
+                    resync = Some((candidate, rest, element));
+                    break;
+                }
+                _ => decoder.restore_state(&state),
+            }
+        }
+
+        let skip_end = resync
+            .as_ref()
+            .map_or(i.len(), |(candidate, _, _)| *candidate);
+        diagnostics.push(Diagnostic {
+            offset,
+            length: skip_end - offset,
+            message: describe_error(&error),
+        });
+
+        match resync {
+            Some((_, rest, element)) => {
+                elements.push(element);
+                offset = i.len() - rest.len();
+            }
+            None => break,
+        }
+    }
+
+    (elements, diagnostics)
+}
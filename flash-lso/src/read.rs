@@ -8,7 +8,7 @@ use crate::amf0;
 use crate::amf0::read::AMF0Decoder;
 #[cfg(feature = "amf3")]
 use crate::amf3::read::AMF3Decoder;
-use crate::errors::Error;
+use crate::errors::{Diagnostic, Error};
 use crate::nom_utils::AMFResult;
 use crate::types::{AMFVersion, Header, Lso};
 use nom::combinator::all_consuming;
@@ -22,6 +22,9 @@ const FORMAT_VERSION_AMF0: u8 = 0x0;
 #[cfg(feature = "amf3")]
 const FORMAT_VERSION_AMF3: u8 = 0x3;
 
+/// The number of bytes before the data covered by the length in the header (version + length)
+const LENGTH_OFFSET: usize = HEADER_VERSION.len() + 4;
+
 /// The main entry point of decoding an LSO file
 /// Example of use
 /// ```
@@ -46,6 +49,17 @@ pub struct Reader {
 }
 
 impl Reader {
+    /// Enable or disable strict mode for every decoder used by this reader
+    ///
+    /// In strict mode data that doesn't conform to the specification will be rejected, see `AMF0Decoder::strict`
+    pub fn set_strict(&mut self, strict: bool) {
+        self.amf0_decoder.strict = strict;
+        #[cfg(feature = "amf3")]
+        {
+            self.amf3_decoder.strict = strict;
+        }
+    }
+
     /// Read a Lso header from the given slice
     pub fn parse_header<'a>(&self, i: &'a [u8]) -> AMFResult<'a, Header> {
         let (i, _) = tag(HEADER_VERSION)(i)?;
@@ -77,6 +91,30 @@ impl Reader {
         ))
     }
 
+    /// Check that the length declared in the header of the given slice matches the length of the slice
+    ///
+    /// Returns `Error::TruncatedFile` if the slice is shorter than declared or `Error::TrailingData` if it is longer
+    pub fn validate_length<'a>(&self, i: &'a [u8]) -> AMFResult<'a, Header> {
+        let (j, header) = self.parse_header(i)?;
+
+        let available = i.len() - LENGTH_OFFSET;
+        let declared = header.length as usize;
+        if declared > available {
+            return Err(nom::Err::Error(Error::TruncatedFile(
+                header.length,
+                available,
+            )));
+        }
+        if declared < available {
+            return Err(nom::Err::Error(Error::TrailingData(
+                header.length,
+                available,
+            )));
+        }
+
+        Ok((j, header))
+    }
+
     /// Read a given buffer as an Lso
     ///
     /// Unlike parse, this function will not error if the entire slice isn't consumed
@@ -105,4 +143,66 @@ impl Reader {
         let (_, lso) = all_consuming(|i| self.parse_incomplete(i))(i)?;
         Ok(lso)
     }
+
+    /// Read a given slice as an Lso, recovering from errors in the body where possible
+    ///
+    /// Root elements that can't be decoded are skipped and decoding resumes at the next readable root element,
+    /// each skipped region is reported as a `Diagnostic` with an offset into `i`
+    /// Errors in the header can't be recovered from and are returned as normal
+    pub fn parse_recover<'a>(
+        &mut self,
+        i: &'a [u8],
+    ) -> Result<(Lso, Vec<Diagnostic>), nom::Err<Error<'a>>> {
+        let (body, header) = self.parse_header(i)?;
+        let (elements, mut diagnostics) = match header.format_version {
+            AMFVersion::AMF0 => self.amf0_decoder.parse_body_recover(body),
+            #[cfg(feature = "amf3")]
+            AMFVersion::AMF3 => self.amf3_decoder.parse_body_recover(body),
+        };
+
+        let header_length = i.len() - body.len();
+        for diagnostic in diagnostics.iter_mut() {
+            diagnostic.offset += header_length;
+        }
+
+        Ok((
+            Lso {
+                header,
+                body: elements,
+            },
+            diagnostics,
+        ))
+    }
+
+    /// Read a given slice as an Lso, after checking that the length declared in the header matches the slice
+    ///
+    /// See `validate_length` for the errors returned if the lengths differ
+    pub fn parse_checked<'a>(&mut self, i: &'a [u8]) -> Result<Lso, nom::Err<Error<'a>>> {
+        self.validate_length(i)?;
+        self.parse(i)
+    }
+
+    /// Read a given slice as an Lso, only parsing up to the length declared in the header
+    ///
+    /// Returns the Lso and any bytes found after the declared length
+    /// If the slice is shorter than the declared length `Error::TruncatedFile` is returned
+    pub fn parse_lenient<'a>(
+        &mut self,
+        i: &'a [u8],
+    ) -> Result<(Lso, &'a [u8]), nom::Err<Error<'a>>> {
+        let (_, header) = self.parse_header(i)?;
+
+        let available = i.len() - LENGTH_OFFSET;
+        let declared = header.length as usize;
+        if declared > available {
+            return Err(nom::Err::Error(Error::TruncatedFile(
+                header.length,
+                available,
+            )));
+        }
+
+        let (data, trailing) = i.split_at(LENGTH_OFFSET + declared);
+        let lso = self.parse(data)?;
+        Ok((lso, trailing))
+    }
 }
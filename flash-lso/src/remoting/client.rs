@@ -0,0 +1,272 @@
+use std::io;
+#[cfg(feature = "http")]
+use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(feature = "http")]
+use std::net::TcpStream;
+use std::rc::Rc;
+
+use crate::nom_utils::describe_error;
+use crate::packet::{Header, Message, Packet};
+use crate::remoting::{CONTENT_TYPE, ON_RESULT, ON_STATUS};
+use crate::types::{AMFVersion, ObjectId, Value};
+
+/// Sends a request body to a gateway and returns the response body
+///
+/// `HttpTransport` is available with the `http` feature, implement this to use a different HTTP client, such as one
+/// supporting HTTPS, or to test against a local stand-in for a gateway
+pub trait Transport {
+    /// Send the given body with the given content type, returning the body of the response
+    fn send(&mut self, content_type: &str, body: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+/// A minimal HTTP/1.1 transport for `http://` URLs
+///
+/// A new connection is made for every request, HTTPS is not supported
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+#[cfg(feature = "http")]
+impl HttpTransport {
+    /// Create a transport that will POST to the given `http://` URL
+    pub fn new(url: &str) -> io::Result<Self> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Only http:// URLs are supported",
+            )
+        })?;
+
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => {
+                let port = port
+                    .parse()
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid port"))?;
+                (host, port)
+            }
+            None => (authority, 80),
+        };
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[cfg(feature = "http")]
+fn read_chunked(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let size = line.trim().split(';').next().unwrap_or_default();
+        let size =
+            usize::from_str_radix(size, 16).map_err(|_| invalid_data("Invalid chunk size"))?;
+        if size == 0 {
+            return Ok(body);
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        // Chunks are followed by CRLF
+        reader.read_line(&mut line)?;
+    }
+}
+
+#[cfg(feature = "http")]
+impl Transport for HttpTransport {
+    fn send(&mut self, content_type: &str, body: &[u8]) -> io::Result<Vec<u8>> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.path,
+            self.host,
+            self.port,
+            content_type,
+            body.len()
+        )?;
+        stream.write_all(body)?;
+        stream.flush()?;
+
+        let mut reader = BufReader::new(stream);
+        let mut status = String::new();
+        reader.read_line(&mut status)?;
+        let code = status.split_whitespace().nth(1).unwrap_or_default();
+        if code != "200" {
+            return Err(io::Error::other(format!(
+                "Gateway responded with '{}'",
+                status.trim()
+            )));
+        }
+
+        let mut content_length = None;
+        let mut chunked = false;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                let value = value.trim();
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length =
+                        Some(value.parse().map_err(|_| invalid_data("Invalid length"))?);
+                } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                    chunked = value.eq_ignore_ascii_case("chunked");
+                }
+            }
+        }
+
+        let mut response = Vec::new();
+        if chunked {
+            response = read_chunked(&mut reader)?;
+        } else if let Some(length) = content_length {
+            response.resize(length, 0);
+            reader.read_exact(&mut response)?;
+        } else {
+            reader.read_to_end(&mut response)?;
+        }
+        Ok(response)
+    }
+}
+
+/// A call to a service method on the gateway
+#[derive(Debug, Clone)]
+pub struct Call {
+    /// The service and method to call, e.g. `Service.method`
+    pub target_uri: String,
+
+    /// The arguments to pass to the method
+    pub arguments: Vec<Rc<Value>>,
+}
+
+impl Call {
+    /// Create a new call to the given target
+    pub fn new(target_uri: impl Into<String>, arguments: Vec<Rc<Value>>) -> Self {
+        Self {
+            target_uri: target_uri.into(),
+            arguments,
+        }
+    }
+}
+
+/// The response to a single call
+#[derive(Debug, Clone, PartialEq)]
+pub enum Response {
+    /// The call succeeded (`/onResult`) and returned the given value
+    Result(Rc<Value>),
+
+    /// The call failed (`/onStatus`), the value usually describes the error
+    Status(Rc<Value>),
+}
+
+/// A blocking client for an AMF Remoting gateway
+pub struct Client<T: Transport> {
+    transport: T,
+
+    /// The version of the packets sent to the gateway
+    pub version: AMFVersion,
+
+    /// Headers sent with every request, such as credentials
+    pub headers: Vec<Header>,
+
+    /// The number used for the response URI of the next call
+    next_response: u32,
+}
+
+#[cfg(feature = "http")]
+impl Client<HttpTransport> {
+    /// Create a client for the gateway at the given `http://` URL
+    pub fn connect(url: &str) -> io::Result<Self> {
+        Ok(Self::new(HttpTransport::new(url)?))
+    }
+}
+
+impl<T: Transport> Client<T> {
+    /// Create a client that sends requests using the given transport
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            version: AMFVersion::AMF0,
+            headers: Vec::new(),
+            next_response: 1,
+        }
+    }
+
+    /// Get the transport used by this client
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Call a single method on the gateway
+    pub fn call(
+        &mut self,
+        target_uri: impl Into<String>,
+        arguments: Vec<Rc<Value>>,
+    ) -> io::Result<Response> {
+        let mut responses = self.call_batch(vec![Call::new(target_uri, arguments)])?;
+        Ok(responses.remove(0))
+    }
+
+    /// Send several calls to the gateway in a single request
+    ///
+    /// Responses are returned in the same order as the calls, an error is returned if any call has no response
+    pub fn call_batch(&mut self, calls: Vec<Call>) -> io::Result<Vec<Response>> {
+        let mut response_uris = Vec::with_capacity(calls.len());
+        let mut messages = Vec::with_capacity(calls.len());
+        for call in calls {
+            let response_uri = format!("/{}", self.next_response);
+            self.next_response += 1;
+
+            messages.push(Message {
+                target_uri: call.target_uri,
+                response_uri: response_uri.clone(),
+                contents: Rc::new(Value::StrictArray(ObjectId::INVALID, call.arguments)),
+            });
+            response_uris.push(response_uri);
+        }
+
+        let request = Packet {
+            version: self.version,
+            headers: self.headers.clone(),
+            messages,
+        };
+        let body = crate::packet::write::write_to_bytes(&request, true)
+            .map_err(|e| invalid_data(e.to_string()))?;
+
+        let response = self.transport.send(CONTENT_TYPE, &body)?;
+        let response =
+            crate::packet::read::parse(&response).map_err(|e| invalid_data(describe_error(&e)))?;
+
+        response_uris
+            .iter()
+            .map(|uri| {
+                response
+                    .messages
+                    .iter()
+                    .find_map(|m| match m.target_uri.strip_prefix(uri.as_str()) {
+                        Some(ON_RESULT) => Some(Response::Result(Rc::clone(&m.contents))),
+                        Some(ON_STATUS) => Some(Response::Status(Rc::clone(&m.contents))),
+                        _ => None,
+                    })
+                    .ok_or_else(|| invalid_data(format!("No response for '{}'", uri)))
+            })
+            .collect()
+    }
+}
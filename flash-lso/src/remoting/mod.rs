@@ -0,0 +1,19 @@
+//! Support for AMF Remoting, calling services on a gateway using AMF packets sent over HTTP
+//!
+//! Each message in a request packet has a unique response URI, e.g. `/1`, and the gateway responds with a message
+//! targeting that URI suffixed with `/onResult` on success or `/onStatus` on failure.
+
+/// A blocking client for calling services on a gateway
+pub mod client;
+
+/// A dispatcher for handling requests to a gateway
+pub mod server;
+
+/// The content type used for AMF packets sent over HTTP
+pub const CONTENT_TYPE: &str = "application/x-amf";
+
+/// The suffix of the target URI of responses to successful calls
+pub const ON_RESULT: &str = "/onResult";
+
+/// The suffix of the target URI of responses to failed calls
+pub const ON_STATUS: &str = "/onStatus";
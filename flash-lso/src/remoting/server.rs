@@ -0,0 +1,337 @@
+#[cfg(feature = "flex")]
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+#[cfg(feature = "flex")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "flex")]
+use crate::base64;
+use crate::errors::Error;
+#[cfg(feature = "flex")]
+use crate::extra::flex::messages::{
+    AbstractMessage, AcknowledgeMessage, AsyncMessage, CommandMessage, CommandOperation,
+    ErrorMessage, RemotingMessage, Uuid,
+};
+use crate::packet::{Header, Message, Packet};
+use crate::remoting::{ON_RESULT, ON_STATUS};
+use crate::types::{Element, ObjectId, Value};
+
+/// A function that handles calls to a service method
+///
+/// Takes the headers of the request and the arguments of the call, returns the result on success or a value
+/// describing the error on failure
+pub type Handler = Box<dyn Fn(&[Header], &[Rc<Value>]) -> Result<Rc<Value>, Rc<Value>>>;
+
+/// A function that checks the username and password of a Flex login
+#[cfg(feature = "flex")]
+pub type LoginHandler = Box<dyn Fn(&str, &str) -> bool>;
+
+/// Routes the messages of request packets to registered handlers
+///
+/// Handlers are registered by target URI, e.g. `Service.method`. With the `flex` feature, Flex remoting messages are
+/// routed using `destination.operation`.
+#[derive(Default)]
+pub struct Dispatcher {
+    handlers: HashMap<String, Handler>,
+    understood_headers: HashSet<String>,
+    #[cfg(feature = "flex")]
+    login: Option<LoginHandler>,
+    #[cfg(feature = "flex")]
+    message_count: Cell<u64>,
+}
+
+/// Get the inner value of values that were switched to AMF3
+#[cfg(feature = "flex")]
+fn unwrap_amf3(value: &Rc<Value>) -> &Rc<Value> {
+    match value.as_ref() {
+        #[cfg(feature = "amf3")]
+        Value::AMF3(inner) => inner,
+        _ => value,
+    }
+}
+
+fn status_object(code: &str, description: &str) -> Rc<Value> {
+    Rc::new(Value::Object(
+        ObjectId::INVALID,
+        vec![
+            Element::new("level", Rc::new(Value::String("error".to_string()))),
+            Element::new("code", Rc::new(Value::String(code.to_string()))),
+            Element::new(
+                "description",
+                Rc::new(Value::String(description.to_string())),
+            ),
+        ],
+        None,
+    ))
+}
+
+/// Wrap the standard form of a Flex message for sending in an AMF0 packet
+#[cfg(feature = "flex")]
+fn flex_message(message: Value) -> Rc<Value> {
+    let message = Rc::new(message);
+
+    #[cfg(feature = "amf3")]
+    let message = Rc::new(Value::AMF3(message));
+
+    message
+}
+
+impl Dispatcher {
+    /// Create a dispatcher with no handlers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a handler for calls to the given target, e.g. `Service.method`
+    pub fn register(
+        &mut self,
+        target: impl Into<String>,
+        handler: impl Fn(&[Header], &[Rc<Value>]) -> Result<Rc<Value>, Rc<Value>> + 'static,
+    ) {
+        self.handlers.insert(target.into(), Box::new(handler));
+    }
+
+    /// Mark the header with the given name as understood
+    ///
+    /// Requests containing a `must_understand` header that has not been marked as understood fail without
+    /// calling any handlers
+    pub fn understand_header(&mut self, name: impl Into<String>) {
+        self.understood_headers.insert(name.into());
+    }
+
+    fn dispatch_message(
+        &self,
+        headers: &[Header],
+        message: &Message,
+    ) -> Result<Rc<Value>, Rc<Value>> {
+        let arguments = match message.contents.as_ref() {
+            Value::StrictArray(_, arguments) => arguments.clone(),
+            _ => vec![Rc::clone(&message.contents)],
+        };
+
+        // Flex clients send a single message object as the only argument
+        #[cfg(feature = "flex")]
+        if let [argument] = arguments.as_slice() {
+            let argument = unwrap_amf3(argument).as_ref();
+            if let Ok(command) = CommandMessage::try_from(argument) {
+                return self.dispatch_command(command);
+            }
+            if let Ok(remoting) = RemotingMessage::try_from(argument) {
+                return self.dispatch_remoting(headers, remoting);
+            }
+        }
+
+        match self.handlers.get(&message.target_uri) {
+            Some(handler) => handler(headers, &arguments),
+            None => Err(status_object(
+                "Server.ResourceUnavailable",
+                &format!("No handler for '{}'", message.target_uri),
+            )),
+        }
+    }
+
+    /// Call the handlers for every message in the given request, returning the response packet
+    pub fn dispatch(&self, request: &Packet) -> Packet {
+        let not_understood = request
+            .headers
+            .iter()
+            .find(|h| h.must_understand && !self.understood_headers.contains(&h.name));
+
+        let messages = request
+            .messages
+            .iter()
+            .map(|message| {
+                let result = match not_understood {
+                    Some(header) => Err(status_object(
+                        "Server.Processing",
+                        &format!("Header '{}' was not understood", header.name),
+                    )),
+                    None => self.dispatch_message(&request.headers, message),
+                };
+
+                let (suffix, contents) = match result {
+                    Ok(value) => (ON_RESULT, value),
+                    Err(value) => (ON_STATUS, value),
+                };
+                Message {
+                    target_uri: format!("{}{}", message.response_uri, suffix),
+                    response_uri: "null".to_string(),
+                    contents,
+                }
+            })
+            .collect();
+
+        Packet {
+            version: request.version,
+            headers: vec![],
+            messages,
+        }
+    }
+
+    /// Handle the body of a request, returning the body of the response
+    pub fn handle<'a>(&self, body: &'a [u8]) -> Result<Vec<u8>, nom::Err<Error<'a>>> {
+        let request = crate::packet::read::parse(body)?;
+        crate::packet::write::write_to_bytes(&self.dispatch(&request), true)
+            .map_err(nom::Err::Error)
+    }
+}
+
+/// Handling of Flex messaging
+#[cfg(feature = "flex")]
+impl Dispatcher {
+    /// Set the handler used to check the credentials of Flex logins, without one every login fails
+    pub fn set_login_handler(&mut self, handler: impl Fn(&str, &str) -> bool + 'static) {
+        self.login = Some(Box::new(handler));
+    }
+
+    /// Generate a new message id, in the UUID format used by Flex
+    fn next_message_id(&self) -> Uuid {
+        let count = self.message_count.get() + 1;
+        self.message_count.set(count);
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        Uuid::String(format!(
+            "{:08X}-{:04X}-{:04X}-{:04X}-{:012X}",
+            (nanos >> 32) as u32,
+            (nanos >> 16) as u16,
+            nanos as u16,
+            (count >> 48) as u16,
+            count & 0xFFFF_FFFF_FFFF
+        ))
+    }
+
+    fn timestamp() -> f64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |d| d.as_millis() as f64)
+    }
+
+    /// Get the fields shared by acknowledge and error responses to the given request
+    fn response(&self, request: &AbstractMessage, body: Rc<Value>) -> AsyncMessage {
+        AsyncMessage {
+            base: AbstractMessage {
+                body: Some(body),
+                client_id: Some(
+                    request
+                        .client_id
+                        .clone()
+                        .unwrap_or_else(|| self.next_message_id()),
+                ),
+                headers: Some(Rc::new(Value::Object(ObjectId::INVALID, vec![], None))),
+                message_id: Some(self.next_message_id()),
+                timestamp: Some(Self::timestamp()),
+                time_to_live: Some(0.0),
+                ..AbstractMessage::default()
+            },
+            correlation_id: request.message_id.clone(),
+        }
+    }
+
+    fn acknowledge(&self, request: &AbstractMessage, body: Rc<Value>) -> Rc<Value> {
+        let message = AcknowledgeMessage {
+            base: self.response(request, body),
+        };
+        flex_message(message.into_standard_value())
+    }
+
+    fn error(
+        &self,
+        request: &AbstractMessage,
+        fault_code: &str,
+        fault_string: &str,
+        root_cause: Option<Rc<Value>>,
+    ) -> Rc<Value> {
+        let message = ErrorMessage {
+            base: AcknowledgeMessage {
+                base: self.response(request, Rc::new(Value::Null)),
+            },
+            fault_code: Some(fault_code.to_string()),
+            fault_string: Some(fault_string.to_string()),
+            root_cause,
+            ..ErrorMessage::default()
+        };
+        flex_message(message.into_standard_value())
+    }
+
+    fn login(&self, request: &AbstractMessage) -> bool {
+        let credentials = match request.body.as_deref() {
+            Some(Value::String(body)) => {
+                base64::decode(body).and_then(|body| String::from_utf8(body).ok())
+            }
+            _ => None,
+        };
+
+        match (&self.login, credentials) {
+            (Some(login), Some(credentials)) => credentials
+                .split_once(':')
+                .is_some_and(|(username, password)| login(username, password)),
+            _ => false,
+        }
+    }
+
+    fn dispatch_command(&self, command: CommandMessage) -> Result<Rc<Value>, Rc<Value>> {
+        let request = &command.base.base;
+
+        match command.operation {
+            Some(CommandOperation::ClientPing | CommandOperation::Logout) => {
+                Ok(self.acknowledge(request, Rc::new(Value::Null)))
+            }
+            Some(CommandOperation::Login) if self.login(request) => {
+                Ok(self.acknowledge(request, Rc::new(Value::String("success".to_string()))))
+            }
+            Some(CommandOperation::Login) => Err(self.error(
+                request,
+                "Client.Authentication",
+                "Invalid username or password",
+                None,
+            )),
+            operation => Err(self.error(
+                request,
+                "Server.Processing",
+                &format!(
+                    "Unsupported command operation {}",
+                    operation.map_or(-1, i32::from)
+                ),
+                None,
+            )),
+        }
+    }
+
+    fn dispatch_remoting(
+        &self,
+        headers: &[Header],
+        message: RemotingMessage,
+    ) -> Result<Rc<Value>, Rc<Value>> {
+        let request = &message.base;
+        let target = format!(
+            "{}.{}",
+            request.destination.as_deref().unwrap_or_default(),
+            message.operation.as_deref().unwrap_or_default()
+        );
+        let arguments = match request.body.as_deref() {
+            Some(Value::StrictArray(_, arguments)) => arguments.clone(),
+            _ => vec![],
+        };
+
+        match self.handlers.get(&target) {
+            Some(handler) => match handler(headers, &arguments) {
+                Ok(result) => Ok(self.acknowledge(request, result)),
+                Err(e) => Err(self.error(
+                    request,
+                    "Server.Processing",
+                    &format!("Call to '{}' failed", target),
+                    Some(e),
+                )),
+            },
+            None => Err(self.error(
+                request,
+                "Server.ResourceUnavailable",
+                &format!("No handler for '{}'", target),
+                None,
+            )),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::types::{AMFVersion, Element, Lso, Value};
+use std::rc::Rc;
+
+/// Reading of remote shared object messages
+pub mod read;
+
+/// Writing of remote shared object messages
+pub mod write;
+
+/// The RTMP message type used for AMF0 encoded shared object messages
+pub const MESSAGE_TYPE_SHARED_OBJECT_AMF0: u8 = 19;
+
+/// The RTMP message type used for AMF3 encoded shared object messages
+#[cfg(feature = "amf3")]
+pub const MESSAGE_TYPE_SHARED_OBJECT_AMF3: u8 = 16;
+
+/// The flag set on messages for persistent shared objects
+pub const FLAG_PERSISTENT: u32 = 2;
+
+/// A single event in a shared object message
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// Sent by clients to start using the shared object
+    Use,
+
+    /// Sent by clients to stop using the shared object
+    Release,
+
+    /// Sent by clients to request that a property be changed
+    RequestChange(Element),
+
+    /// Sent by servers to notify clients that a property has changed
+    Change(Element),
+
+    /// Sent by servers to confirm that the requested change to the named property was made
+    Success(String),
+
+    /// Call the named handler on every client with the given arguments
+    SendMessage(String, Vec<Rc<Value>>),
+
+    /// Sent by servers to report an error, format is (code, level)
+    Status(String, String),
+
+    /// Sent by servers to notify clients that every property was removed
+    Clear,
+
+    /// Sent by servers to notify clients that the named property was removed
+    Remove(String),
+
+    /// Sent by clients to request that the named property be removed
+    RequestRemove(String),
+
+    /// Sent by servers to confirm a `Use` event
+    UseSuccess,
+}
+
+impl Event {
+    /// Get the type byte used to encode this event
+    pub fn event_type(&self) -> u8 {
+        match self {
+            Event::Use => 1,
+            Event::Release => 2,
+            Event::RequestChange(_) => 3,
+            Event::Change(_) => 4,
+            Event::Success(_) => 5,
+            Event::SendMessage(_, _) => 6,
+            Event::Status(_, _) => 7,
+            Event::Clear => 8,
+            Event::Remove(_) => 9,
+            Event::RequestRemove(_) => 10,
+            Event::UseSuccess => 11,
+        }
+    }
+}
+
+/// A message sent between a client and server about a remote shared object
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedObjectMessage {
+    /// The encoding used for property values, determines the message type
+    pub version: AMFVersion,
+
+    /// The name of the shared object
+    pub name: String,
+
+    /// The version of the shared object this message applies to
+    pub object_version: u32,
+
+    /// Flags for the shared object, see `FLAG_PERSISTENT`
+    pub flags: u32,
+
+    /// The events in this message, in order
+    pub events: Vec<Event>,
+}
+
+impl SharedObjectMessage {
+    /// Create a new AMF0 message for the shared object with the given name
+    pub fn new(name: impl Into<String>, events: Vec<Event>) -> Self {
+        Self {
+            version: AMFVersion::AMF0,
+            name: name.into(),
+            object_version: 0,
+            flags: 0,
+            events,
+        }
+    }
+
+    /// Get the RTMP message type used to send this message
+    pub fn message_type(&self) -> u8 {
+        match self.version {
+            AMFVersion::AMF0 => MESSAGE_TYPE_SHARED_OBJECT_AMF0,
+            #[cfg(feature = "amf3")]
+            AMFVersion::AMF3 => MESSAGE_TYPE_SHARED_OBJECT_AMF3,
+        }
+    }
+
+    /// Check if this message is for a persistent shared object
+    pub fn is_persistent(&self) -> bool {
+        self.flags & FLAG_PERSISTENT != 0
+    }
+
+    /// Apply the `Change`, `Remove` and `Clear` events of this message to the body of the given `Lso`
+    ///
+    /// This can be used to mirror a remote shared object, other events are ignored
+    pub fn apply(&self, lso: &mut Lso) {
+        for event in &self.events {
+            match event {
+                Event::Change(element) => {
+                    match lso.body.iter_mut().find(|e| e.name == element.name) {
+                        Some(existing) => existing.value = Rc::clone(&element.value),
+                        None => lso.body.push(element.clone()),
+                    }
+                }
+                Event::Remove(name) => lso.body.retain(|e| &e.name != name),
+                Event::Clear => lso.body.clear(),
+                _ => {}
+            }
+        }
+    }
+}
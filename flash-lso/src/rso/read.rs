@@ -0,0 +1,150 @@
+use nom::bytes::complete::take;
+use nom::combinator::all_consuming;
+use nom::multi::many0;
+use nom::number::complete::{be_u32, be_u8};
+
+use crate::amf0;
+use crate::amf0::read::AMF0Decoder;
+use crate::errors::Error;
+use crate::nom_utils::AMFResult;
+use crate::rso::{Event, SharedObjectMessage, MESSAGE_TYPE_SHARED_OBJECT_AMF0};
+use crate::types::{AMFVersion, Element, Value};
+use std::rc::Rc;
+
+#[cfg(feature = "amf3")]
+use crate::amf3::read::AMF3Decoder;
+#[cfg(feature = "amf3")]
+use crate::rso::MESSAGE_TYPE_SHARED_OBJECT_AMF3;
+#[cfg(feature = "amf3")]
+use nom::bytes::complete::tag;
+
+/// The only format defined for AMF3 shared object messages
+#[cfg(feature = "amf3")]
+const FORMAT_AMF3: u8 = 0;
+
+/// Decodes property values using the encoding of the message
+enum ValueDecoder {
+    Amf0(AMF0Decoder),
+    #[cfg(feature = "amf3")]
+    Amf3(AMF3Decoder),
+}
+
+impl ValueDecoder {
+    fn new(version: AMFVersion) -> Self {
+        match version {
+            AMFVersion::AMF0 => Self::Amf0(AMF0Decoder::default()),
+            #[cfg(feature = "amf3")]
+            AMFVersion::AMF3 => Self::Amf3(AMF3Decoder::default()),
+        }
+    }
+
+    fn parse_value<'a>(&mut self, i: &'a [u8]) -> AMFResult<'a, Rc<Value>> {
+        match self {
+            Self::Amf0(decoder) => decoder.parse_single_element(i),
+            #[cfg(feature = "amf3")]
+            Self::Amf3(decoder) => decoder.parse_single_element(i),
+        }
+    }
+}
+
+fn parse_property<'a>(decoder: &mut ValueDecoder, i: &'a [u8]) -> AMFResult<'a, Element> {
+    let (i, name) = amf0::read::parse_string(i)?;
+    let (i, value) = decoder.parse_value(i)?;
+    Ok((i, Element::new(name, value)))
+}
+
+fn parse_name(i: &[u8]) -> AMFResult<'_, String> {
+    let (i, name) = amf0::read::parse_string(i)?;
+    Ok((i, name.to_string()))
+}
+
+fn parse_send_message<'a>(decoder: &mut ValueDecoder, i: &'a [u8]) -> AMFResult<'a, Event> {
+    let (i, handler) = decoder.parse_value(i)?;
+    let handler = match handler.as_ref() {
+        Value::String(handler) => handler.clone(),
+        _ => return Err(nom::Err::Error(Error::InvalidCommand)),
+    };
+    let (i, arguments) = many0(|i| decoder.parse_value(i))(i)?;
+    Ok((i, Event::SendMessage(handler, arguments)))
+}
+
+fn parse_status(i: &[u8]) -> AMFResult<'_, Event> {
+    let (i, code) = parse_name(i)?;
+    let (i, level) = parse_name(i)?;
+    Ok((i, Event::Status(code, level)))
+}
+
+fn parse_event_data<'a>(
+    decoder: &mut ValueDecoder,
+    event_type: u8,
+    i: &'a [u8],
+) -> AMFResult<'a, Event> {
+    match event_type {
+        1 => Ok((i, Event::Use)),
+        2 => Ok((i, Event::Release)),
+        3 => parse_property(decoder, i).map(|(i, e)| (i, Event::RequestChange(e))),
+        4 => parse_property(decoder, i).map(|(i, e)| (i, Event::Change(e))),
+        5 => parse_name(i).map(|(i, n)| (i, Event::Success(n))),
+        6 => parse_send_message(decoder, i),
+        7 => parse_status(i),
+        8 => Ok((i, Event::Clear)),
+        9 => parse_name(i).map(|(i, n)| (i, Event::Remove(n))),
+        10 => parse_name(i).map(|(i, n)| (i, Event::RequestRemove(n))),
+        11 => Ok((i, Event::UseSuccess)),
+        _ => Err(nom::Err::Error(Error::UnsupportedType(event_type))),
+    }
+}
+
+fn parse_event<'a>(decoder: &mut ValueDecoder, i: &'a [u8]) -> AMFResult<'a, Event> {
+    let (i, event_type) = be_u8(i)?;
+    let (i, length) = be_u32(i)?;
+    let (i, data) = take(length)(i)?;
+
+    let (_, event) = all_consuming(|d| parse_event_data(decoder, event_type, d))(data)?;
+    Ok((i, event))
+}
+
+fn parse_message(i: &[u8], version: AMFVersion) -> AMFResult<'_, SharedObjectMessage> {
+    let mut decoder = ValueDecoder::new(version);
+
+    let (i, name) = parse_name(i)?;
+    let (i, object_version) = be_u32(i)?;
+    let (i, flags) = be_u32(i)?;
+    let (i, _reserved) = be_u32(i)?;
+    let (i, events) = many0(|i| parse_event(&mut decoder, i))(i)?;
+
+    Ok((
+        i,
+        SharedObjectMessage {
+            version,
+            name,
+            object_version,
+            flags,
+            events,
+        },
+    ))
+}
+
+/// Read the payload of an AMF0 shared object message (message type 19)
+pub fn parse_amf0(i: &[u8]) -> Result<SharedObjectMessage, nom::Err<Error<'_>>> {
+    let (_, message) = all_consuming(|i| parse_message(i, AMFVersion::AMF0))(i)?;
+    Ok(message)
+}
+
+/// Read the payload of an AMF3 shared object message (message type 16), including the leading format byte
+#[cfg(feature = "amf3")]
+pub fn parse_amf3(i: &[u8]) -> Result<SharedObjectMessage, nom::Err<Error<'_>>> {
+    let (i, _) = tag(&[FORMAT_AMF3])(i)?;
+    let (_, message) = all_consuming(|i| parse_message(i, AMFVersion::AMF3))(i)?;
+    Ok(message)
+}
+
+/// Read the payload of a shared object message with the given RTMP message type
+pub fn parse(message_type: u8, i: &[u8]) -> Result<SharedObjectMessage, nom::Err<Error<'_>>> {
+    match message_type {
+        MESSAGE_TYPE_SHARED_OBJECT_AMF0 => parse_amf0(i),
+        #[cfg(feature = "amf3")]
+        MESSAGE_TYPE_SHARED_OBJECT_AMF3 => parse_amf3(i),
+        _ => Err(nom::Err::Error(Error::UnsupportedType(message_type))),
+    }
+}
@@ -0,0 +1,96 @@
+//! Handles writing of remote shared object messages
+
+use crate::amf0;
+use crate::errors::Error;
+use crate::nom_utils::write_string;
+use crate::rso::{Event, SharedObjectMessage};
+use crate::types::{AMFVersion, Element, Value};
+use std::io::Result;
+use std::rc::Rc;
+
+#[cfg(feature = "amf3")]
+use crate::amf3::write::AMF3Encoder;
+
+/// Encodes property values using the encoding of the message
+enum ValueEncoder {
+    Amf0,
+    #[cfg(feature = "amf3")]
+    Amf3(Box<AMF3Encoder>),
+}
+
+impl ValueEncoder {
+    fn new(version: AMFVersion) -> Self {
+        match version {
+            AMFVersion::AMF0 => Self::Amf0,
+            #[cfg(feature = "amf3")]
+            AMFVersion::AMF3 => Self::Amf3(Box::default()),
+        }
+    }
+
+    fn write_value(&self, out: &mut Vec<u8>, value: &Rc<Value>) -> Result<()> {
+        match self {
+            Self::Amf0 => amf0::write::write_value(out, value),
+            #[cfg(feature = "amf3")]
+            Self::Amf3(encoder) => encoder.write_value_element(out, value),
+        }
+    }
+}
+
+fn write_property(encoder: &ValueEncoder, out: &mut Vec<u8>, element: &Element) -> Result<()> {
+    write_string(out, &element.name)?;
+    encoder.write_value(out, &element.value)
+}
+
+fn write_event_data(encoder: &ValueEncoder, out: &mut Vec<u8>, event: &Event) -> Result<()> {
+    match event {
+        Event::Use | Event::Release | Event::Clear | Event::UseSuccess => Ok(()),
+        Event::RequestChange(element) | Event::Change(element) => {
+            write_property(encoder, out, element)
+        }
+        Event::Success(name) | Event::Remove(name) | Event::RequestRemove(name) => {
+            write_string(out, name)
+        }
+        Event::SendMessage(handler, arguments) => {
+            encoder.write_value(out, &Rc::new(Value::String(handler.clone())))?;
+            arguments
+                .iter()
+                .try_for_each(|argument| encoder.write_value(out, argument))
+        }
+        Event::Status(code, level) => {
+            write_string(out, code)?;
+            write_string(out, level)
+        }
+    }
+}
+
+/// Write the payload of the given message, including the leading format byte for AMF3 messages
+pub fn write_to_bytes(
+    message: &SharedObjectMessage,
+) -> std::result::Result<Vec<u8>, Error<'static>> {
+    let encoder = ValueEncoder::new(message.version);
+    let mut out = vec![];
+
+    #[cfg(feature = "amf3")]
+    if message.version == AMFVersion::AMF3 {
+        out.push(0);
+    }
+
+    write_string(&mut out, &message.name).map_err(|e| Error::IoError(e.to_string(), e.kind()))?;
+    out.extend(message.object_version.to_be_bytes());
+    out.extend(message.flags.to_be_bytes());
+    // Reserved
+    out.extend(0u32.to_be_bytes());
+
+    for event in &message.events {
+        let mut data = vec![];
+        write_event_data(&encoder, &mut data, event)
+            .map_err(|e| Error::IoError(e.to_string(), e.kind()))?;
+
+        out.push(event.event_type());
+        let length = u32::try_from(data.len()).map_err(|_| Error::PacketTooLarge)?;
+        out.extend(length.to_be_bytes());
+        out.append(&mut data);
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,133 @@
+use crate::types::{AMFVersion, Element, ObjectId, Value};
+use std::rc::Rc;
+
+/// Reading of RTMP command messages
+pub mod read;
+
+/// Writing of RTMP command messages
+pub mod write;
+
+/// The RTMP message type used for AMF0 encoded commands
+pub const MESSAGE_TYPE_COMMAND_AMF0: u8 = 20;
+
+/// The RTMP message type used for AMF3 encoded commands
+#[cfg(feature = "amf3")]
+pub const MESSAGE_TYPE_COMMAND_AMF3: u8 = 17;
+
+/// The name of the command sent by clients to connect to an application
+pub const CONNECT: &str = "connect";
+
+/// The name of the command sent by clients to create a new stream
+pub const CREATE_STREAM: &str = "createStream";
+
+/// The name of the command sent by clients to play a stream
+pub const PLAY: &str = "play";
+
+/// The name of the command sent in response to a successful call
+pub const RESULT: &str = "_result";
+
+/// The name of the command sent in response to a failed call
+pub const ERROR: &str = "_error";
+
+/// The name of the command sent by servers to report the status of a stream
+pub const ON_STATUS: &str = "onStatus";
+
+/// An RTMP command message, such as `connect` or `_result`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    /// The encoding of this command, determines the message type
+    ///
+    /// AMF3 commands are still encoded as AMF0 values, but may contain `Value::AMF3` values
+    pub version: AMFVersion,
+
+    /// The name of the command
+    pub name: String,
+
+    /// Used to match responses to their requests, 0 if no response is expected
+    pub transaction_id: f64,
+
+    /// The command object, `Value::Null` if the command doesn't have one
+    pub command_object: Rc<Value>,
+
+    /// Any additional arguments of the command
+    pub arguments: Vec<Rc<Value>>,
+}
+
+fn anonymous_object(elements: Vec<Element>) -> Rc<Value> {
+    Rc::new(Value::Object(ObjectId::INVALID, elements, None))
+}
+
+impl Command {
+    /// Create a new AMF0 command
+    pub fn new(
+        name: impl Into<String>,
+        transaction_id: f64,
+        command_object: Rc<Value>,
+        arguments: Vec<Rc<Value>>,
+    ) -> Self {
+        Self {
+            version: AMFVersion::AMF0,
+            name: name.into(),
+            transaction_id,
+            command_object,
+            arguments,
+        }
+    }
+
+    /// Create a `connect` command with the given connection properties, e.g. `app` and `tcUrl`
+    pub fn connect(transaction_id: f64, properties: Vec<Element>) -> Self {
+        Self::new(
+            CONNECT,
+            transaction_id,
+            anonymous_object(properties),
+            vec![],
+        )
+    }
+
+    /// Create a `createStream` command
+    pub fn create_stream(transaction_id: f64) -> Self {
+        Self::new(CREATE_STREAM, transaction_id, Rc::new(Value::Null), vec![])
+    }
+
+    /// Create a `play` command for the given stream
+    pub fn play(stream_name: &str) -> Self {
+        Self::new(
+            PLAY,
+            0.0,
+            Rc::new(Value::Null),
+            vec![Rc::new(Value::String(stream_name.to_string()))],
+        )
+    }
+
+    /// Create a `_result` response to the command with the given transaction id
+    pub fn result(transaction_id: f64, properties: Rc<Value>, information: Rc<Value>) -> Self {
+        Self::new(RESULT, transaction_id, properties, vec![information])
+    }
+
+    /// Create an `_error` response to the command with the given transaction id
+    pub fn error(transaction_id: f64, properties: Rc<Value>, information: Rc<Value>) -> Self {
+        Self::new(ERROR, transaction_id, properties, vec![information])
+    }
+
+    /// Create an `onStatus` command with an info object containing the given level, code and description
+    pub fn on_status(level: &str, code: &str, description: &str) -> Self {
+        let info = anonymous_object(vec![
+            Element::new("level", Rc::new(Value::String(level.to_string()))),
+            Element::new("code", Rc::new(Value::String(code.to_string()))),
+            Element::new(
+                "description",
+                Rc::new(Value::String(description.to_string())),
+            ),
+        ]);
+        Self::new(ON_STATUS, 0.0, Rc::new(Value::Null), vec![info])
+    }
+
+    /// Get the RTMP message type used to send this command
+    pub fn message_type(&self) -> u8 {
+        match self.version {
+            AMFVersion::AMF0 => MESSAGE_TYPE_COMMAND_AMF0,
+            #[cfg(feature = "amf3")]
+            AMFVersion::AMF3 => MESSAGE_TYPE_COMMAND_AMF3,
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use nom::combinator::all_consuming;
+use nom::multi::many0;
+
+use crate::amf0::read::AMF0Decoder;
+use crate::errors::Error;
+use crate::nom_utils::AMFResult;
+use crate::rtmp::{Command, MESSAGE_TYPE_COMMAND_AMF0};
+use crate::types::{AMFVersion, Value};
+use std::rc::Rc;
+
+#[cfg(feature = "amf3")]
+use crate::rtmp::MESSAGE_TYPE_COMMAND_AMF3;
+#[cfg(feature = "amf3")]
+use nom::bytes::complete::tag;
+
+/// The only format defined for AMF3 command messages, the values that follow are AMF0 encoded
+#[cfg(feature = "amf3")]
+const FORMAT_AMF0: u8 = 0;
+
+/// Get the inner value of values that were switched to AMF3
+fn unwrap_amf3(value: &Value) -> &Value {
+    match value {
+        #[cfg(feature = "amf3")]
+        Value::AMF3(inner) => inner,
+        _ => value,
+    }
+}
+
+fn parse_command(i: &[u8], version: AMFVersion) -> AMFResult<'_, Command> {
+    let mut decoder = AMF0Decoder::default();
+
+    let (i, name) = decoder.parse_single_element(i)?;
+    let name = match unwrap_amf3(&name) {
+        Value::String(name) => name.clone(),
+        _ => return Err(nom::Err::Error(Error::InvalidCommand)),
+    };
+
+    let (i, transaction_id) = decoder.parse_single_element(i)?;
+    let transaction_id = match unwrap_amf3(&transaction_id) {
+        Value::Number(n) => *n,
+        Value::Integer(n) => *n as f64,
+        _ => return Err(nom::Err::Error(Error::InvalidCommand)),
+    };
+
+    // Some commands, such as those sent by older encoders, omit the command object entirely
+    let (i, command_object) = if i.is_empty() {
+        (i, Rc::new(Value::Null))
+    } else {
+        decoder.parse_single_element(i)?
+    };
+
+    let (i, arguments) = many0(|i| decoder.parse_single_element(i))(i)?;
+
+    Ok((
+        i,
+        Command {
+            version,
+            name,
+            transaction_id,
+            command_object,
+            arguments,
+        },
+    ))
+}
+
+/// Read the payload of an AMF0 command message (message type 20)
+pub fn parse_amf0(i: &[u8]) -> Result<Command, nom::Err<Error<'_>>> {
+    let (_, command) = all_consuming(|i| parse_command(i, AMFVersion::AMF0))(i)?;
+    Ok(command)
+}
+
+/// Read the payload of an AMF3 command message (message type 17), including the leading format byte
+#[cfg(feature = "amf3")]
+pub fn parse_amf3(i: &[u8]) -> Result<Command, nom::Err<Error<'_>>> {
+    let (i, _) = tag(&[FORMAT_AMF0])(i)?;
+    let (_, command) = all_consuming(|i| parse_command(i, AMFVersion::AMF3))(i)?;
+    Ok(command)
+}
+
+/// Read the payload of a command message with the given RTMP message type
+pub fn parse(message_type: u8, i: &[u8]) -> Result<Command, nom::Err<Error<'_>>> {
+    match message_type {
+        MESSAGE_TYPE_COMMAND_AMF0 => parse_amf0(i),
+        #[cfg(feature = "amf3")]
+        MESSAGE_TYPE_COMMAND_AMF3 => parse_amf3(i),
+        _ => Err(nom::Err::Error(Error::UnsupportedType(message_type))),
+    }
+}
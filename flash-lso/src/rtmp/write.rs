@@ -0,0 +1,33 @@
+//! Handles writing of RTMP command messages
+
+use crate::amf0;
+use crate::errors::Error;
+use crate::rtmp::Command;
+use crate::types::Value;
+use std::rc::Rc;
+
+#[cfg(feature = "amf3")]
+use crate::types::AMFVersion;
+
+/// Write the payload of the given command, including the leading format byte for AMF3 commands
+///
+/// Values are always written as AMF0, use `Value::AMF3` for values that should be switched to AMF3
+pub fn write_to_bytes(command: &Command) -> Result<Vec<u8>, Error<'static>> {
+    let mut out = vec![];
+
+    #[cfg(feature = "amf3")]
+    if command.version == AMFVersion::AMF3 {
+        out.push(0);
+    }
+
+    let name = Rc::new(Value::String(command.name.clone()));
+    let transaction_id = Rc::new(Value::Number(command.transaction_id));
+
+    [&name, &transaction_id, &command.command_object]
+        .into_iter()
+        .chain(&command.arguments)
+        .try_for_each(|value| amf0::write::write_value(&mut out, value))
+        .map_err(|e| Error::IoError(e.to_string(), e.kind()))?;
+
+    Ok(out)
+}
@@ -0,0 +1,211 @@
+//! Support for locating Local Shared Objects stored on disk by Flash Player and compatible players
+//!
+//! Flash Player (including the standalone projector) stores objects as
+//! `#SharedObjects/<random>/<domain>/<local path>/<name>.sol`, where the `<random>` directory is
+//! created per-install. AIR applications store objects as `#SharedObjects/<swf name>/<name>.sol`
+//! inside the "Local Store" directory of the application, and Ruffle uses
+//! `SharedObjects/<domain>/<local path>/<name>.sol` inside its data directory.
+//!
+//! Each of these directories is represented as a `Store`, the "domain" of an AIR store is the name of the SWF.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::nom_utils::describe_error;
+use crate::read::Reader;
+use crate::types::Lso;
+use crate::write::Writer;
+
+/// The file extension used for Local Shared Objects
+const EXTENSION: &str = "sol";
+
+/// The domain used by Flash Player for objects created by local SWF files
+pub const LOCAL_DOMAIN: &str = "localhost";
+
+/// The player that created a `Store`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StoreKind {
+    /// Flash Player, either the browser plugin or the standalone projector
+    FlashPlayer,
+
+    /// An AIR application
+    Air,
+
+    /// Ruffle desktop
+    Ruffle,
+}
+
+/// A directory containing Local Shared Objects, grouped by domain
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Store {
+    /// The directory containing the domain directories
+    pub root: PathBuf,
+
+    /// The player that created this store
+    pub kind: StoreKind,
+}
+
+/// A Local Shared Object found in a `Store`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StoredObject {
+    /// The domain the object belongs to
+    pub domain: String,
+
+    /// The `localPath` the object was created with, always starts with `/`
+    pub local_path: String,
+
+    /// The name of the object, as given to `SharedObject.getLocal`
+    pub name: String,
+
+    /// The location of the object on disk
+    pub path: PathBuf,
+}
+
+fn sub_directories(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut out = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            out.push(entry.path());
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
+/// Split a domain, `localPath` or object name into path components, ignoring any that could escape the store
+fn path_components(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/')
+        .filter(|c| !c.is_empty() && *c != "." && *c != "..")
+}
+
+impl Store {
+    /// Create a store from the directory containing its domain directories
+    pub fn new(root: impl Into<PathBuf>, kind: StoreKind) -> Self {
+        Self {
+            root: root.into(),
+            kind,
+        }
+    }
+
+    /// Find every store in the given home directory, using the Linux layouts of each supported player
+    ///
+    /// Locations that don't exist are skipped
+    pub fn discover(home: &Path) -> io::Result<Vec<Store>> {
+        let mut stores = Vec::new();
+
+        let flash_player = home.join(".macromedia/Flash_Player/#SharedObjects");
+        if flash_player.is_dir() {
+            for root in sub_directories(&flash_player)? {
+                stores.push(Store::new(root, StoreKind::FlashPlayer));
+            }
+        }
+
+        let air = home.join(".appdata");
+        if air.is_dir() {
+            for app in sub_directories(&air)? {
+                let root = app.join("Local Store/#SharedObjects");
+                if root.is_dir() {
+                    stores.push(Store::new(root, StoreKind::Air));
+                }
+            }
+        }
+
+        let ruffle = home.join(".local/share/ruffle/SharedObjects");
+        if ruffle.is_dir() {
+            stores.push(Store::new(ruffle, StoreKind::Ruffle));
+        }
+
+        Ok(stores)
+    }
+
+    /// List the domains that have objects in this store
+    pub fn domains(&self) -> io::Result<Vec<String>> {
+        Ok(sub_directories(&self.root)?
+            .iter()
+            .filter_map(|d| d.file_name())
+            .map(|d| d.to_string_lossy().to_string())
+            .collect())
+    }
+
+    /// The directory of the given domain, any `.` or `..` components of `domain` are ignored
+    fn domain_path(&self, domain: &str) -> PathBuf {
+        let mut path = self.root.clone();
+        path.extend(path_components(domain));
+        path
+    }
+
+    /// List every object stored for the given domain
+    pub fn objects(&self, domain: &str) -> io::Result<Vec<StoredObject>> {
+        let mut out = Vec::new();
+        let mut pending = vec![(self.domain_path(domain), String::new())];
+
+        while let Some((dir, local_path)) = pending.pop() {
+            for entry in fs::read_dir(&dir)? {
+                let entry = entry?;
+                let file_name = entry.file_name().to_string_lossy().to_string();
+
+                if entry.file_type()?.is_dir() {
+                    pending.push((entry.path(), format!("{}/{}", local_path, file_name)));
+                } else if let Some(name) = file_name.strip_suffix(&format!(".{}", EXTENSION)) {
+                    out.push(StoredObject {
+                        domain: domain.to_string(),
+                        local_path: if local_path.is_empty() {
+                            "/".to_string()
+                        } else {
+                            local_path.clone()
+                        },
+                        name: name.to_string(),
+                        path: entry.path(),
+                    });
+                }
+            }
+        }
+
+        out.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(out)
+    }
+
+    /// List every object in this store
+    pub fn all_objects(&self) -> io::Result<Vec<StoredObject>> {
+        let mut out = Vec::new();
+        for domain in self.domains()? {
+            out.extend(self.objects(&domain)?);
+        }
+        Ok(out)
+    }
+
+    /// Get the path of the file used for `SharedObject.getLocal(name, local_path)` by a SWF on the given domain
+    ///
+    /// When no `localPath` is given to `getLocal` Flash Player uses the full path of the SWF, e.g. `/games/game.swf`
+    /// Local SWF files use the domain `LOCAL_DOMAIN`
+    /// Any `.` or `..` components of `domain`, `local_path` or `name` are ignored
+    pub fn object_path(&self, domain: &str, local_path: &str, name: &str) -> PathBuf {
+        let mut path = self.domain_path(domain);
+        path.extend(path_components(local_path).chain(path_components(name)));
+        // Names may contain '.', so the extension is appended rather than set
+        let mut path = path.into_os_string();
+        path.push(".");
+        path.push(EXTENSION);
+        path.into()
+    }
+
+    /// Read the object at the given path using the given reader
+    pub fn load(&self, path: &Path, reader: &mut Reader) -> io::Result<Lso> {
+        let data = fs::read(path)?;
+        reader
+            .parse(&data)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, describe_error(&e)))
+    }
+
+    /// Write an object to the given path using the given writer, creating any missing directories
+    pub fn save(&self, path: &Path, lso: &mut Lso, writer: &mut Writer) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut data = Vec::new();
+        writer.write_full(&mut data, lso)?;
+        fs::write(path, data)
+    }
+}
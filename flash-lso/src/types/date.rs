@@ -0,0 +1,110 @@
+//! Conversions between `Value::Date` and the date types of `chrono` and `time`
+//!
+//! A date is stored as milliseconds since the unix epoch, invalid dates (such as `new Date(NaN)`) have a NaN value and
+//! can't be converted. AMF0 dates also store the timezone they were written in, as a signed 16 bit number of minutes
+//! in the same form as ActionScript's `Date.getTimezoneOffset`, so UTC+1 is -60. This is applied to the converted date.
+//! Dates without a timezone (AMF3) are converted as UTC
+
+use super::Value;
+#[cfg(any(feature = "chrono", feature = "time"))]
+use crate::errors::Error;
+
+/// Split the milliseconds of a date into whole seconds and nanoseconds, if it is valid
+fn split_millis(millis: f64) -> Option<(i64, u32)> {
+    if !millis.is_finite() {
+        return None;
+    }
+
+    let seconds = (millis / 1000.0).floor();
+    if seconds < i64::MIN as f64 || seconds >= i64::MAX as f64 {
+        return None;
+    }
+
+    // Sub-millisecond precision is rounded to the nearest nanosecond, which can carry into the next second
+    let nanos = ((millis - seconds * 1000.0) * 1_000_000.0).round() as u32;
+    if nanos >= 1_000_000_000 {
+        Some((seconds as i64 + 1, nanos - 1_000_000_000))
+    } else {
+        Some((seconds as i64, nanos))
+    }
+}
+
+/// Get the seconds, nanoseconds and the offset from UTC in seconds of a date value
+fn date_parts(value: &Value) -> Option<(i64, u32, i32)> {
+    match value.unwrap_amf3() {
+        Value::Date(millis, tz) => {
+            let (seconds, nanos) = split_millis(*millis)?;
+            let offset = tz.map_or(0, |tz| -i32::from(tz as i16) * 60);
+            Some((seconds, nanos, offset))
+        }
+        _ => None,
+    }
+}
+
+/// Create a date value from a timestamp in nanoseconds and an offset from UTC in seconds
+fn date_from_parts(nanos: i128, offset: i32) -> Value {
+    Value::Date(
+        nanos as f64 / 1_000_000.0,
+        Some((-offset / 60) as i16 as u16),
+    )
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<&Value> for chrono::DateTime<chrono::FixedOffset> {
+    type Error = Error<'static>;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        let (seconds, nanos, offset) =
+            date_parts(value).ok_or(Error::UnexpectedValue("a valid date"))?;
+        let offset =
+            chrono::FixedOffset::east_opt(offset).ok_or(Error::UnexpectedValue("a valid date"))?;
+        let utc = chrono::DateTime::from_timestamp(seconds, nanos)
+            .ok_or(Error::UnexpectedValue("a valid date"))?;
+        Ok(utc.with_timezone(&offset))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<&Value> for chrono::DateTime<chrono::Utc> {
+    type Error = Error<'static>;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        chrono::DateTime::<chrono::FixedOffset>::try_from(value).map(|d| d.to_utc())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> From<chrono::DateTime<Tz>> for Value {
+    fn from(date: chrono::DateTime<Tz>) -> Self {
+        use chrono::Offset;
+
+        let offset = date.offset().fix().local_minus_utc();
+        let nanos = i128::from(date.timestamp()) * 1_000_000_000
+            + i128::from(date.timestamp_subsec_nanos());
+        date_from_parts(nanos, offset)
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<&Value> for time::OffsetDateTime {
+    type Error = Error<'static>;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        let (seconds, nanos, offset) =
+            date_parts(value).ok_or(Error::UnexpectedValue("a valid date"))?;
+        let offset = time::UtcOffset::from_whole_seconds(offset)
+            .map_err(|_| Error::UnexpectedValue("a valid date"))?;
+        let utc = time::OffsetDateTime::from_unix_timestamp_nanos(
+            i128::from(seconds) * 1_000_000_000 + i128::from(nanos),
+        )
+        .map_err(|_| Error::UnexpectedValue("a valid date"))?;
+        Ok(utc.to_offset(offset))
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Value {
+    fn from(date: time::OffsetDateTime) -> Self {
+        date_from_parts(date.unix_timestamp_nanos(), date.offset().whole_seconds())
+    }
+}
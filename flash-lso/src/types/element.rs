@@ -33,3 +33,67 @@ impl Element {
         self.name.as_str()
     }
 }
+
+/// Lookup and mutation of a list of elements by name, such as the body of an `Lso` or the members of an object
+///
+/// Flash writes and expects properties in a fixed order, so replacing an element keeps its position and new elements
+/// are appended
+///
+/// The methods are named so that they don't clash with those of `Vec` and slices
+pub trait ElementList {
+    /// Get the value of the element named `name`
+    fn get_by_name(&self, name: &str) -> Option<&Value>;
+
+    /// Get a mutable reference to the value of the element named `name`, cloning it if it is shared
+    fn get_mut_by_name(&mut self, name: &str) -> Option<&mut Value>;
+
+    /// Set the value of the element named `name`, returning the previous value if there was one
+    fn insert_by_name(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<Rc<Value>>,
+    ) -> Option<Rc<Value>>;
+
+    /// Remove the element named `name`, returning its value if there was one
+    fn remove_by_name(&mut self, name: &str) -> Option<Rc<Value>>;
+
+    /// Check if there is an element named `name`
+    fn contains_name(&self, name: &str) -> bool;
+}
+
+impl ElementList for Vec<Element> {
+    fn get_by_name(&self, name: &str) -> Option<&Value> {
+        self.iter().find(|e| e.name == name).map(Element::value)
+    }
+
+    fn get_mut_by_name(&mut self, name: &str) -> Option<&mut Value> {
+        self.iter_mut()
+            .find(|e| e.name == name)
+            .map(|e| Rc::make_mut(&mut e.value))
+    }
+
+    fn insert_by_name(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<Rc<Value>>,
+    ) -> Option<Rc<Value>> {
+        let name = name.into();
+        let value = value.into();
+        match self.iter_mut().find(|e| e.name == name) {
+            Some(element) => Some(std::mem::replace(&mut element.value, value)),
+            None => {
+                self.push(Element::new(name, value));
+                None
+            }
+        }
+    }
+
+    fn remove_by_name(&mut self, name: &str) -> Option<Rc<Value>> {
+        let index = self.iter().position(|e| e.name == name)?;
+        Some(Vec::remove(self, index).value)
+    }
+
+    fn contains_name(&self, name: &str) -> bool {
+        self.iter().any(|e| e.name == name)
+    }
+}
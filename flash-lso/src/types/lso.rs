@@ -1,4 +1,5 @@
-use super::{AMFVersion, Element, Header};
+use super::{AMFVersion, Element, ElementList, Header, Value};
+use std::rc::Rc;
 
 /// A container for lso files
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -26,6 +27,42 @@ impl Lso {
             body,
         }
     }
+
+    /// Get the value of the root element named `name`
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.body.get_by_name(name)
+    }
+
+    /// Get a mutable reference to the value of the root element named `name`, cloning it if it is shared
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        self.body.get_mut_by_name(name)
+    }
+
+    /// Set the value of the root element named `name`, returning the previous value if there was one
+    ///
+    /// An existing element keeps its position in the body, otherwise the element is added to the end
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<Rc<Value>>,
+    ) -> Option<Rc<Value>> {
+        self.body.insert_by_name(name, value)
+    }
+
+    /// Remove the root element named `name`, returning its value if there was one
+    pub fn remove(&mut self, name: &str) -> Option<Rc<Value>> {
+        self.body.remove_by_name(name)
+    }
+
+    /// Check if there is a root element named `name`
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.body.contains_name(name)
+    }
+
+    /// Iterate over the root elements of this lso, in order
+    pub fn iter(&self) -> std::slice::Iter<'_, Element> {
+        self.body.iter()
+    }
 }
 
 impl IntoIterator for Lso {
@@ -36,3 +73,12 @@ impl IntoIterator for Lso {
         self.body.into_iter()
     }
 }
+
+impl<'a> IntoIterator for &'a Lso {
+    type Item = &'a Element;
+    type IntoIter = std::slice::Iter<'a, Element>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.body.iter()
+    }
+}
@@ -1,6 +1,8 @@
 mod amf_version;
 mod attribute;
 mod class_definition;
+#[cfg(any(feature = "chrono", feature = "time"))]
+mod date;
 mod element;
 mod lso;
 mod lso_header;
@@ -12,9 +14,10 @@ mod object_id;
 pub use amf_version::AMFVersion;
 pub use attribute::Attribute;
 pub use class_definition::ClassDefinition;
-pub use element::Element;
+pub use element::{Element, ElementList};
 pub use lso::Lso;
 pub use lso_header::Header;
 pub use object_id::ObjectId;
 pub use reference::Reference;
+pub(crate) use value::array_length;
 pub use value::Value;
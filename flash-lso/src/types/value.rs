@@ -1,7 +1,7 @@
 use super::{ClassDefinition, Element, ObjectId, Reference};
+use crate::errors::Error;
 use std::rc::Rc;
 
-//TODO: should amf3 assoc arrays be their own type with a dense and assoc section
 /// A single or compound value
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq)]
@@ -25,13 +25,29 @@ pub enum Value {
     Undefined,
 
     /// Represent ECMA-Arrays (amf0) and associative arrays (amf3, even if they contain a dense part)
-    /// Final value represents the length of the array in amf0, this can differ from the actual number of elements
+    ///
+    /// The fields are (id, dense part, associative part, declared length). Dense item `n` is at index `n`, associative
+    /// keys that are array indices continue the array past the dense part, see `Value::array_length`
+    /// - The amf0 reader puts every entry in the associative part and keeps the declared length as read. Flash Player
+    ///   declares the `length` of the array, while FLV metadata usually declares the number of entries
+    /// - The amf3 reader sets the declared length to `Value::array_length`, as amf3 doesn't encode one
+    /// - The amf0 writer writes dense item `n` with the key `n`, followed by the associative part. The declared length
+    ///   is written unless it is less than `Value::array_length`, in which case that is written instead so no item is
+    ///   past the end of the array
+    /// - The amf3 writer ignores the declared length
+    ///
+    /// The declared length is never reduced automatically, as sparse arrays can be longer than their contents, use
+    /// `Value::normalize_array_length` after removing items
     ECMAArray(ObjectId, Vec<Rc<Value>>, Vec<Element>, u32),
 
     /// Represent a strict array (amf0) or a dense array (amf3)
     StrictArray(ObjectId, Vec<Rc<Value>>),
 
-    /// Represent a timezone in the format (seconds since epoch, timezone or UTC if missing (amf3) )
+    /// Represent a date in the format (milliseconds since the unix epoch, timezone)
+    ///
+    /// Invalid dates have a NaN value. The timezone is only present in amf0, it is the bits of a signed 16 bit number of
+    /// minutes in the same form as ActionScript's `Date.getTimezoneOffset`, so UTC+1 is -60. Dates without a timezone
+    /// are in UTC
     Date(f64, Option<u16>),
 
     /// Represent the unsupported type
@@ -71,6 +87,15 @@ pub enum Value {
     /// Format is ((key, value), has_weak_keys)
     Dictionary(ObjectId, Vec<(Rc<Value>, Rc<Value>)>, bool),
 
+    /// Represent the array type (amf3) with both of its sections
+    /// Format is (id, dense part, associative part)
+    ///
+    /// Only read when `AMF3Decoder::mixed_arrays` is set. Unlike `StrictArray` and `ECMAArray` the variant doesn't depend
+    /// on whether the associative part is empty, so editing an array never changes its type. Both parts keep the order
+    /// they were read in. In amf0 this is written as an ECMA array with the length `Value::array_length`, see
+    /// `Value::into_mixed_array` and `Value::into_split_array` for converting to and from the other array types
+    MixedArray(ObjectId, Vec<Rc<Value>>, Vec<Element>),
+
     /// Represent a external object, such as from flex
     /// (custom_elements, regular elements, class def)
     Custom(Vec<Element>, Vec<Element>, Option<ClassDefinition>),
@@ -93,3 +118,265 @@ impl FromIterator<Value> for Vec<Rc<Value>> {
         iter.into_iter().map(Rc::new).collect()
     }
 }
+
+impl Value {
+    /// Get the value wrapped by an embedded AMF3 value, or this value otherwise
+    pub(crate) fn unwrap_amf3(&self) -> &Value {
+        match self {
+            #[cfg(feature = "amf3")]
+            Value::AMF3(v) => v.unwrap_amf3(),
+            _ => self,
+        }
+    }
+
+    /// Get this value as a number, if it is a `Number` or `Integer`
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.unwrap_amf3() {
+            Value::Number(n) => Some(*n),
+            Value::Integer(i) => Some(f64::from(*i)),
+            _ => None,
+        }
+    }
+
+    /// Get this value as an integer, if it is an `Integer` or a `Number` with no fractional part
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.unwrap_amf3() {
+            Value::Integer(i) => Some(i64::from(*i)),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 2f64.powi(63) => Some(*n as i64),
+            _ => None,
+        }
+    }
+
+    /// Get this value as a string slice, if it is a `String`
+    pub fn as_str(&self) -> Option<&str> {
+        match self.unwrap_amf3() {
+            Value::String(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Get this value as a bool, if it is a `Bool`
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.unwrap_amf3() {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Get the contents of this value, if it is a `ByteArray`
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self.unwrap_amf3() {
+            Value::ByteArray(bytes) => Some(bytes.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// Get the named elements of this value
+    ///
+    /// These are the members of an `Object`, the associative portion of an `ECMAArray` or the external elements of a
+    /// `Custom` value
+    pub fn as_elements(&self) -> Option<&[Element]> {
+        match self.unwrap_amf3() {
+            Value::Object(_, elements, _)
+            | Value::ECMAArray(_, _, elements, _)
+            | Value::MixedArray(_, _, elements)
+            | Value::Custom(elements, _, _) => Some(elements.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// Get the value of the element named `key`, see `as_elements`
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_elements()?
+            .iter()
+            .find(|e| e.name == key)
+            .map(|e| e.value())
+    }
+
+    /// Get the length an ActionScript `Array` with this content would have
+    ///
+    /// For an `ECMAArray` or `MixedArray` this is the length of the dense part, or one more than the largest associative
+    /// key that is an array index if that is larger. The declared length is not included. For a `StrictArray` this is
+    /// the number of items
+    pub fn array_length(&self) -> Option<u32> {
+        match self.unwrap_amf3() {
+            Value::StrictArray(_, items) => Some(items.len() as u32),
+            Value::ECMAArray(_, dense, assoc, _) | Value::MixedArray(_, dense, assoc) => {
+                Some(array_length(dense, assoc))
+            }
+            _ => None,
+        }
+    }
+
+    /// Set the declared length of an `ECMAArray` to its `array_length`, does nothing for other values
+    pub fn normalize_array_length(&mut self) {
+        if let Value::ECMAArray(_, dense, assoc, length) = self {
+            *length = array_length(dense, assoc);
+        }
+    }
+
+    /// Convert a `StrictArray` or `ECMAArray` into a `MixedArray` with the same id and parts, other values are returned
+    /// unchanged
+    ///
+    /// The declared length of an `ECMAArray` is dropped, it can be recovered with `Value::array_length` unless the array
+    /// was sparse
+    pub fn into_mixed_array(self) -> Value {
+        match self {
+            Value::StrictArray(id, dense) => Value::MixedArray(id, dense, Vec::new()),
+            Value::ECMAArray(id, dense, assoc, _) => Value::MixedArray(id, dense, assoc),
+            _ => self,
+        }
+    }
+
+    /// Convert a `MixedArray` into the type the amf3 reader uses by default, other values are returned unchanged
+    ///
+    /// This is a `StrictArray` if the associative part is empty, otherwise an `ECMAArray` with a declared length of
+    /// `Value::array_length`
+    pub fn into_split_array(self) -> Value {
+        match self {
+            Value::MixedArray(id, dense, assoc) if assoc.is_empty() => {
+                Value::StrictArray(id, dense)
+            }
+            Value::MixedArray(id, dense, assoc) => {
+                let length = array_length(&dense, &assoc);
+                Value::ECMAArray(id, dense, assoc, length)
+            }
+            _ => self,
+        }
+    }
+
+    /// Get the item at `index` in an array or vector
+    ///
+    /// For arrays this is an index into the dense portion, items of int, uint and double vectors are returned as new
+    /// `Integer` and `Number` values
+    pub fn get_index(&self, index: usize) -> Option<Rc<Value>> {
+        match self.unwrap_amf3() {
+            Value::StrictArray(_, items)
+            | Value::ECMAArray(_, items, _, _)
+            | Value::MixedArray(_, items, _)
+            | Value::VectorObject(_, items, _, _) => items.get(index).cloned(),
+            Value::VectorInt(items, _) => items.get(index).map(|i| Rc::new(Value::Integer(*i))),
+            Value::VectorUInt(items, _) => items
+                .get(index)
+                .map(|i| Rc::new(Value::Number(f64::from(*i)))),
+            Value::VectorDouble(items, _) => items.get(index).map(|n| Rc::new(Value::Number(*n))),
+            _ => None,
+        }
+    }
+}
+
+/// Parse an associative array key as an array index, keys such as "01" or "+1" are not indices
+fn array_index(key: &str) -> Option<u32> {
+    key.parse::<u32>()
+        .ok()
+        .filter(|index| *index != u32::MAX && index.to_string() == key)
+}
+
+/// Get the length of an array with the given dense and associative parts, see `Value::array_length`
+pub(crate) fn array_length(dense: &[Rc<Value>], assoc: &[Element]) -> u32 {
+    assoc
+        .iter()
+        .filter_map(|e| array_index(&e.name))
+        .map(|index| index + 1)
+        .fold(dense.len() as u32, u32::max)
+}
+
+impl TryFrom<&Value> for f64 {
+    type Error = Error<'static>;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        value.as_f64().ok_or(Error::UnexpectedValue("a number"))
+    }
+}
+
+impl TryFrom<&Value> for i64 {
+    type Error = Error<'static>;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        value.as_i64().ok_or(Error::UnexpectedValue("an integer"))
+    }
+}
+
+impl TryFrom<&Value> for i32 {
+    type Error = Error<'static>;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        value
+            .as_i64()
+            .and_then(|i| i32::try_from(i).ok())
+            .ok_or(Error::UnexpectedValue("a 32 bit integer"))
+    }
+}
+
+impl TryFrom<&Value> for bool {
+    type Error = Error<'static>;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        value.as_bool().ok_or(Error::UnexpectedValue("a boolean"))
+    }
+}
+
+impl<'a> TryFrom<&'a Value> for &'a str {
+    type Error = Error<'static>;
+
+    fn try_from(value: &'a Value) -> Result<Self, Self::Error> {
+        value.as_str().ok_or(Error::UnexpectedValue("a string"))
+    }
+}
+
+impl TryFrom<&Value> for String {
+    type Error = Error<'static>;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        value
+            .as_str()
+            .map(str::to_string)
+            .ok_or(Error::UnexpectedValue("a string"))
+    }
+}
+
+impl<'a> TryFrom<&'a Value> for &'a [u8] {
+    type Error = Error<'static>;
+
+    fn try_from(value: &'a Value) -> Result<Self, Self::Error> {
+        value
+            .as_bytes()
+            .ok_or(Error::UnexpectedValue("a byte array"))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(i: i32) -> Self {
+        Value::Integer(i)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(bytes: Vec<u8>) -> Self {
+        Value::ByteArray(bytes)
+    }
+}
@@ -39,6 +39,30 @@ impl Writer {
         writer.write_all(&buffer)?;
         Ok(())
     }
+
+    /// Get the number of bytes that `write_full` would produce for the given LSO, including the header
+    ///
+    /// Nothing is written and the state of the encoders is unchanged. Fails if the LSO can't be written, or if it is
+    /// AMF3 and the `amf3` feature is disabled
+    pub fn encoded_length(&self, lso: &Lso) -> std::io::Result<usize> {
+        let body_length = if lso.header.format_version == AMFVersion::AMF0 {
+            crate::amf0::write::body_length(&lso.body)?
+        } else {
+            #[cfg(feature = "amf3")]
+            {
+                self.amf3_encoder.body_length(&lso.body)?
+            }
+            #[cfg(not(feature = "amf3"))]
+            {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "AMF3 support is not enabled",
+                ));
+            }
+        };
+
+        Ok(HEADER_VERSION.len() + 4 + header_length(&lso.header) + body_length)
+    }
 }
 
 fn write_header<'a, 'b: 'a, W: Write + 'a>(
@@ -76,6 +100,21 @@ pub fn write_to_bytes<'a>(lso: &mut Lso) -> Result<Vec<u8>, Error<'a>> {
     Ok(v)
 }
 
+/// A `Write` sink that discards its input, only counting the number of bytes written
+#[derive(Default)]
+pub(crate) struct ByteCounter(pub(crate) usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 pub(crate) trait WriteExt {
     fn write_u8(&mut self, value: u8) -> std::io::Result<()>;
     fn write_u16(&mut self, value: u16) -> std::io::Result<()>;
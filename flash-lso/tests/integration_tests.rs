@@ -23,6 +23,16 @@ impl<'a> fmt::Debug for PrettyArray<'a> {
     }
 }
 
+/// A registry containing the given codecs
+#[cfg(feature = "flex")]
+fn codecs(
+    register: fn(&mut flash_lso::amf3::custom_encoder::ExternalCodecRegistry),
+) -> flash_lso::amf3::custom_encoder::ExternalCodecRegistry {
+    let mut registry = flash_lso::amf3::custom_encoder::ExternalCodecRegistry::new();
+    register(&mut registry);
+    registry
+}
+
 macro_rules! packet_test {
     ($([$name: ident, $path: expr, $exact_lengths: expr]),*) => {
         $(
@@ -117,7 +127,7 @@ macro_rules! auto_test_flex {
 
             let data = include_bytes!(concat!("sol/", $path, ".sol"));
             let mut des = Reader::default();
-            flex::read::register_decoders(&mut des.amf3_decoder);
+            codecs(flex::register_codecs).install_reader(&mut des);
             let parse_res = des.parse_incomplete(data);
 
             if let Ok((unparsed_bytes, mut sol)) =  parse_res {
@@ -128,11 +138,11 @@ macro_rules! auto_test_flex {
 
                 let mut buffer = vec![];
                 let mut s = Writer::default();
-                flex::write::register_encoders(&mut s.amf3_encoder);
+                codecs(flex::register_codecs).install_writer(&mut s);
                 s.write_full(&mut buffer, &mut sol)?;
 
                 let mut des2 = Reader::default();
-                flex::read::register_decoders(&mut des2.amf3_decoder);
+                codecs(flex::register_codecs).install_reader(&mut des2);
                 let sol2 = des2.parse(&buffer).expect("Failed to parse buffer");
                 assert!(sol2 == sol);
 
@@ -244,7 +254,7 @@ macro_rules! json_test_flex {
 
             let data = include_bytes!(concat!("sol/", $path, ".sol"));
             let mut des = Reader::default();
-            flex::read::register_decoders(&mut des.amf3_decoder);
+            codecs(flex::register_codecs).install_reader(&mut des);
             let parse_res = des.parse(data)?;
             let output_json = serde_json::to_string(&parse_res)?;
 
@@ -258,6 +268,23 @@ macro_rules! json_test_flex {
     }
 }
 
+macro_rules! length_test {
+    ($([$name: ident, $path: expr]),*) => {
+        $(
+        #[test]
+        pub fn $name() -> Result<(), Box<dyn std::error::Error>> {
+            let data = include_bytes!(concat!("sol/", $path, ".sol"));
+            let sol = Reader::default().parse(data)?;
+
+            let length = flash_lso::write::Writer::default().encoded_length(&sol)?;
+            assert_eq!(length, data.len());
+
+            Ok(())
+        }
+        )*
+    }
+}
+
 json_test! {
     // AS2
     [json_as2_array, "AS2-Array-Demo"],
@@ -425,6 +452,32 @@ packet_test! {
     [armorgames_auth_response, "armorgames_auth_response", false]
 }
 
+length_test! {
+    [length_as2_demo, "AS2-Demo"],
+    [length_as2_ecma_array, "AS2-ECMAArray-Demo"],
+    [length_as2_long_string, "AS2-LongString-Demo"],
+    [length_as2_typed_object, "AS2-TypedObject-Demo"],
+    [length_as3_typed_object, "AS3-TypedObject-Demo"],
+    [length_as3_vector_typed_object, "AS3-VectorTypedObject-Demo"],
+    [length_as3_dictionary, "AS3-Dictionary-Demo"],
+    [length_coc_8, "CoC_8"],
+    [length_canvas, "canvas"],
+    [length_self_referential, "other/self-referential"]
+}
+
+#[test]
+pub fn test_value_length_amf3() {
+    let data = include_bytes!("./amf/LearnToFly3.profileData.saveString.amf");
+    let (_, obj) = flash_lso::amf3::read::AMF3Decoder::default()
+        .parse_single_element(data)
+        .expect("Failed to parse object");
+
+    let encoder = flash_lso::amf3::write::AMF3Encoder::default();
+    assert_eq!(encoder.value_length(&obj).unwrap(), data.len());
+    // Measuring must not affect the reference tables
+    assert_eq!(encoder.value_length(&obj).unwrap(), data.len());
+}
+
 json_test_bytearray_object_amf3! {
     [json_learntofly3, "LearnToFly3.profileData.saveString"],
     [self_referential_object, "self-referential-object"],
@@ -472,3 +525,2200 @@ pub fn test_recursive_object() {
         panic!("Expected object");
     }
 }
+
+#[test]
+pub fn test_truncated_file() {
+    let data = include_bytes!("sol/AS2-Demo.sol");
+    let truncated = &data[..data.len() - 10];
+
+    let res = Reader::default().parse_checked(truncated);
+    assert_eq!(
+        res,
+        Err(nom::Err::Error(Error::TruncatedFile(
+            data.len() as u32 - 6,
+            data.len() - 16
+        )))
+    );
+}
+
+#[test]
+pub fn test_trailing_data() {
+    let data = include_bytes!("sol/AS2-Demo.sol");
+    let mut padded = data.to_vec();
+    padded.extend_from_slice(&[0, 0, 0, 0]);
+
+    let res = Reader::default().parse_checked(&padded);
+    assert_eq!(
+        res,
+        Err(nom::Err::Error(Error::TrailingData(
+            data.len() as u32 - 6,
+            data.len() - 2
+        )))
+    );
+
+    let (lso, trailing) = Reader::default()
+        .parse_lenient(&padded)
+        .expect("Failed to parse padded file");
+    assert_eq!(lso, Reader::default().parse(data).unwrap());
+    assert_eq!(trailing, &[0, 0, 0, 0]);
+}
+
+/// Replace the type marker of the root element with the given name, returns the offset of the marker
+fn corrupt_type_marker(data: &mut [u8], name: &str, marker: u8) -> usize {
+    let offset = data
+        .windows(name.len())
+        .position(|w| w == name.as_bytes())
+        .expect("Element not found")
+        + name.len();
+    data[offset] = marker;
+    offset
+}
+
+#[test]
+pub fn test_recover_amf0() {
+    let data = include_bytes!("sol/AS2-Demo.sol");
+    let expected = Reader::default().parse(data).unwrap();
+
+    let mut corrupt = data.to_vec();
+    corrupt_type_marker(&mut corrupt, "myFloat", 4);
+    assert!(Reader::default().parse(&corrupt).is_err());
+
+    let (lso, diagnostics) = Reader::default()
+        .parse_recover(&corrupt)
+        .expect("Failed to parse header");
+    let mut expected_body = expected.body.clone();
+    expected_body.retain(|e| e.name != "myFloat");
+    assert_eq!(lso.body, expected_body);
+    assert_eq!(diagnostics.len(), 1);
+}
+
+#[test]
+pub fn test_recover_amf3() {
+    let data = include_bytes!("sol/CoC_8.sol");
+    let expected = Reader::default().parse(data).unwrap();
+
+    let mut corrupt = data.to_vec();
+    let offset = corrupt_type_marker(&mut corrupt, "tailVenum", 0xff);
+
+    let (lso, diagnostics) = Reader::default()
+        .parse_recover(&corrupt)
+        .expect("Failed to parse header");
+    let mut expected_body = expected.body.clone();
+    expected_body.retain(|e| e.name != "tailVenum");
+    assert_eq!(lso.body, expected_body);
+    assert_eq!(diagnostics.len(), 1);
+    // The element starts with a one byte length followed by the name
+    assert_eq!(diagnostics[0].offset, offset - "tailVenum".len() - 1);
+}
+
+#[test]
+pub fn test_recover_truncated() {
+    let data = include_bytes!("sol/AS2-Demo.sol");
+    let expected = Reader::default().parse(data).unwrap();
+
+    let (lso, diagnostics) = Reader::default()
+        .parse_recover(&data[..data.len() - 4])
+        .expect("Failed to parse header");
+    assert_eq!(lso.body, expected.body[..expected.body.len() - 1]);
+    assert_eq!(diagnostics.len(), 1);
+}
+
+#[test]
+pub fn test_strict_conforming() {
+    for data in [
+        include_bytes!("sol/AS2-Demo.sol").as_slice(),
+        include_bytes!("sol/AS2-Date-Demo.sol").as_slice(),
+        include_bytes!("sol/CoC_8.sol").as_slice(),
+    ] {
+        let mut reader = Reader::default();
+        reader.set_strict(true);
+        assert_eq!(reader.parse(data), Reader::default().parse(data));
+    }
+}
+
+#[test]
+pub fn test_strict_amf0() {
+    let mut decoder = flash_lso::amf0::read::AMF0Decoder::default();
+    decoder.strict = true;
+
+    // Unknown marker
+    assert_eq!(
+        decoder.parse_single_element(&[0x20]),
+        Err(nom::Err::Error(Error::UnsupportedType(0x20)))
+    );
+    // MovieClip
+    assert_eq!(
+        decoder.parse_single_element(&[0x04]),
+        Err(nom::Err::Error(Error::ReservedType(0x04)))
+    );
+    // Date with a timezone of +24 hours
+    let mut date = vec![0x0B];
+    date.extend(0f64.to_be_bytes());
+    date.extend((24i16 * 60).to_be_bytes());
+    assert_eq!(
+        decoder.parse_single_element(&date),
+        Err(nom::Err::Error(Error::InvalidTimezone(24 * 60)))
+    );
+    // Offsets are positive west of UTC, so UTC+14 is -840 and UTC-12 is 720
+    let date_with_offset = |offset: i16| {
+        let mut date = vec![0x0B];
+        date.extend(0f64.to_be_bytes());
+        date.extend(offset.to_be_bytes());
+        date
+    };
+    for offset in [-14 * 60, -13 * 60, -9 * 60 - 30, 0, 5 * 60, 12 * 60] {
+        assert_eq!(
+            decoder
+                .parse_single_element(&date_with_offset(offset))
+                .unwrap()
+                .1
+                .deref(),
+            &Value::Date(0.0, Some(offset as u16))
+        );
+    }
+    for offset in [-14 * 60 - 1, 12 * 60 + 1] {
+        assert_eq!(
+            decoder.parse_single_element(&date_with_offset(offset)),
+            Err(nom::Err::Error(Error::InvalidTimezone(offset)))
+        );
+    }
+    // String that is not valid UTF-8
+    let string = [0x02, 0x00, 0x02, 0xC3, 0x28];
+    assert_eq!(
+        decoder.parse_single_element(&string),
+        Err(nom::Err::Error(Error::InvalidString))
+    );
+    // Same for object keys
+    assert_eq!(
+        decoder.parse_single_element(&[0x03, 0x00, 0x01, 0xFF, 0x05, 0x00, 0x00, 0x09]),
+        Err(nom::Err::Error(Error::InvalidString))
+    );
+
+    // All are accepted when not strict, apart from the invalid string
+    let mut decoder = flash_lso::amf0::read::AMF0Decoder::default();
+    assert_eq!(
+        decoder.parse_single_element(&[0x20]).unwrap().1.deref(),
+        &Value::Unsupported
+    );
+    assert!(decoder.parse_single_element(&date).is_ok());
+    assert!(matches!(
+        decoder.parse_single_element(&string),
+        Err(nom::Err::Error(Error::Nom(_, _)))
+    ));
+}
+
+#[test]
+pub fn test_strict_amf3() {
+    let mut decoder = flash_lso::amf3::read::AMF3Decoder::default();
+    decoder.strict = true;
+
+    // Integer 1 encoded in 2 bytes
+    assert_eq!(
+        decoder.parse_single_element(&[0x04, 0x80, 0x01]),
+        Err(nom::Err::Error(Error::OverlongInteger))
+    );
+    // String with an over-long length
+    assert_eq!(
+        decoder.parse_single_element(&[0x06, 0x80, 0x03, b'a']),
+        Err(nom::Err::Error(Error::OverlongInteger))
+    );
+    // Object with an inline trait that is both external and dynamic
+    assert_eq!(
+        decoder.parse_single_element(&[0x0A, 0x0F, 0x07, b'F', b'o', b'o']),
+        Err(nom::Err::Error(Error::DynamicExternal("Foo".to_string())))
+    );
+    // String that is not valid UTF-8
+    assert_eq!(
+        decoder.parse_single_element(&[0x06, 0x05, 0xC3, 0x28]),
+        Err(nom::Err::Error(Error::InvalidString))
+    );
+    // Same for XML
+    assert_eq!(
+        decoder.parse_single_element(&[0x0B, 0x03, 0xFF]),
+        Err(nom::Err::Error(Error::InvalidString))
+    );
+
+    let mut decoder = flash_lso::amf3::read::AMF3Decoder::default();
+    assert_eq!(
+        decoder
+            .parse_single_element(&[0x04, 0x80, 0x01])
+            .unwrap()
+            .1
+            .deref(),
+        &Value::Integer(1)
+    );
+}
+
+#[test]
+pub fn test_store() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::store::{Store, StoreKind, LOCAL_DOMAIN};
+    use flash_lso::write::Writer;
+
+    let home = std::env::temp_dir().join(format!("flash-lso-store-{}", std::process::id()));
+    let root = home.join(".macromedia/Flash_Player/#SharedObjects/ABCD1234");
+    let data = include_bytes!("sol/AS2-Demo.sol");
+
+    let store = Store::new(&root, StoreKind::FlashPlayer);
+    let path = store.object_path("example.com", "/games/demo.swf", "AS2-Demo");
+    assert_eq!(path, root.join("example.com/games/demo.swf/AS2-Demo.sol"));
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(&path, data)?;
+
+    let stores = Store::discover(&home)?;
+    assert_eq!(stores, vec![store.clone()]);
+    assert_eq!(store.domains()?, vec!["example.com".to_string()]);
+
+    let objects = store.all_objects()?;
+    assert_eq!(objects.len(), 1);
+    assert_eq!(objects[0].local_path, "/games/demo.swf");
+    assert_eq!(objects[0].name, "AS2-Demo");
+    assert_eq!(objects[0].path, path);
+
+    let mut lso = store.load(&path, &mut Reader::default())?;
+    assert_eq!(lso, Reader::default().parse(data)?);
+
+    let saved = store.object_path(LOCAL_DOMAIN, "/", "../copy");
+    assert_eq!(saved, root.join("localhost/copy.sol"));
+    assert_eq!(
+        store.object_path("../../etc", "x", "passwd"),
+        root.join("etc/x/passwd.sol")
+    );
+    assert_eq!(
+        store.object_path("/etc", "x", "passwd"),
+        root.join("etc/x/passwd.sol")
+    );
+    assert_eq!(store.objects("../example.com")?[0].path, path);
+    store.save(&saved, &mut lso, &mut Writer::default())?;
+    assert_eq!(std::fs::read(&saved)?, data.to_vec());
+
+    std::fs::remove_dir_all(&home)?;
+    Ok(())
+}
+
+#[test]
+pub fn test_flv_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::flv::{Flv, Header, ScriptData, Tag, TagData, ON_CUE_POINT, TAG_TYPE_VIDEO};
+    use flash_lso::types::{Element, ObjectId};
+    use std::rc::Rc;
+
+    let mut flv = Flv {
+        header: Header {
+            version: 1,
+            has_audio: false,
+            has_video: true,
+        },
+        tags: vec![Tag {
+            tag_type: TAG_TYPE_VIDEO,
+            timestamp: 0x01020304,
+            stream_id: 0,
+            data: TagData::Raw(vec![0x17, 0, 0, 0, 0]),
+        }],
+    };
+    assert!(flv.metadata().is_none());
+
+    flv.metadata_mut()
+        .set_property("duration", Rc::new(Value::Number(12.5)));
+    flv.metadata_mut()
+        .set_property("duration", Rc::new(Value::Number(13.0)));
+    let cue_point = Value::Object(
+        ObjectId::INVALID,
+        vec![Element::new("name", Rc::new(Value::String("intro".into())))],
+        None,
+    );
+    flv.tags.push(Tag::script(
+        500,
+        ScriptData::new(ON_CUE_POINT, Rc::new(cue_point)),
+    ));
+
+    let bytes = flash_lso::flv::write::write_to_bytes(&flv)?;
+    assert_eq!(&bytes[..13], b"FLV\x01\x01\x00\x00\x00\x09\x00\x00\x00\x00");
+
+    let parsed = flash_lso::flv::read::parse(&bytes).unwrap();
+    assert_eq!(parsed, flv);
+    assert_eq!(
+        parsed.metadata().and_then(|m| m.properties()),
+        Some(&[Element::new("duration", Rc::new(Value::Number(13.0)))][..])
+    );
+    assert_eq!(
+        parsed.cue_points().map(|(t, _)| t).collect::<Vec<_>>(),
+        vec![500]
+    );
+    assert!(flash_lso::flv::read::parse(&bytes[..bytes.len() - 1]).is_err());
+
+    // The declared entry count of metadata survives edits, and counts new properties
+    let mut metadata = ScriptData::new(
+        "onMetaData",
+        Rc::new(Value::ECMAArray(
+            ObjectId::INVALID,
+            vec![],
+            vec![
+                Element::new("duration", Rc::new(Value::Number(1.0))),
+                Element::new("width", Rc::new(Value::Number(320.0))),
+            ],
+            11,
+        )),
+    );
+    metadata.set_property("width", Rc::new(Value::Number(640.0)));
+    assert!(matches!(
+        metadata.value.as_ref(),
+        Value::ECMAArray(_, _, _, 11)
+    ));
+    metadata.set_property("height", Rc::new(Value::Number(480.0)));
+    assert!(matches!(
+        metadata.value.as_ref(),
+        Value::ECMAArray(_, _, _, 11)
+    ));
+    assert_eq!(
+        flv.metadata().map(|m| m.value.as_ref()),
+        Some(&Value::ECMAArray(
+            ObjectId::INVALID,
+            vec![],
+            vec![Element::new("duration", Rc::new(Value::Number(13.0)))],
+            1
+        ))
+    );
+    Ok(())
+}
+
+#[test]
+pub fn test_flv_undecodable_script() {
+    use flash_lso::flv::{TagData, TAG_TYPE_SCRIPT};
+
+    #[rustfmt::skip]
+    let data = [
+        b'F', b'L', b'V', 1, 0b100, 0, 0, 0, 9,
+        0, 0, 0, 0,
+        TAG_TYPE_SCRIPT, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF,
+        0, 0, 0, 13,
+    ];
+
+    let flv = flash_lso::flv::read::parse(&data).unwrap();
+    assert!(flv.header.has_audio && !flv.header.has_video);
+    assert_eq!(flv.tags[0].data, TagData::Raw(vec![0xFF, 0xFF]));
+}
+
+#[test]
+pub fn test_rtmp_command_amf0() {
+    use flash_lso::rtmp::{Command, MESSAGE_TYPE_COMMAND_AMF0, RESULT};
+    use flash_lso::types::Element;
+    use std::rc::Rc;
+
+    let connect = Command::connect(
+        1.0,
+        vec![
+            Element::new("app", Rc::new(Value::String("live".into()))),
+            Element::new(
+                "tcUrl",
+                Rc::new(Value::String("rtmp://localhost/live".into())),
+            ),
+        ],
+    );
+    assert_eq!(connect.message_type(), MESSAGE_TYPE_COMMAND_AMF0);
+
+    let bytes = flash_lso::rtmp::write::write_to_bytes(&connect).unwrap();
+    assert_eq!(&bytes[..10], b"\x02\x00\x07connect");
+    let parsed = flash_lso::rtmp::read::parse(MESSAGE_TYPE_COMMAND_AMF0, &bytes).unwrap();
+    assert_eq!(parsed, connect);
+
+    // _result(2, null, 1)
+    #[rustfmt::skip]
+    let data = [
+        0x02, 0x00, 0x07, b'_', b'r', b'e', b's', b'u', b'l', b't',
+        0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x05,
+        0x00, 0x3F, 0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let result = flash_lso::rtmp::read::parse_amf0(&data).unwrap();
+    assert_eq!(result.name, RESULT);
+    assert_eq!(result.transaction_id, 2.0);
+    assert_eq!(result.command_object, Rc::new(Value::Null));
+    assert_eq!(result.arguments, vec![Rc::new(Value::Number(1.0))]);
+    assert_eq!(
+        flash_lso::rtmp::write::write_to_bytes(&result).unwrap(),
+        data
+    );
+
+    // The name must be a string
+    assert!(flash_lso::rtmp::read::parse_amf0(&data[10..]).is_err());
+    assert!(flash_lso::rtmp::read::parse(8, &data).is_err());
+}
+
+#[test]
+pub fn test_rtmp_command_amf3() {
+    use flash_lso::rtmp::{Command, MESSAGE_TYPE_COMMAND_AMF3};
+    use flash_lso::types::AMFVersion;
+    use std::rc::Rc;
+
+    let mut status = Command::on_status("status", "NetStream.Play.Start", "Started playing");
+    status.version = AMFVersion::AMF3;
+    status
+        .arguments
+        .push(Rc::new(Value::AMF3(Rc::new(Value::Integer(5)))));
+    assert_eq!(status.message_type(), MESSAGE_TYPE_COMMAND_AMF3);
+
+    let bytes = flash_lso::rtmp::write::write_to_bytes(&status).unwrap();
+    assert_eq!(bytes[0], 0);
+    assert_eq!(&bytes[bytes.len() - 3..], &[0x11, 0x04, 0x05]);
+
+    let parsed = flash_lso::rtmp::read::parse(MESSAGE_TYPE_COMMAND_AMF3, &bytes).unwrap();
+    assert_eq!(parsed, status);
+
+    // The AMF3 variant requires the format byte
+    assert!(flash_lso::rtmp::read::parse_amf3(&bytes[1..]).is_err());
+}
+
+#[test]
+pub fn test_rso_message_amf0() {
+    use flash_lso::rso::{Event, SharedObjectMessage, FLAG_PERSISTENT};
+    use flash_lso::types::{AMFVersion, Element, Lso};
+    use std::rc::Rc;
+
+    // "chat", version 3, persistent, change(count = 1.0), use success
+    #[rustfmt::skip]
+    let data = [
+        0x00, 0x04, b'c', b'h', b'a', b't',
+        0x00, 0x00, 0x00, 0x03,
+        0x00, 0x00, 0x00, 0x02,
+        0x00, 0x00, 0x00, 0x00,
+        0x04, 0x00, 0x00, 0x00, 0x10,
+        0x00, 0x05, b'c', b'o', b'u', b'n', b't',
+        0x00, 0x3F, 0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x0B, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    let message = flash_lso::rso::read::parse(19, &data).unwrap();
+    assert_eq!(message.name, "chat");
+    assert_eq!(message.object_version, 3);
+    assert!(message.is_persistent());
+    assert_eq!(
+        message.events,
+        vec![
+            Event::Change(Element::new("count", Rc::new(Value::Number(1.0)))),
+            Event::UseSuccess
+        ]
+    );
+    assert_eq!(
+        flash_lso::rso::write::write_to_bytes(&message).unwrap(),
+        data
+    );
+
+    let mut lso = Lso::new_empty("chat", AMFVersion::AMF0);
+    message.apply(&mut lso);
+    let mut update = SharedObjectMessage::new(
+        "chat",
+        vec![
+            Event::Change(Element::new("count", Rc::new(Value::Number(2.0)))),
+            Event::Change(Element::new("topic", Rc::new(Value::Null))),
+            Event::Remove("topic".into()),
+            Event::SendMessage("say".into(), vec![Rc::new(Value::String("hi".into()))]),
+            Event::Status("SharedObject.BadPersistence".into(), "error".into()),
+        ],
+    );
+    update.flags = FLAG_PERSISTENT;
+    update.apply(&mut lso);
+    assert_eq!(
+        lso.body,
+        vec![Element::new("count", Rc::new(Value::Number(2.0)))]
+    );
+
+    let bytes = flash_lso::rso::write::write_to_bytes(&update).unwrap();
+    assert_eq!(flash_lso::rso::read::parse_amf0(&bytes).unwrap(), update);
+
+    // Unknown event type
+    let mut bad = data;
+    bad[18] = 0x20;
+    assert!(flash_lso::rso::read::parse_amf0(&bad).is_err());
+}
+
+#[test]
+pub fn test_rso_message_amf3() {
+    use flash_lso::rso::{Event, SharedObjectMessage, MESSAGE_TYPE_SHARED_OBJECT_AMF3};
+    use flash_lso::types::{AMFVersion, Element};
+    use std::rc::Rc;
+
+    let mut message = SharedObjectMessage::new(
+        "scores",
+        vec![
+            Event::Use,
+            Event::RequestChange(Element::new("best", Rc::new(Value::Integer(42)))),
+            Event::RequestChange(Element::new("first", Rc::new(Value::String("top".into())))),
+            Event::RequestChange(Element::new("second", Rc::new(Value::String("top".into())))),
+        ],
+    );
+    message.version = AMFVersion::AMF3;
+    assert_eq!(message.message_type(), MESSAGE_TYPE_SHARED_OBJECT_AMF3);
+
+    let bytes = flash_lso::rso::write::write_to_bytes(&message).unwrap();
+    assert_eq!(bytes[0], 0);
+    // The value of the second change is a reference to the string table
+    assert_eq!(&bytes[bytes.len() - 2..], &[0x06, 0x00]);
+    assert_eq!(
+        flash_lso::rso::read::parse(MESSAGE_TYPE_SHARED_OBJECT_AMF3, &bytes).unwrap(),
+        message
+    );
+}
+
+#[test]
+pub fn test_remoting_client() {
+    use flash_lso::packet::{Message, Packet};
+    use flash_lso::remoting::client::{Call, Client, Response, Transport};
+    use flash_lso::remoting::CONTENT_TYPE;
+    use flash_lso::types::AMFVersion;
+    use std::rc::Rc;
+
+    /// Echoes the first argument of each call, failing calls to `Service.fail`
+    struct EchoGateway;
+
+    impl Transport for EchoGateway {
+        fn send(&mut self, content_type: &str, body: &[u8]) -> std::io::Result<Vec<u8>> {
+            assert_eq!(content_type, CONTENT_TYPE);
+            let request = flash_lso::packet::read::parse(body).unwrap();
+            assert_eq!(request.headers.len(), 0);
+
+            // Respond in reverse order to check that responses are matched by URI
+            let messages = request
+                .messages
+                .iter()
+                .rev()
+                .map(|m| {
+                    let first = match m.contents.as_ref() {
+                        Value::StrictArray(_, args) => Rc::clone(&args[0]),
+                        _ => panic!("Arguments should be a strict array"),
+                    };
+                    let suffix = if m.target_uri == "Service.fail" {
+                        "/onStatus"
+                    } else {
+                        "/onResult"
+                    };
+                    Message {
+                        target_uri: format!("{}{}", m.response_uri, suffix),
+                        response_uri: "null".into(),
+                        contents: first,
+                    }
+                })
+                .collect();
+
+            let response = Packet {
+                version: AMFVersion::AMF0,
+                headers: vec![],
+                messages,
+            };
+            Ok(flash_lso::packet::write::write_to_bytes(&response, true).unwrap())
+        }
+    }
+
+    let mut client = Client::new(EchoGateway);
+    assert_eq!(
+        client
+            .call("Service.echo", vec![Rc::new(Value::Number(1.0))])
+            .unwrap(),
+        Response::Result(Rc::new(Value::Number(1.0)))
+    );
+
+    let responses = client
+        .call_batch(vec![
+            Call::new("Service.echo", vec![Rc::new(Value::Bool(true))]),
+            Call::new("Service.fail", vec![Rc::new(Value::Null)]),
+        ])
+        .unwrap();
+    assert_eq!(
+        responses,
+        vec![
+            Response::Result(Rc::new(Value::Bool(true))),
+            Response::Status(Rc::new(Value::Null))
+        ]
+    );
+}
+
+#[cfg(feature = "http")]
+#[test]
+pub fn test_remoting_http_transport() {
+    use flash_lso::remoting::client::{HttpTransport, Transport};
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length: ") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n1\r\n!\r\n0\r\n\r\n")
+            .unwrap();
+        (request_line, body)
+    });
+
+    let mut transport = HttpTransport::new(&format!("http://127.0.0.1:{}/gateway", port)).unwrap();
+    let response = transport.send("application/x-amf", b"request").unwrap();
+    assert_eq!(response, b"ok!");
+
+    let (request_line, body) = server.join().unwrap();
+    assert_eq!(request_line, "POST /gateway HTTP/1.1\r\n");
+    assert_eq!(body, b"request");
+
+    assert!(HttpTransport::new("https://example.com/gateway").is_err());
+}
+
+#[test]
+pub fn test_remoting_dispatcher() {
+    use flash_lso::packet::{Header, Message, Packet};
+    use flash_lso::remoting::server::Dispatcher;
+    use flash_lso::types::{AMFVersion, ObjectId};
+    use std::rc::Rc;
+
+    let mut dispatcher = Dispatcher::new();
+    dispatcher.register("Math.add", |_, args| match args {
+        [a, b] => match (a.as_ref(), b.as_ref()) {
+            (Value::Number(a), Value::Number(b)) => Ok(Rc::new(Value::Number(a + b))),
+            _ => Err(Rc::new(Value::String("Expected numbers".into()))),
+        },
+        _ => Err(Rc::new(Value::String("Expected two arguments".into()))),
+    });
+    dispatcher.understand_header("Credentials");
+
+    let call = |target: &str, response: &str, args: Vec<Rc<Value>>| Message {
+        target_uri: target.into(),
+        response_uri: response.into(),
+        contents: Rc::new(Value::StrictArray(ObjectId::INVALID, args)),
+    };
+    let mut request = Packet {
+        version: AMFVersion::AMF0,
+        headers: vec![Header {
+            name: "Credentials".into(),
+            must_understand: true,
+            value: Rc::new(Value::Null),
+        }],
+        messages: vec![
+            call(
+                "Math.add",
+                "/1",
+                vec![Rc::new(Value::Number(1.0)), Rc::new(Value::Number(2.0))],
+            ),
+            call("Math.add", "/2", vec![]),
+            call("Math.missing", "/3", vec![]),
+        ],
+    };
+
+    let body = flash_lso::packet::write::write_to_bytes(&request, true).unwrap();
+    let response = dispatcher.handle(&body).unwrap();
+    let response = flash_lso::packet::read::parse(&response).unwrap();
+    let targets: Vec<_> = response
+        .messages
+        .iter()
+        .map(|m| m.target_uri.as_str())
+        .collect();
+    assert_eq!(targets, vec!["/1/onResult", "/2/onStatus", "/3/onStatus"]);
+    assert_eq!(response.messages[0].contents, Rc::new(Value::Number(3.0)));
+
+    // Headers that must be understood but are not fail every message
+    request.headers[0].name = "Unknown".into();
+    let response = dispatcher.dispatch(&request);
+    assert!(response
+        .messages
+        .iter()
+        .all(|m| m.target_uri.ends_with("/onStatus")));
+}
+
+#[cfg(feature = "flex")]
+#[test]
+pub fn test_remoting_dispatcher_flex() {
+    use flash_lso::extra::flex::messages::{
+        AbstractMessage, AcknowledgeMessage, AsyncMessage, CommandMessage, CommandOperation,
+        ErrorMessage, RemotingMessage, Uuid,
+    };
+    use flash_lso::packet::{Message, Packet};
+    use flash_lso::remoting::server::Dispatcher;
+    use flash_lso::types::{AMFVersion, ObjectId};
+    use std::convert::TryFrom;
+    use std::rc::Rc;
+
+    let mut dispatcher = Dispatcher::new();
+    dispatcher.set_login_handler(|user, password| user == "admin" && password == "hunter2");
+    dispatcher.register("echo.say", |_, args| Ok(Rc::clone(&args[0])));
+
+    let string = |s: &str| Rc::new(Value::String(s.to_string()));
+    let command = |operation: CommandOperation, body: Rc<Value>| {
+        let command = CommandMessage {
+            base: AsyncMessage {
+                base: AbstractMessage {
+                    body: Some(body),
+                    message_id: Some(Uuid::String("REQUEST".to_string())),
+                    ..AbstractMessage::default()
+                },
+                ..AsyncMessage::default()
+            },
+            operation: Some(operation),
+        };
+        Rc::new(Value::AMF3(Rc::new(command.into_standard_value())))
+    };
+    let remoting = RemotingMessage {
+        base: AbstractMessage {
+            body: Some(Rc::new(Value::StrictArray(
+                ObjectId::INVALID,
+                vec![string("hi")],
+            ))),
+            destination: Some("echo".to_string()),
+            ..AbstractMessage::default()
+        },
+        operation: Some("say".to_string()),
+        ..RemotingMessage::default()
+    };
+
+    let message = |response: &str, contents: Rc<Value>| Message {
+        target_uri: "null".into(),
+        response_uri: response.into(),
+        contents: Rc::new(Value::StrictArray(ObjectId::INVALID, vec![contents])),
+    };
+    let request = Packet {
+        version: AMFVersion::AMF3,
+        headers: vec![],
+        messages: vec![
+            message(
+                "/1",
+                command(CommandOperation::ClientPing, Rc::new(Value::Null)),
+            ),
+            // Login as admin:hunter2 and admin:wrong
+            message(
+                "/2",
+                command(CommandOperation::Login, string("YWRtaW46aHVudGVyMg==")),
+            ),
+            message(
+                "/3",
+                command(CommandOperation::Login, string("YWRtaW46d3Jvbmc=")),
+            ),
+            message("/4", Rc::new(Value::AMF3(Rc::new(remoting.into())))),
+        ],
+    };
+
+    let body = flash_lso::packet::write::write_to_bytes(&request, true).unwrap();
+    let response = dispatcher.handle(&body).unwrap();
+    let response = flash_lso::packet::read::parse(&response).unwrap();
+    let targets: Vec<_> = response
+        .messages
+        .iter()
+        .map(|m| m.target_uri.as_str())
+        .collect();
+    assert_eq!(
+        targets,
+        vec!["/1/onResult", "/2/onResult", "/3/onStatus", "/4/onResult"]
+    );
+
+    // Replies are sent in the standard form
+    let contents = |m: &Message| match m.contents.as_ref() {
+        Value::AMF3(inner) => Rc::clone(inner),
+        _ => panic!("Expected an AMF3 value"),
+    };
+    let ping = contents(&response.messages[0]);
+    match ping.as_ref() {
+        Value::Object(_, _, Some(class_def)) => {
+            assert_eq!(class_def.name, AcknowledgeMessage::STANDARD_CLASS_NAME)
+        }
+        _ => panic!("Expected a typed object"),
+    }
+    let ping = AcknowledgeMessage::try_from(ping.as_ref()).unwrap();
+    assert_eq!(
+        ping.base.correlation_id,
+        Some(Uuid::String("REQUEST".to_string()))
+    );
+    assert!(ping.base.base.message_id.is_some());
+
+    let login = AcknowledgeMessage::try_from(contents(&response.messages[1]).as_ref()).unwrap();
+    assert_eq!(login.base.base.body, Some(string("success")));
+
+    let error = ErrorMessage::try_from(contents(&response.messages[2]).as_ref()).unwrap();
+    assert_eq!(error.fault_code.as_deref(), Some("Client.Authentication"));
+
+    let echo = AcknowledgeMessage::try_from(contents(&response.messages[3]).as_ref()).unwrap();
+    assert_eq!(echo.base.base.body, Some(string("hi")));
+}
+
+#[cfg(feature = "flex")]
+#[test]
+pub fn test_flex_typed_messages() {
+    use flash_lso::extra::flex::messages::{
+        AbstractMessage, AcknowledgeMessage, AsyncMessage, CommandMessage, CommandOperation,
+        ErrorMessage, Uuid,
+    };
+    use flash_lso::types::{ClassDefinition, Element};
+    use std::convert::TryFrom;
+    use std::rc::Rc;
+
+    // Elements as produced by the command message decoder
+    let value = Value::Custom(
+        vec![
+            Element::new("body", Rc::new(Value::Null)),
+            Element::new("client_id_bytes", Rc::new(Value::ByteArray(vec![7; 16]))),
+            Element::new("message_id", Rc::new(Value::String("ABC".into()))),
+            Element::new("timestamp", Rc::new(Value::Number(1000.0))),
+            Element::new("ttl", Rc::new(Value::Integer(0))),
+            Element::new("children_3", Rc::new(Value::Bool(true))),
+            Element::new("correlation_id", Rc::new(Value::String("DEF".into()))),
+            Element::new("operation", Rc::new(Value::Integer(5))),
+        ],
+        vec![],
+        Some(ClassDefinition::default_with_name("DSC".to_string())),
+    );
+
+    let command = CommandMessage::try_from(&value).unwrap();
+    assert_eq!(command.operation, Some(CommandOperation::ClientPing));
+    assert_eq!(
+        command.base.correlation_id,
+        Some(Uuid::String("DEF".into()))
+    );
+    assert_eq!(command.base.base.client_id, Some(Uuid::Bytes(vec![7; 16])));
+    assert_eq!(command.base.base.timestamp, Some(1000.0));
+    assert_eq!(command.base.base.time_to_live, Some(0.0));
+    assert_eq!(
+        command.base.base.extra,
+        vec![Element::new("children_3", Rc::new(Value::Bool(true)))]
+    );
+
+    let round_trip = Value::from(command.clone());
+    match &round_trip {
+        Value::Custom(_, _, Some(def)) => assert_eq!(def.name, CommandMessage::CLASS_NAME),
+        _ => panic!("Expected a custom value"),
+    }
+    assert_eq!(CommandMessage::try_from(&round_trip).unwrap(), command);
+
+    let error = ErrorMessage {
+        base: AcknowledgeMessage {
+            base: AsyncMessage {
+                base: AbstractMessage {
+                    message_id: Some(Uuid::String("GHI".into())),
+                    ..Default::default()
+                },
+                correlation_id: Some(Uuid::String("ABC".into())),
+            },
+        },
+        fault_code: Some("Server.Processing".into()),
+        ..Default::default()
+    };
+    assert_eq!(
+        ErrorMessage::try_from(&Value::from(error.clone())).unwrap(),
+        error
+    );
+
+    assert_eq!(
+        CommandMessage::try_from(&Value::Null),
+        Err(Error::UnexpectedValue("a flex message"))
+    );
+    assert_eq!(
+        ErrorMessage::try_from(&value),
+        Err(Error::UnexpectedClass(
+            ErrorMessage::CLASS_NAME,
+            "DSC".to_string()
+        ))
+    );
+    let bad = Value::Custom(
+        vec![Element::new("operation", Rc::new(Value::Null))],
+        vec![],
+        Some(ClassDefinition::default_with_name(
+            CommandMessage::CLASS_NAME.to_string(),
+        )),
+    );
+    assert_eq!(
+        CommandMessage::try_from(&bad),
+        Err(Error::InvalidMember("operation".to_string()))
+    );
+    assert_eq!(i32::from(CommandOperation::Other(42)), 42);
+}
+
+#[cfg(feature = "flex")]
+#[test]
+pub fn test_flex_small_messages() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::extra::flex;
+    use flash_lso::extra::flex::messages::{
+        AbstractMessage, AcknowledgeMessage, AsyncMessage, CommandMessage, CommandOperation,
+        RemotingMessage, Uuid,
+    };
+    use flash_lso::types::{AMFVersion, Element, Lso};
+    use flash_lso::write::Writer;
+    use std::convert::TryFrom;
+    use std::rc::Rc;
+
+    let with_class = |value: Value, name: &str| match value {
+        Value::Custom(elements, dynamic, Some(mut def)) => {
+            def.name = name.to_string();
+            Rc::new(Value::Custom(elements, dynamic, Some(def)))
+        }
+        _ => panic!("Expected a custom value"),
+    };
+
+    let base = AbstractMessage {
+        body: Some(Rc::new(Value::String("body".into()))),
+        client_id: Some(Uuid::Bytes(vec![1; 16])),
+        message_id: Some(Uuid::String("MESSAGE".into())),
+        timestamp: Some(1234.0),
+        time_to_live: Some(0.0),
+        ..Default::default()
+    };
+    let ack = AcknowledgeMessage {
+        base: AsyncMessage {
+            base: base.clone(),
+            correlation_id: Some(Uuid::String("REQUEST".into())),
+        },
+    };
+    let command = CommandMessage {
+        base: AsyncMessage {
+            base: base.clone(),
+            correlation_id: None,
+        },
+        operation: Some(CommandOperation::ClientPing),
+    };
+    let remoting = RemotingMessage {
+        base: AbstractMessage {
+            destination: Some("echo".into()),
+            body: Some(Rc::new(Value::String("hi".into()))),
+            ..Default::default()
+        },
+        operation: Some("say".into()),
+        source: None,
+    };
+
+    let mut lso = Lso::new(
+        vec![
+            Element::new("ack", with_class(ack.clone().into(), "DSK")),
+            Element::new("command", with_class(command.clone().into(), "DSC")),
+            Element::new("remoting", Rc::new(remoting.clone().into())),
+        ],
+        "flex",
+        AMFVersion::AMF3,
+    );
+
+    let mut buffer = vec![];
+    let mut writer = Writer::default();
+    codecs(flex::register_codecs).install_writer(&mut writer);
+    writer.write_full(&mut buffer, &mut lso)?;
+
+    let mut reader = Reader::default();
+    codecs(flex::register_codecs).install_reader(&mut reader);
+    let decoded = reader
+        .parse(&buffer)
+        .expect("Failed to parse flex messages");
+
+    assert_eq!(
+        AcknowledgeMessage::try_from(decoded.body[0].value()).unwrap(),
+        ack
+    );
+    assert_eq!(
+        CommandMessage::try_from(decoded.body[1].value()).unwrap(),
+        command
+    );
+    assert_eq!(
+        RemotingMessage::try_from(decoded.body[2].value()).unwrap(),
+        remoting
+    );
+    Ok(())
+}
+
+#[cfg(feature = "flex")]
+#[test]
+pub fn test_flex_uuid() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::extra::flex;
+    use flash_lso::extra::flex::messages::{
+        AbstractMessage, AcknowledgeMessage, AsyncMessage, Uuid,
+    };
+    use flash_lso::extra::flex::uuid::{bytes_to_string, string_to_bytes};
+    use flash_lso::types::{AMFVersion, Element, Lso};
+    use flash_lso::write::Writer;
+    use std::convert::TryFrom;
+    use std::rc::Rc;
+
+    let id = "CA5B3C0E-5A8B-4F4D-A5B6-0F7B2B1B8B6C";
+    let bytes = string_to_bytes(id).unwrap();
+    assert_eq!(bytes[..4], [0xCA, 0x5B, 0x3C, 0x0E]);
+    assert_eq!(bytes_to_string(&bytes).unwrap(), id);
+    assert_eq!(string_to_bytes(&id.to_lowercase()), Some(bytes));
+    assert_eq!(string_to_bytes("CA5B3C0E5A8B4F4DA5B60F7B2B1B8B6C"), None);
+    assert_eq!(
+        string_to_bytes("+A5B3C0E-5A8B-4F4D-A5B6-0F7B2B1B8B6C"),
+        None
+    );
+    assert_eq!(bytes_to_string(&bytes[..15]), None);
+    assert_eq!(
+        Uuid::Bytes(bytes.to_vec()).to_canonical_string(),
+        Some(id.to_string())
+    );
+    assert_eq!(Uuid::String(id.to_string()).to_bytes(), Some(bytes));
+
+    // The compact encoder writes canonical ids as bytes, other ids are left as strings
+    let ack = AcknowledgeMessage {
+        base: AsyncMessage {
+            base: AbstractMessage {
+                message_id: Some(Uuid::String(id.to_string())),
+                client_id: Some(Uuid::String("not-a-uuid".to_string())),
+                ..Default::default()
+            },
+            correlation_id: Some(Uuid::String(id.to_string())),
+        },
+    };
+    let mut lso = Lso::new(
+        vec![Element::new("ack", Rc::new(ack.into()))],
+        "flex",
+        AMFVersion::AMF3,
+    );
+
+    let mut buffer = vec![];
+    let mut writer = Writer::default();
+    codecs(flex::register_codecs_compact).install_writer(&mut writer);
+    writer.write_full(&mut buffer, &mut lso)?;
+
+    let mut reader = Reader::default();
+    codecs(flex::register_codecs).install_reader(&mut reader);
+    let decoded = reader.parse(&buffer).expect("Failed to parse flex message");
+    let decoded = AcknowledgeMessage::try_from(decoded.body[0].value()).unwrap();
+    assert_eq!(
+        decoded.base.base.message_id,
+        Some(Uuid::Bytes(bytes.to_vec()))
+    );
+    assert_eq!(
+        decoded.base.correlation_id,
+        Some(Uuid::Bytes(bytes.to_vec()))
+    );
+    assert_eq!(
+        decoded.base.base.client_id,
+        Some(Uuid::String("not-a-uuid".to_string()))
+    );
+    Ok(())
+}
+
+#[test]
+pub fn test_external_codec_registry() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::amf3::custom_encoder::{ExternalCodec, ExternalCodecRegistry};
+    use flash_lso::amf3::read::AMF3Decoder;
+    use flash_lso::amf3::write::AMF3Encoder;
+    use flash_lso::types::{AMFVersion, Attribute, ClassDefinition, Element, Lso};
+    use flash_lso::write::Writer;
+    use std::rc::Rc;
+
+    /// A point written as two big endian u16s
+    struct Point;
+
+    impl ExternalCodec for Point {
+        fn decode<'a>(
+            &self,
+            i: &'a [u8],
+            _decoder: &mut AMF3Decoder,
+        ) -> nom::IResult<&'a [u8], Vec<Element>, Error<'a>> {
+            let (i, x) = nom::number::complete::be_u16(i)?;
+            let (i, y) = nom::number::complete::be_u16(i)?;
+            Ok((
+                i,
+                vec![
+                    Element::new("x", Rc::new(Value::Integer(x as i32))),
+                    Element::new("y", Rc::new(Value::Integer(y as i32))),
+                ],
+            ))
+        }
+
+        fn encode(
+            &self,
+            elements: &[Element],
+            _class_def: &Option<ClassDefinition>,
+            _encoder: &AMF3Encoder,
+        ) -> std::io::Result<Vec<u8>> {
+            Ok(elements
+                .iter()
+                .flat_map(|e| match e.value() {
+                    Value::Integer(i) => (*i as u16).to_be_bytes(),
+                    _ => panic!("Expected an integer"),
+                })
+                .collect())
+        }
+    }
+
+    let mut registry = ExternalCodecRegistry::new();
+    registry.register("com.example.Point", Rc::new(Point));
+    assert!(registry.contains("com.example.Point"));
+
+    let mut class_def = ClassDefinition::default_with_name("com.example.Point".to_string());
+    class_def.attributes.insert(Attribute::External);
+    let point = Value::Custom(
+        vec![
+            Element::new("x", Rc::new(Value::Integer(3))),
+            Element::new("y", Rc::new(Value::Integer(4))),
+        ],
+        vec![],
+        Some(class_def),
+    );
+    let mut lso = Lso::new(
+        vec![Element::new("point", Rc::new(point))],
+        "points",
+        AMFVersion::AMF3,
+    );
+
+    let mut writer = Writer::default();
+    registry.install_writer(&mut writer);
+    let length = writer.encoded_length(&lso)?;
+    let mut buffer = vec![];
+    writer.write_full(&mut buffer, &mut lso)?;
+    assert_eq!(length, buffer.len());
+
+    // Without the codec the external object can't be read
+    assert!(Reader::default().parse(&buffer).is_err());
+
+    let mut reader = Reader::default();
+    registry.install_reader(&mut reader);
+    assert_eq!(reader.parse(&buffer).unwrap(), lso);
+
+    // Without the codec the external object can't be written, or measured
+    assert_eq!(
+        Writer::default().encoded_length(&lso).unwrap_err().kind(),
+        std::io::ErrorKind::InvalidData
+    );
+    assert_eq!(
+        flash_lso::write::write_to_bytes(&mut lso),
+        Err(Error::IoError(
+            "No encoder for externalized class 'com.example.Point'".to_string(),
+            std::io::ErrorKind::InvalidData
+        ))
+    );
+
+    // The same applies when nested inside of another external object
+    #[cfg(feature = "flex")]
+    {
+        let mut class_def =
+            ClassDefinition::default_with_name("flex.messaging.io.ArrayCollection".to_string());
+        class_def.attributes.insert(Attribute::External);
+        let collection = Value::Custom(
+            vec![Element::new("data", lso.body[0].value.clone())],
+            vec![],
+            Some(class_def),
+        );
+        let mut lso = Lso::new(
+            vec![Element::new("points", Rc::new(collection))],
+            "points",
+            AMFVersion::AMF3,
+        );
+        let mut writer = Writer::default();
+        codecs(flash_lso::extra::flex::register_codecs).install_writer(&mut writer);
+        let error = writer.write_full(&mut vec![], &mut lso).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+    Ok(())
+}
+
+#[test]
+pub fn test_unknown_external_class() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::amf3::read::{UnknownExternalPolicy, UNKNOWN_EXTERNAL_DATA};
+    use flash_lso::types::{AMFVersion, Attribute, ClassDefinition, Element, Lso};
+    use flash_lso::write::Writer;
+    use std::rc::Rc;
+
+    let mut class_def = ClassDefinition::default_with_name("com.example.Blob".to_string());
+    class_def.attributes.insert(Attribute::External);
+    let blob = Value::Custom(
+        vec![Element::new(
+            UNKNOWN_EXTERNAL_DATA,
+            Rc::new(Value::ByteArray(vec![1, 2, 3, 4])),
+        )],
+        vec![],
+        Some(class_def),
+    );
+    let mut lso = Lso::new(
+        vec![
+            Element::new("name", Rc::new(Value::String("blobs".to_string()))),
+            Element::new("blob", Rc::new(blob)),
+        ],
+        "blobs",
+        AMFVersion::AMF3,
+    );
+
+    // Captured data is written back verbatim without needing an encoder
+    let mut buffer = vec![];
+    Writer::default().write_full(&mut buffer, &mut lso)?;
+    let mut header = vec![];
+    Writer::default().write_full(&mut header, &mut Lso::new_empty("blobs", AMFVersion::AMF3))?;
+    let data_offset = buffer.len() - header.len() - 4 - 1;
+    assert_eq!(&buffer[buffer.len() - 5..], &[1, 2, 3, 4, 0]);
+
+    match Reader::default().parse(&buffer) {
+        Err(nom::Err::Failure(Error::UnknownExternalClass(name, offset))) => {
+            assert_eq!(name, "com.example.Blob");
+            assert_eq!(offset, data_offset);
+        }
+        other => panic!("Expected an unknown external class error, got {:?}", other),
+    }
+
+    let mut reader = Reader::default();
+    reader.amf3_decoder.unknown_external = UnknownExternalPolicy::Capture;
+    let mut read = reader.parse(&buffer).unwrap();
+    assert_eq!(read, lso);
+
+    let mut rewritten = vec![];
+    Writer::default().write_full(&mut rewritten, &mut read)?;
+    assert_eq!(rewritten, buffer);
+    Ok(())
+}
+
+#[test]
+pub fn test_amf0_writer() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::amf0::writer::{Amf0Writer, CacheKey, ObjWriter};
+    use flash_lso::types::ClassDefinition;
+    use flash_lso::write::Writer;
+
+    let (player, inventory, item) = (1u8, 2u8, 3u8);
+
+    let mut w = Amf0Writer::default();
+    w.string("name", "save");
+    let (writer, player_ref) = w.typed_object(
+        CacheKey::from_ptr(&player),
+        ClassDefinition::default_with_name("com.example.Player".to_string()),
+    );
+    let mut player_writer = writer.unwrap();
+    player_writer.number("level", 3.0);
+    {
+        let (writer, _) = player_writer.object(CacheKey::from_ptr(&inventory));
+        let mut inventory_writer = writer.unwrap();
+        {
+            let (writer, _) = inventory_writer.array(CacheKey::from_ptr(&item));
+            let mut item_writer = writer.unwrap();
+            item_writer.string("name", "sword");
+            item_writer.reference("owner", player_ref);
+            item_writer.commit("0", 1);
+        }
+        inventory_writer.commit("inventory");
+    }
+    player_writer.commit("player");
+
+    // Writing the same object again gives a reference to the original
+    let (writer, existing_ref) = w.object(CacheKey::from_ptr(&player));
+    assert!(writer.is_none());
+    assert_eq!(existing_ref, player_ref);
+    w.reference("current", existing_ref);
+
+    let mut lso = w.commit_lso("save");
+    let mut buffer = vec![];
+    Writer::default().write_full(&mut buffer, &mut lso)?;
+    assert_eq!(Reader::default().parse(&buffer).unwrap(), lso);
+    Ok(())
+}
+
+#[test]
+pub fn test_amf0_writer_nested_references() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::amf0::read::AMF0Decoder;
+    use flash_lso::amf0::writer::{Amf0Writer, CacheKey, ObjWriter};
+    use flash_lso::types::{ClassDefinition, Reference};
+    use std::rc::Rc;
+
+    fn child(value: &Value, name: &str) -> Rc<Value> {
+        match value {
+            Value::Object(_, elements, _) | Value::ECMAArray(_, _, elements, _) => elements
+                .iter()
+                .find(|e| e.name == name)
+                .map(|e| e.value.clone())
+                .unwrap(),
+            _ => panic!("Expected an object or array"),
+        }
+    }
+
+    fn reference(value: &Value) -> Reference {
+        match value {
+            Value::Reference(r) => *r,
+            _ => panic!("Expected a reference"),
+        }
+    }
+
+    let (outer, middle, inner, items) = (1u8, 2u8, 3u8, 4u8);
+
+    let mut w = Amf0Writer::default();
+    w.string("name", "save");
+    let (writer, outer_ref) = w.object(CacheKey::from_ptr(&outer));
+    let mut outer_writer = writer.unwrap();
+    outer_writer.number("level", 1.0);
+    {
+        let (writer, middle_ref) = outer_writer.object(CacheKey::from_ptr(&middle));
+        let mut middle_writer = writer.unwrap();
+        middle_writer.string("level", "2");
+        {
+            let (writer, _) = middle_writer.typed_object(
+                CacheKey::from_ptr(&inner),
+                ClassDefinition::default_with_name("com.example.Inner".to_string()),
+            );
+            let mut inner_writer = writer.unwrap();
+            inner_writer.bool("level", true);
+            inner_writer.reference("outer", outer_ref);
+            inner_writer.reference("middle", middle_ref);
+            {
+                let (writer, items_ref) = inner_writer.array(CacheKey::from_ptr(&items));
+                let mut items_writer = writer.unwrap();
+                items_writer.number("0", 4.0);
+                items_writer.reference("self", items_ref);
+                items_writer.commit("items", 1);
+            }
+            inner_writer.commit("inner");
+        }
+        middle_writer.commit("middle");
+    }
+    let (writer, inner_ref) = outer_writer.object(CacheKey::from_ptr(&inner));
+    assert!(writer.is_none());
+    outer_writer.reference("inner", inner_ref);
+    outer_writer.commit("outer");
+    w.reference("again", outer_ref);
+
+    let mut lso = w.commit_lso("save");
+    let buffer = flash_lso::write::write_to_bytes(&mut lso)?;
+    let body_start = buffer.len() - flash_lso::amf0::write::body_length(&lso.body)?;
+
+    let mut decoder = AMF0Decoder::default();
+    let (_, body) = decoder.parse_body(&buffer[body_start..]).unwrap();
+    assert_eq!(body, lso.body);
+
+    let outer = body[1].value.clone();
+    let middle = child(&outer, "middle");
+    let inner = child(&middle, "inner");
+    let items = child(&inner, "items");
+
+    let resolve = |value: Rc<Value>| decoder.resolve_reference(reference(&value)).unwrap();
+    assert_eq!(resolve(child(&inner, "outer")), outer);
+    assert_eq!(resolve(child(&inner, "middle")), middle);
+    assert_eq!(resolve(child(&items, "self")), items);
+    assert_eq!(resolve(child(&outer, "inner")), inner);
+    assert_eq!(resolve(body[2].value.clone()), outer);
+    Ok(())
+}
+
+#[test]
+pub fn test_amf3_writer() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::amf3::writer::{Amf3Writer, CacheKey, ObjWriter};
+    use flash_lso::types::{Attribute, ClassDefinition};
+    use flash_lso::write::Writer;
+
+    let (player, inventory, item, stats, points) = (1u8, 2u8, 3u8, 4u8, 5u8);
+
+    let mut w = Amf3Writer::default();
+    w.string("name", "save");
+    let (writer, player_id) = w.typed_object(
+        CacheKey::from_ptr(&player),
+        ClassDefinition {
+            name: "com.example.Player".to_string(),
+            attributes: Attribute::Dynamic.into(),
+            static_properties: vec!["level".to_string(), "name".to_string()],
+        },
+    );
+    let mut player_writer = writer.unwrap();
+    player_writer.bool("alive", true);
+    player_writer.string("name", "alice");
+    player_writer.integer("level", 3);
+    {
+        let (writer, _) = player_writer.array(CacheKey::from_ptr(&inventory));
+        let mut inventory_writer = writer.unwrap();
+        inventory_writer.string("owner", "alice");
+        let (writer, _) = inventory_writer.object(CacheKey::from_ptr(&item));
+        let mut item_writer = writer.unwrap();
+        item_writer.string("name", "sword");
+        item_writer.reference("owner", player_id);
+        let item_value = item_writer.into_value()?;
+        inventory_writer.push(item_value);
+        inventory_writer.commit("inventory");
+    }
+    {
+        let (writer, _) = player_writer.dictionary(CacheKey::from_ptr(&stats), false);
+        let mut stats_writer = writer.unwrap();
+        stats_writer.number("speed", 1.5);
+        stats_writer.insert(Value::Integer(7), Value::Bool(false));
+        stats_writer.commit("stats");
+    }
+    {
+        let (writer, _) =
+            player_writer.object_vector(CacheKey::from_ptr(&points), "flash.geom.Point", false);
+        let mut points_writer = writer.unwrap();
+        points_writer.null("");
+        points_writer.commit("points");
+    }
+    player_writer.vector_int("scores", &[1, 2, 3], false);
+    player_writer.commit("player")?;
+
+    // Writing the same object again gives a reference to the original
+    let (writer, existing_id) = w.object(CacheKey::from_ptr(&player));
+    assert!(writer.is_none());
+    assert_eq!(existing_id, player_id);
+    w.reference("current", existing_id);
+
+    let mut lso = w.commit_lso("save");
+    let mut buffer = vec![];
+    Writer::default().write_full(&mut buffer, &mut lso)?;
+    let parsed = Reader::default().parse(&buffer).unwrap();
+    assert_eq!(parsed, lso);
+
+    // References written by id resolve to the objects they were given for
+    let player = parsed.get("player").unwrap();
+    assert!(matches!(player, Value::Object(id, _, _) if *id == player_id));
+    assert_eq!(
+        parsed.get("current"),
+        Some(&Value::Amf3ObjectReference(player_id))
+    );
+    let item = match player.get("inventory").unwrap() {
+        Value::ECMAArray(_, dense, _, _) => dense[0].clone(),
+        other => panic!("Unexpected inventory {:?}", other),
+    };
+    assert_eq!(
+        item.get("owner"),
+        Some(&Value::Amf3ObjectReference(player_id))
+    );
+    Ok(())
+}
+
+#[test]
+pub fn test_amf3_writer_sealed_object() -> Result<(), Box<dyn std::error::Error>> {
+    use enumset::EnumSet;
+    use flash_lso::amf3::writer::{Amf3Writer, CacheKey, ObjWriter};
+    use flash_lso::types::ClassDefinition;
+    use flash_lso::write::write_to_bytes;
+
+    let sealed = ClassDefinition {
+        name: "Sealed".to_string(),
+        attributes: EnumSet::empty(),
+        static_properties: vec!["a".to_string(), "b".to_string()],
+    };
+    let key = 1u8;
+
+    // Static properties that aren't written are undefined
+    let mut w = Amf3Writer::default();
+    let (writer, _) = w.typed_object(CacheKey::from_ptr(&key), sealed.clone());
+    let mut obj_writer = writer.unwrap();
+    obj_writer.integer("a", 1);
+    obj_writer.commit("obj")?;
+    let mut lso = w.commit_lso("save");
+    let bytes = write_to_bytes(&mut lso)?;
+    #[rustfmt::skip]
+    let body = [
+        0x07, b'o', b'b', b'j',
+        0x0A, 0x23, 0x0D, b'S', b'e', b'a', b'l', b'e', b'd', 0x03, b'a', 0x03, b'b',
+        0x04, 0x01,
+        0x00,
+        0x00,
+    ];
+    assert!(bytes.ends_with(&body));
+    let parsed = Reader::default().parse(&bytes).unwrap();
+    assert_eq!(parsed.get("obj").unwrap().get("b"), Some(&Value::Undefined));
+    assert_eq!(parsed, lso);
+
+    // Members that aren't declared can't be written to a sealed class
+    let mut w = Amf3Writer::default();
+    let (writer, _) = w.typed_object(CacheKey::from_ptr(&key), sealed);
+    let mut obj_writer = writer.unwrap();
+    obj_writer.integer("a", 1);
+    obj_writer.integer("extra", 2);
+    assert_eq!(
+        obj_writer.commit("obj"),
+        Err(Error::UndeclaredMember(
+            "Sealed".to_string(),
+            "extra".to_string()
+        ))
+    );
+    assert!(w.commit_lso("save").body.is_empty());
+    Ok(())
+}
+
+#[test]
+pub fn test_value_accessors() {
+    use flash_lso::types::{Element, ObjectId};
+    use std::convert::TryFrom;
+    use std::rc::Rc;
+
+    assert_eq!(Value::from(1.5).as_f64(), Some(1.5));
+    assert_eq!(Value::from(3).as_f64(), Some(3.0));
+    assert_eq!(Value::from(3.0).as_i64(), Some(3));
+    assert_eq!(Value::from(3.5).as_i64(), None);
+    assert_eq!(Value::from("abc").as_str(), Some("abc"));
+    assert_eq!(Value::from(true).as_bool(), Some(true));
+    assert_eq!(Value::from(vec![1, 2]).as_bytes(), Some(&[1u8, 2][..]));
+    assert_eq!(Value::Null.as_str(), None);
+
+    assert_eq!(i32::try_from(&Value::Number(7.0)), Ok(7));
+    assert_eq!(
+        i32::try_from(&Value::Number(1e10)),
+        Err(Error::UnexpectedValue("a 32 bit integer"))
+    );
+    assert_eq!(String::try_from(&Value::from("abc")), Ok("abc".to_string()));
+    assert_eq!(
+        bool::try_from(&Value::Integer(1)),
+        Err(Error::UnexpectedValue("a boolean"))
+    );
+
+    let object = Value::Object(
+        ObjectId::INVALID,
+        vec![
+            Element::new("level", Rc::new(Value::from(4))),
+            Element::new(
+                "items",
+                Rc::new(Value::StrictArray(
+                    ObjectId::INVALID,
+                    vec![Value::from("sword"), Value::from("shield")]
+                        .into_iter()
+                        .collect(),
+                )),
+            ),
+            Element::new("scores", Rc::new(Value::VectorDouble(vec![0.5], false))),
+        ],
+        None,
+    );
+    assert_eq!(object.as_elements().map(|e| e.len()), Some(3));
+    assert_eq!(object.get("level").and_then(Value::as_i64), Some(4));
+    assert_eq!(object.get("missing"), None);
+    assert_eq!(
+        object
+            .get("items")
+            .and_then(|items| items.get_index(1))
+            .as_deref()
+            .and_then(Value::as_str),
+        Some("shield")
+    );
+    assert_eq!(
+        object
+            .get("scores")
+            .and_then(|scores| scores.get_index(0))
+            .and_then(|score| score.as_f64()),
+        Some(0.5)
+    );
+    assert!(object.get_index(0).is_none());
+
+    #[cfg(feature = "amf3")]
+    assert_eq!(Value::AMF3(Rc::new(Value::from(2))).as_f64(), Some(2.0));
+}
+
+#[test]
+pub fn test_lso_lookup() {
+    use flash_lso::types::{AMFVersion, Element, ElementList, Lso, ObjectId};
+    use std::rc::Rc;
+
+    let mut lso = Lso::new_empty("save", AMFVersion::AMF0);
+    assert_eq!(lso.insert("name", Value::from("alice")), None);
+    assert_eq!(lso.insert("score", Value::from(10.0)), None);
+    assert_eq!(lso.insert("level", Value::from(1.0)), None);
+    assert!(lso.contains_key("score"));
+    assert_eq!(lso.get("score"), Some(&Value::Number(10.0)));
+
+    // Replacing keeps the position of the element
+    assert_eq!(
+        lso.insert("score", Value::from(20.0)),
+        Some(Rc::new(Value::Number(10.0)))
+    );
+    let names: Vec<&str> = lso.iter().map(|e| e.name()).collect();
+    assert_eq!(names, vec!["name", "score", "level"]);
+
+    *lso.get_mut("level").unwrap() = Value::from(2.0);
+    assert_eq!(lso.get("level").and_then(Value::as_f64), Some(2.0));
+
+    assert_eq!(lso.remove("name"), Some(Rc::new(Value::from("alice"))));
+    assert_eq!(lso.remove("name"), None);
+    assert!(!lso.contains_key("name"));
+    let names: Vec<&str> = (&lso).into_iter().map(|e| e.name()).collect();
+    assert_eq!(names, vec!["score", "level"]);
+
+    // The same helpers work on the members of an object
+    let mut members = vec![Element::new("x", Rc::new(Value::from(1.0)))];
+    members.insert_by_name("y", Value::from(2.0));
+    assert_eq!(members.get_by_name("y"), Some(&Value::Number(2.0)));
+    assert!(members.contains_name("x"));
+    assert_eq!(members.remove_by_name("x"), Some(Rc::new(Value::from(1.0))));
+    lso.insert("point", Value::Object(ObjectId::INVALID, members, None));
+    assert_eq!(
+        lso.get("point").and_then(|p| p.get("y")),
+        Some(&Value::Number(2.0))
+    );
+
+    // AMF0 has no integer type, so integers are written as numbers
+    lso.insert("lives", Value::from(3));
+    let bytes = flash_lso::write::write_to_bytes(&mut lso).unwrap();
+    let decoded = Reader::default().parse(&bytes).unwrap();
+    assert_eq!(decoded.get("lives"), Some(&Value::Number(3.0)));
+    assert_eq!(
+        flash_lso::write::Writer::default()
+            .encoded_length(&lso)
+            .unwrap(),
+        bytes.len()
+    );
+}
+
+#[test]
+#[cfg(feature = "chrono")]
+pub fn test_date_chrono() {
+    use chrono::{DateTime, FixedOffset, Utc};
+    use std::convert::TryFrom;
+
+    let date = Value::Date(1_577_836_800_500.0, Some(-60i16 as u16));
+    let converted = DateTime::<FixedOffset>::try_from(&date).unwrap();
+    assert_eq!(converted.to_rfc3339(), "2020-01-01T01:00:00.500+01:00");
+    assert_eq!(Value::from(converted), date);
+
+    let west = Value::Date(1_577_836_800_000.0, Some(300));
+    let converted = DateTime::<FixedOffset>::try_from(&west).unwrap();
+    assert_eq!(converted.to_rfc3339(), "2019-12-31T19:00:00-05:00");
+    assert_eq!(Value::from(converted), west);
+
+    let utc = DateTime::<Utc>::try_from(&Value::Date(0.0, None)).unwrap();
+    assert_eq!(utc.to_rfc3339(), "1970-01-01T00:00:00+00:00");
+    assert_eq!(Value::from(utc), Value::Date(0.0, Some(0)));
+
+    assert!(DateTime::<FixedOffset>::try_from(&Value::Date(f64::NAN, None)).is_err());
+    assert!(DateTime::<FixedOffset>::try_from(&Value::Date(f64::INFINITY, None)).is_err());
+    assert!(DateTime::<FixedOffset>::try_from(&Value::Number(0.0)).is_err());
+}
+
+#[test]
+#[cfg(feature = "time")]
+pub fn test_date_time() {
+    use std::convert::TryFrom;
+    use time::{Month, OffsetDateTime};
+
+    let date = Value::Date(1_577_836_800_500.0, Some(300));
+    let converted = OffsetDateTime::try_from(&date).unwrap();
+    assert_eq!(
+        converted.date(),
+        time::Date::from_calendar_date(2019, Month::December, 31).unwrap()
+    );
+    assert_eq!(
+        converted.time(),
+        time::Time::from_hms_milli(19, 0, 0, 500).unwrap()
+    );
+    assert_eq!(converted.offset().whole_minutes(), -300);
+    assert_eq!(Value::from(converted), date);
+
+    assert!(OffsetDateTime::try_from(&Value::Date(f64::NAN, Some(0))).is_err());
+    assert!(OffsetDateTime::try_from(&Value::Date(1e300, None)).is_err());
+}
+
+#[test]
+#[cfg(feature = "xml")]
+pub fn test_xml_fixtures() {
+    use flash_lso::extra::xml::{read, write};
+    use flash_lso::types::Element;
+
+    fn collect_xml(value: &Value, out: &mut Vec<String>) {
+        let elements = |elements: &[Element], out: &mut Vec<String>| {
+            elements.iter().for_each(|e| collect_xml(e.value(), out))
+        };
+        match value {
+            Value::XML(content, _) => out.push(content.clone()),
+            Value::Object(_, members, _) => elements(members, out),
+            Value::ECMAArray(_, dense, assoc, _) => {
+                dense.iter().for_each(|v| collect_xml(v, out));
+                elements(assoc, out);
+            }
+            Value::StrictArray(_, items) | Value::VectorObject(_, items, _, _) => {
+                items.iter().for_each(|v| collect_xml(v, out))
+            }
+            Value::Dictionary(_, entries, _) => entries.iter().for_each(|(k, v)| {
+                collect_xml(k, out);
+                collect_xml(v, out);
+            }),
+            Value::Custom(custom, members, _) => {
+                elements(custom, out);
+                elements(members, out);
+            }
+            Value::AMF3(v) => collect_xml(v, out),
+            _ => {}
+        }
+    }
+
+    let files: [&[u8]; 8] = [
+        include_bytes!("sol/AS2-Demo.sol"),
+        include_bytes!("sol/AS2-XML-Demo.sol"),
+        include_bytes!("sol/AS3-Demo.sol"),
+        include_bytes!("sol/AS3-Dictionary-Demo.sol"),
+        include_bytes!("sol/AS3-XML-Demo.sol"),
+        include_bytes!("sol/AS3-XMLDoc-Demo.sol"),
+        include_bytes!("sol/InfectonatorSurvivors76561198009932603.sol"),
+        include_bytes!("sol/robokill.sol"),
+    ];
+    let mut contents = Vec::new();
+    for file in files {
+        let lso = Reader::default().parse(file).unwrap();
+        lso.body
+            .iter()
+            .for_each(|e| collect_xml(e.value(), &mut contents));
+    }
+    assert!(!contents.is_empty());
+
+    for content in contents {
+        let document = read::parse(&content).unwrap();
+        assert_eq!(write::to_string(&document), content);
+    }
+}
+
+#[test]
+#[cfg(feature = "xml")]
+pub fn test_xml_dom() {
+    use flash_lso::extra::xml::{read, write};
+
+    let content = "<start>\n  <span>testing</span>\n</start>";
+    let document = read::parse(content).unwrap();
+    assert_eq!(document.root().unwrap().name, "start");
+    assert_eq!(write::to_string(&document), content);
+
+    let deep = "<a>".repeat(200000);
+    assert_eq!(
+        read::parse(&deep),
+        Err(nom::Err::Failure(Error::XmlTooDeep(read::MAX_DEPTH)))
+    );
+    let nested = format!(
+        "{}{}",
+        "<a>".repeat(read::MAX_DEPTH),
+        "</a>".repeat(read::MAX_DEPTH)
+    );
+    assert!(read::parse(&nested).is_ok());
+
+    let compact = read::parse("<start><span>testing</span></start>").unwrap();
+    assert_eq!(write::to_pretty_string(&compact), content);
+
+    let mut document =
+        read::parse(r#"<a href="x &amp; y"><b>1 &lt; 2</b><c/><!-- note --></a>"#).unwrap();
+    let root = document.root().unwrap();
+    assert_eq!(root.attribute("href"), Some("x & y"));
+    assert_eq!(root.find("b").unwrap().text(), "1 < 2");
+
+    let root = document.root_mut().unwrap();
+    root.find_mut("b").unwrap().set_text("3 > 2");
+    root.set_attribute("id", "\"quoted\"");
+    assert_eq!(
+        write::to_string(&document),
+        r#"<a href="x &amp; y" id="&quot;quoted&quot;"><b>3 &gt; 2</b><c/><!-- note --></a>"#
+    );
+    assert_eq!(
+        document.to_value(true),
+        Value::XML(
+            r#"<a href="x &amp; y" id="&quot;quoted&quot;"><b>3 &gt; 2</b><c /><!-- note --></a>"#
+                .to_string(),
+            true
+        )
+    );
+
+    assert!(read::parse("<a><b></a>").is_err());
+}
+
+#[test]
+#[cfg(feature = "bytearray")]
+pub fn test_nested_bytearray() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::extra::bytearray::{self, Compression, Encoding, NestedAmf, NestedDecoder};
+    use flash_lso::types::{ClassDefinition, Element, ObjectId};
+    use std::rc::Rc;
+
+    let data = include_bytes!("./amf/LearnToFly3.profileData.saveString.amf");
+    let (_, expected) = flash_lso::amf3::read::AMF3Decoder::default()
+        .parse_single_element(data)
+        .expect("Failed to parse object");
+
+    for compression in [
+        Compression::None,
+        Compression::Zlib,
+        Compression::Deflate,
+        Compression::Lzma,
+    ] {
+        let compressed = bytearray::compress(data, compression);
+        assert_eq!(bytearray::detect_compression(&compressed), compression);
+
+        let nested = bytearray::decode(&compressed).expect("Failed to decode nested data");
+        assert_eq!(nested.compression, compression);
+        assert_eq!(nested.encoding, Encoding::Amf3);
+        assert_eq!(nested.values, vec![expected.clone()]);
+
+        let rewritten = nested.to_bytes()?;
+        assert_eq!(
+            bytearray::decompress(&rewritten),
+            (compression, data.to_vec())
+        );
+    }
+
+    // Several AMF0 values written one after the other
+    let nested = NestedAmf {
+        compression: Compression::Zlib,
+        encoding: Encoding::Amf0,
+        values: vec![
+            Rc::new(Value::Object(
+                ObjectId::INVALID,
+                vec![Element::new("name", Rc::new(Value::from("save")))],
+                Some(ClassDefinition::default_with_name("Save".to_string())),
+            )),
+            Rc::new(Value::Number(3.0)),
+        ],
+    };
+    let value = nested.to_value()?;
+    let Value::ByteArray(bytes) = &value else {
+        panic!("Expected a ByteArray, got {:?}", value);
+    };
+    assert_eq!(bytearray::decode(bytes), Some(nested));
+
+    assert_eq!(bytearray::decode(&[]), None);
+    assert_eq!(bytearray::decode(b"not amf"), None);
+
+    // Data that decompresses to more than the limit is left alone
+    let decoder = NestedDecoder {
+        max_decompressed_size: data.len() - 1,
+        ..Default::default()
+    };
+    for compression in [Compression::Zlib, Compression::Deflate, Compression::Lzma] {
+        let compressed = bytearray::compress(data, compression);
+        assert_eq!(
+            bytearray::decompress_limited(&compressed, data.len() - 1),
+            (Compression::None, compressed.clone())
+        );
+        assert_eq!(
+            bytearray::decompress_limited(&compressed, data.len()),
+            (compression, data.to_vec())
+        );
+        assert_eq!(decoder.decode(&compressed), None);
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "bytearray")]
+pub fn test_nested_base64() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::extra::bytearray::{self, Compression, Container, Encoding, NestedValue};
+    use flash_lso::types::ElementList;
+    use std::rc::Rc;
+
+    let data = include_bytes!("./amf/LearnToFly3.profileData.saveString.amf");
+    let nested = NestedValue {
+        container: Container::Base64,
+        amf: bytearray::decode(&bytearray::compress(data, Compression::Zlib)).unwrap(),
+    };
+    let value = nested.to_value()?;
+    let Value::String(encoded) = &value else {
+        panic!("Expected a String, got {:?}", value);
+    };
+    assert!(encoded.starts_with("eJ"));
+
+    let mut decoded = NestedValue::decode(&value).expect("Failed to decode nested data");
+    assert_eq!(decoded, nested);
+    assert_eq!(decoded.amf.encoding, Encoding::Amf3);
+
+    // Edit a property and re-encode
+    let mut profile = decoded.amf.values[0].as_ref().clone();
+    if let Value::Object(_, elements, _) = &mut profile {
+        elements.insert_by_name("daysWithoutEasterEgg", Value::Integer(7));
+    }
+    decoded.amf.values[0] = Rc::new(profile);
+    let edited = NestedValue::decode(&decoded.to_value()?).unwrap();
+    assert_eq!(
+        edited.amf.values[0].get("daysWithoutEasterEgg"),
+        Some(&Value::Integer(7))
+    );
+
+    let unwrapped = NestedValue::decode(&Value::ByteArray(data.to_vec())).unwrap();
+    assert_eq!(unwrapped.container, Container::ByteArray);
+    assert_eq!(unwrapped.amf.compression, Compression::None);
+
+    // Strings that happen to be valid Base64 of a few primitives are left alone
+    for s in ["", "AAAA", "Bxxxxxxxxxxx", "hello world", "abc"] {
+        assert_eq!(NestedValue::decode(&Value::String(s.to_string())), None);
+    }
+    assert_eq!(NestedValue::decode(&Value::Number(1.0)), None);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "bytearray")]
+pub fn test_nested_unwrap() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::extra::bytearray::{
+        self, Compression, Container, Encoding, NestedAmf, NestedDecoder, NestedValue, PathSegment,
+    };
+    use flash_lso::types::{AMFVersion, Attribute, ClassDefinition, Element, Lso, ObjectId};
+    use std::rc::Rc;
+
+    // A Base64 string, inside of an object in a ByteArray, inside of an object in the body
+    let data = include_bytes!("./amf/LearnToFly3.profileData.saveString.amf");
+    let inner = NestedValue {
+        container: Container::Base64,
+        amf: bytearray::decode(&bytearray::compress(data, Compression::Zlib)).unwrap(),
+    };
+    let outer = NestedAmf {
+        compression: Compression::None,
+        encoding: Encoding::Amf3,
+        values: vec![Rc::new(Value::Object(
+            ObjectId(1),
+            vec![Element::new("profile", Rc::new(inner.to_value()?))],
+            Some(ClassDefinition {
+                name: String::new(),
+                attributes: Attribute::Dynamic.into(),
+                static_properties: Vec::new(),
+            }),
+        ))],
+    };
+    let lso = Lso::new(
+        vec![Element::new(
+            "data",
+            Rc::new(Value::Object(
+                ObjectId::INVALID,
+                vec![Element::new("blob", Rc::new(outer.to_value()?))],
+                None,
+            )),
+        )],
+        "save",
+        AMFVersion::AMF0,
+    );
+
+    let unwrapped = NestedDecoder::default().unwrap_lso(&lso);
+    assert_eq!(unwrapped.len(), 1);
+    assert_eq!(
+        unwrapped[0].path,
+        vec![
+            PathSegment::Member("data".to_string()),
+            PathSegment::Member("blob".to_string())
+        ]
+    );
+    assert_eq!(unwrapped[0].nested.container, Container::ByteArray);
+    assert_eq!(unwrapped[0].nested.amf, outer);
+
+    let children = &unwrapped[0].children;
+    assert_eq!(children.len(), 1);
+    assert_eq!(
+        children[0].path,
+        vec![
+            PathSegment::Index(0),
+            PathSegment::Member("profile".to_string())
+        ]
+    );
+    assert_eq!(children[0].nested, inner);
+
+    // The nested data is re-encoded to the value it was found in
+    let blob = lso.get("data").unwrap().get("blob").unwrap();
+    assert_eq!(&unwrapped[0].nested.to_value()?, blob);
+    Ok(())
+}
+
+#[test]
+pub fn test_ecma_array_length() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::types::{AMFVersion, Element, Lso, ObjectId};
+    use std::rc::Rc;
+
+    let array = |dense: Vec<Value>, assoc: &[(&str, f64)], length| {
+        Value::ECMAArray(
+            ObjectId::INVALID,
+            dense.into_iter().map(Rc::new).collect(),
+            assoc
+                .iter()
+                .map(|(name, n)| Element::new(*name, Rc::new(Value::Number(*n))))
+                .collect(),
+            length,
+        )
+    };
+
+    // Only keys that are array indices extend the array
+    let sparse = array(
+        vec![Value::Null, Value::Null],
+        &[("5", 1.0), ("name", 2.0), ("07", 3.0), ("-1", 4.0)],
+        0,
+    );
+    assert_eq!(sparse.array_length(), Some(6));
+    assert_eq!(array(vec![], &[("name", 1.0)], 1).array_length(), Some(0));
+
+    let mut normalized = array(vec![], &[("0", 1.0)], 10);
+    normalized.normalize_array_length();
+    assert_eq!(normalized, array(vec![], &[("0", 1.0)], 1));
+
+    let round_trip = |version, value: Value| -> Result<u32, Box<dyn std::error::Error>> {
+        let mut lso = Lso::new_empty("test", version);
+        lso.insert("array", value);
+        let bytes = flash_lso::write::write_to_bytes(&mut lso)?;
+        let lso = Reader::default()
+            .parse(&bytes)
+            .map_err(|e| format!("{:?}", e))?;
+        match lso.get("array") {
+            Some(Value::ECMAArray(_, _, _, length)) => Ok(*length),
+            other => Err(format!("Expected an ECMAArray, got {:?}", other).into()),
+        }
+    };
+
+    // The declared length is kept in amf0 unless it is too short for the contents
+    assert_eq!(round_trip(AMFVersion::AMF0, sparse.clone())?, 6);
+    assert_eq!(
+        round_trip(AMFVersion::AMF0, array(vec![], &[("0", 1.0)], 10))?,
+        10
+    );
+    assert_eq!(
+        round_trip(AMFVersion::AMF0, array(vec![], &[("name", 1.0)], 1))?,
+        1
+    );
+
+    // Amf3 doesn't encode a length, it is always derived from the contents
+    assert_eq!(round_trip(AMFVersion::AMF3, sparse)?, 6);
+    assert_eq!(
+        round_trip(AMFVersion::AMF3, array(vec![], &[("0", 1.0)], 10))?,
+        1
+    );
+    Ok(())
+}
+
+#[test]
+pub fn test_mixed_array() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::types::{AMFVersion, Element, ElementList, Lso, ObjectId};
+    use std::rc::Rc;
+
+    fn count_arrays(value: &Value, counts: &mut [usize; 3]) {
+        let elements = |elements: &[Element], counts: &mut [usize; 3]| {
+            elements
+                .iter()
+                .for_each(|e| count_arrays(e.value(), counts))
+        };
+        match value {
+            Value::StrictArray(_, dense) => {
+                counts[0] += 1;
+                dense.iter().for_each(|v| count_arrays(v, counts));
+            }
+            Value::ECMAArray(_, dense, assoc, _) => {
+                counts[1] += 1;
+                dense.iter().for_each(|v| count_arrays(v, counts));
+                elements(assoc, counts);
+            }
+            Value::MixedArray(_, dense, assoc) => {
+                counts[2] += 1;
+                dense.iter().for_each(|v| count_arrays(v, counts));
+                elements(assoc, counts);
+            }
+            Value::Object(_, members, _) => elements(members, counts),
+            Value::AMF3(v) => count_arrays(v, counts),
+            _ => {}
+        }
+    }
+
+    // Mixed arrays are written the same as the arrays they were read from
+    let data = include_bytes!("sol/CoC_8.sol");
+    let mut reader = Reader::default();
+    reader.amf3_decoder.mixed_arrays = true;
+    let mut lso = reader.parse(data).map_err(|e| format!("{:?}", e))?;
+    let mut counts = [0; 3];
+    lso.iter()
+        .for_each(|e| count_arrays(e.value(), &mut counts));
+    assert_eq!(counts[..2], [0, 0]);
+    assert!(counts[2] > 0);
+    assert_eq!(flash_lso::write::write_to_bytes(&mut lso)?, data.to_vec());
+
+    // Conversions to and from the other array types
+    let dense = vec![Rc::new(Value::Number(1.0))];
+    let strict = Value::StrictArray(ObjectId::INVALID, dense.clone());
+    let mixed = strict.clone().into_mixed_array();
+    assert_eq!(
+        mixed,
+        Value::MixedArray(ObjectId::INVALID, dense.clone(), vec![])
+    );
+    assert_eq!(mixed.clone().into_split_array(), strict);
+
+    let assoc = vec![Element::new("3", Rc::new(Value::Null))];
+    let ecma = Value::ECMAArray(ObjectId::INVALID, dense.clone(), assoc.clone(), 4);
+    assert_eq!(ecma.clone().into_mixed_array().into_split_array(), ecma);
+    assert_eq!(Value::Null.into_mixed_array(), Value::Null);
+
+    // Adding and removing associative entries doesn't change the type
+    let mut edited = mixed;
+    if let Value::MixedArray(_, _, assoc) = &mut edited {
+        assoc.insert_by_name("name", Value::from("value"));
+        assoc.remove_by_name("name");
+    }
+    let mut lso = Lso::new_empty("test", AMFVersion::AMF3);
+    lso.insert("array", edited);
+    let bytes = flash_lso::write::write_to_bytes(&mut lso)?;
+    let mut reader = Reader::default();
+    reader.amf3_decoder.mixed_arrays = true;
+    let lso = reader.parse(&bytes).map_err(|e| format!("{:?}", e))?;
+    assert!(matches!(
+        lso.get("array"),
+        Some(Value::MixedArray(_, items, assoc)) if *items == dense && assoc.is_empty()
+    ));
+    Ok(())
+}
+
+#[cfg(feature = "flex")]
+#[test]
+pub fn test_flash_codecs() -> Result<(), Box<dyn std::error::Error>> {
+    use flash_lso::amf3::custom_encoder::ExternalCodecRegistry;
+    use flash_lso::extra::flash;
+    use flash_lso::types::{AMFVersion, Attribute, ClassDefinition, Element, Lso, ObjectId};
+    use flash_lso::write::Writer;
+    use std::rc::Rc;
+
+    let external = |name: &str, elements: Vec<Element>| {
+        let mut class_def = ClassDefinition::default_with_name(name.to_string());
+        class_def.attributes.insert(Attribute::External);
+        Rc::new(Value::Custom(elements, vec![], Some(class_def)))
+    };
+    let number = |n: f64| Rc::new(Value::Number(n));
+
+    let mut lso = Lso::new(
+        vec![
+            Element::new(
+                "items",
+                external(
+                    "mx.collections.ArrayCollection",
+                    vec![Element::new(
+                        "data",
+                        Rc::new(Value::StrictArray(
+                            ObjectId::INVALID,
+                            vec![number(1.0), number(2.0)],
+                        )),
+                    )],
+                ),
+            ),
+            Element::new(
+                "proxy",
+                external(
+                    "mx.utils.ObjectProxy",
+                    vec![Element::new("object", Rc::new(Value::from("value")))],
+                ),
+            ),
+        ],
+        "save",
+        AMFVersion::AMF3,
+    );
+
+    let mut writer = Writer::default();
+    codecs(flash::register_codecs).install_writer(&mut writer);
+    let mut buffer = vec![];
+    writer.write_full(&mut buffer, &mut lso)?;
+
+    let mut reader = Reader::default();
+    codecs(flash::register_codecs).install_reader(&mut reader);
+    let mut decoded = reader.parse(&buffer).expect("Failed to parse flash types");
+    assert_eq!(decoded.body[1], lso.body[1]);
+
+    let mut buffer2 = vec![];
+    writer = Writer::default();
+    codecs(flash::register_codecs).install_writer(&mut writer);
+    writer.write_full(&mut buffer2, &mut decoded)?;
+    assert_eq!(buffer, buffer2);
+
+    // A collection without its data can't be written
+    let mut lso = Lso::new(
+        vec![Element::new(
+            "items",
+            external("mx.collections.ArrayCollection", vec![]),
+        )],
+        "save",
+        AMFVersion::AMF3,
+    );
+    let error = writer.write_full(&mut vec![], &mut lso).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+    // Geometry classes aren't externalizable, so have no codec
+    let mut registry = ExternalCodecRegistry::new();
+    flash::register_codecs(&mut registry);
+    assert!(registry.contains("mx.collections.ArrayCollection"));
+    for name in [
+        "flash.geom.Point",
+        "flash.geom.Rectangle",
+        "flash.geom.Matrix",
+    ] {
+        assert!(!registry.contains(name));
+    }
+    Ok(())
+}
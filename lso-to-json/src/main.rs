@@ -3,11 +3,14 @@
 
 #![deny(missing_docs, clippy::missing_docs_in_private_items)]
 
-use clap::{Arg, Command};
-use flash_lso::amf3::read::AMF3Decoder;
+use chrono::{DateTime, FixedOffset, SecondsFormat};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use flash_lso::amf3::custom_encoder::ExternalCodecRegistry;
+use flash_lso::amf3::read::{AMF3Decoder, UnknownExternalPolicy};
+use flash_lso::extra::bytearray::{NestedDecoder, UnwrappedValue};
 use flash_lso::extra::*;
 use flash_lso::read::Reader;
-use flash_lso::types::Lso;
+use flash_lso::types::{Lso, Value};
 use std::path::PathBuf;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -16,8 +19,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let matched = Command::new("Lso -> json converter")
         .version("1.0")
         .author("CUB3D <callumthom11@gmail.com>")
-        .subcommand(Command::new("file").arg(Arg::new("INPUT").help("").required(true)))
-        .subcommand(Command::new("object-amf3").arg(Arg::new("INPUT").help("").required(true)))
+        .subcommand(
+            Command::new("file")
+                .arg(Arg::new("INPUT").help("").required(true))
+                .arg(iso_dates_arg())
+                .arg(unwrap_nested_arg())
+                .arg(capture_unknown_arg()),
+        )
+        .subcommand(
+            Command::new("object-amf3")
+                .arg(Arg::new("INPUT").help("").required(true))
+                .arg(iso_dates_arg())
+                .arg(unwrap_nested_arg()),
+        )
         .subcommand(Command::new("regen").arg(Arg::new("INPUT").help("").required(true)))
         .subcommand_required(true)
         .get_matches();
@@ -29,9 +43,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     match cmd {
         "file" => {
             let data = std::fs::read(PathBuf::from(file_name))?;
-            match parse_file(&data) {
+            match parse_file(&data, args.get_flag("capture-unknown-externals")) {
                 Ok(lso) => {
-                    let json = serde_json::to_string(&lso).expect("Unable to encode lso as json");
+                    let nested = unwrap_nested(args, |decoder| decoder.unwrap_lso(&lso));
+                    let json = serde_json::to_value(&lso)
+                        .and_then(|json| to_json(json, nested, args))
+                        .expect("Unable to encode lso as json");
                     println!("{}", json);
                 }
                 Err(e) => {
@@ -58,7 +75,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         .unwrap()
                         .join(f.file_name().to_string_lossy().replace(".sol", ".json"));
 
-                    match parse_file(&data) {
+                    match parse_file(&data, false) {
                         Ok(lso) => {
                             let json =
                                 serde_json::to_string(&lso).expect("Unable to encode lso as json");
@@ -98,7 +115,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             let (_, obj) = AMF3Decoder::default()
                 .parse_single_element(&data)
                 .expect("Failed to parse object");
-            let json = serde_json::to_string(&obj).expect("Unable to encode lso as json");
+            let nested = unwrap_nested(args, |decoder| decoder.unwrap(&obj));
+            let json = serde_json::to_value(&obj)
+                .and_then(|json| to_json(json, nested, args))
+                .expect("Unable to encode lso as json");
             println!("{}", json);
         }
         _ => {
@@ -109,10 +129,100 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-/// Parse a given slice into an Lso
-fn parse_file(data: &[u8]) -> Result<Lso, Box<dyn std::error::Error + '_>> {
+/// The codecs used for externalized flex and ActionScript classes
+fn codec_registry() -> ExternalCodecRegistry {
+    let mut registry = ExternalCodecRegistry::new();
+    flex::register_codecs(&mut registry);
+    flash::register_codecs(&mut registry);
+    registry
+}
+
+/// Parse a given slice into an Lso, capturing the data of unknown externalized classes if `capture_unknown` is set
+fn parse_file(data: &[u8], capture_unknown: bool) -> Result<Lso, Box<dyn std::error::Error + '_>> {
     let mut d = Reader::default();
-    flex::read::register_decoders(&mut d.amf3_decoder);
+    codec_registry().install_reader(&mut d);
+    if capture_unknown {
+        d.amf3_decoder.unknown_external = UnknownExternalPolicy::Capture;
+    }
     let lso = d.parse(data)?;
     Ok(lso)
 }
+
+/// The argument for rendering dates as ISO-8601 strings
+fn iso_dates_arg() -> Arg {
+    Arg::new("iso-dates")
+        .long("iso-dates")
+        .action(ArgAction::SetTrue)
+        .help("Render dates as ISO-8601 strings rather than epoch milliseconds and a timezone")
+}
+
+/// The argument for decoding AMF data nested in strings and byte arrays
+fn unwrap_nested_arg() -> Arg {
+    Arg::new("unwrap-nested")
+        .long("unwrap-nested")
+        .action(ArgAction::SetTrue)
+        .help("Decode AMF data nested in Base64 strings and (compressed) byte arrays, listing it with its path")
+}
+
+/// The argument for keeping the data of externalized classes that have no codec
+fn capture_unknown_arg() -> Arg {
+    Arg::new("capture-unknown-externals")
+        .long("capture-unknown-externals")
+        .action(ArgAction::SetTrue)
+        .help("Keep the data of externalized objects with no known codec as a byte array rather than failing, only usable if the object is the last value in the file")
+}
+
+/// Find the AMF data nested inside of a value with `unwrap`, if requested by `args`
+fn unwrap_nested(
+    args: &ArgMatches,
+    unwrap: impl FnOnce(&NestedDecoder) -> Vec<UnwrappedValue>,
+) -> Option<Vec<UnwrappedValue>> {
+    if !args.get_flag("unwrap-nested") {
+        return None;
+    }
+    Some(unwrap(&NestedDecoder::new(codec_registry())))
+}
+
+/// Encode json, along with any nested data that was found as `{"value": ..., "nested": [...]}`, following the options
+/// in `args`
+fn to_json(
+    json: serde_json::Value,
+    nested: Option<Vec<UnwrappedValue>>,
+    args: &ArgMatches,
+) -> serde_json::Result<String> {
+    let mut json = match nested {
+        Some(nested) => serde_json::json!({ "value": json, "nested": nested }),
+        None => json,
+    };
+    if args.get_flag("iso-dates") {
+        render_iso_dates(&mut json);
+    }
+    serde_json::to_string(&json)
+}
+
+/// Replace every valid date in the given json with its ISO-8601 rendering
+fn render_iso_dates(json: &mut serde_json::Value) {
+    match json {
+        serde_json::Value::Object(map) => {
+            if map.len() == 1 {
+                if let Some(date) = map.get_mut("Date") {
+                    if let Some(iso) = iso_date(date) {
+                        *date = serde_json::Value::String(iso);
+                    }
+                    return;
+                }
+            }
+            map.values_mut().for_each(render_iso_dates);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(render_iso_dates),
+        _ => {}
+    }
+}
+
+/// Render the `[millis, timezone]` json of a `Value::Date` as ISO-8601, if it is a valid date
+fn iso_date(date: &serde_json::Value) -> Option<String> {
+    let millis = date.get(0)?.as_f64()?;
+    let tz = date.get(1)?.as_u64().and_then(|tz| u16::try_from(tz).ok());
+    let date = DateTime::<FixedOffset>::try_from(&Value::Date(millis, tz)).ok()?;
+    Some(date.to_rfc3339_opts(SecondsFormat::Millis, false))
+}
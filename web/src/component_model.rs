@@ -1,7 +1,10 @@
+use chrono::{DateTime, FixedOffset, SecondsFormat};
 use std::string::ToString;
 use yew::prelude::*;
 
-use flash_lso::extra::flex;
+use flash_lso::amf3::custom_encoder::ExternalCodecRegistry;
+use flash_lso::amf3::read::UnknownExternalPolicy;
+use flash_lso::extra::{flash, flex};
 use flash_lso::read::Reader;
 use flash_lso::types::{Attribute, Element, Lso, Value};
 
@@ -117,7 +120,11 @@ impl Component for Model {
             }
             Msg::Loaded(index, file) => {
                 let mut parser = Reader::default();
-                flex::read::register_decoders(&mut parser.amf3_decoder);
+                let mut registry = ExternalCodecRegistry::new();
+                flex::register_codecs(&mut registry);
+                flash::register_codecs(&mut registry);
+                registry.install_reader(&mut parser);
+                parser.amf3_decoder.unknown_external = UnknownExternalPolicy::Capture;
 
                 match parser.parse(&file.content) {
                     Ok(sol) => {
@@ -362,6 +369,13 @@ impl Model {
                         })} value={format!("{}", x)} class="form-control" type="number"/>
                   </div>
 
+                  <div class="input-group mb-2">
+                    <div class="input-group-prepend">
+                      <div class="input-group-text">{"ISO-8601"}</div>
+                    </div>
+                    <input value={DateTime::<FixedOffset>::try_from(&Value::Date(x, tz)).map_or_else(|_| "Invalid date".to_string(), |d| d.to_rfc3339_opts(SecondsFormat::Millis, false))} class="form-control" type="text" readonly=true/>
+                  </div>
+
                   { if tz.is_some() { html!{
                   <div class="input-group mb-2">
                     <div class="input-group-prepend">
@@ -373,14 +387,14 @@ impl Model {
                             let input = target.and_then(|t| t.dyn_into::<HtmlInputElement>().ok());
 
                             input.map(|input| {
-                            if let Ok(tz) = input.value().parse::<u16>() {
-                                     Msg::Edited(Value::Date(x, Some(tz)))
+                            if let Ok(tz) = input.value().parse::<i16>() {
+                                     Msg::Edited(Value::Date(x, Some(tz as u16)))
                                  } else {
                                      Msg::Edited(Value::Date(x, tz))
                                 }
                             })
                         })}
-                      value={format!("{}", tz.web_expect("Unable to get timezone"))} class="form-control" type="number"/>
+                      value={format!("{}", tz.web_expect("Unable to get timezone") as i16)} class="form-control" type="number"/>
                   </div>
                   }} else {html!{}}}
                 </>
@@ -704,6 +718,7 @@ impl Model {
                                     Value::VectorObject(_, _, _, _) => "Vector<Object>".to_string(),
                                     Value::Amf3ObjectReference(_) => "Reference".to_string(),
                                     Value::Dictionary(_, _, _) => "Dictionary".to_string(),
+                                    Value::MixedArray(_, _, _) => "MixedArray".to_string(),
                                     Value::Custom(_, _, cd) => {
                                         if let Some(cd) = cd {
                                             format!("Custom<{}>", cd.name)
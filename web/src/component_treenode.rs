@@ -1,4 +1,7 @@
 use crate::{EditableValue, TreeNodePath};
+use flash_lso::amf3::custom_encoder::ExternalCodecRegistry;
+use flash_lso::extra::bytearray::{NestedDecoder, NestedValue};
+use flash_lso::extra::{flash, flex};
 use flash_lso::types::{Element, Value};
 use std::ops::Deref;
 use std::rc::Rc;
@@ -13,11 +16,24 @@ pub enum Msg {
     ElementChange(Element),
     CustomElementChange(Element),
     CustomElementChangeStandard(Element),
+    NestedElementChange(Element),
 }
 
 pub struct TreeNode {
     expanded: bool,
     value: Value,
+    /// AMF data nested inside of a string or byte array value
+    nested: Option<NestedValue>,
+    /// Whether `nested` has been decoded, this is only done once the node is expanded as decoding can be slow
+    nested_decoded: bool,
+}
+
+/// The codecs used for externalized classes in nested AMF data
+fn nested_registry() -> ExternalCodecRegistry {
+    let mut registry = ExternalCodecRegistry::new();
+    flex::register_codecs(&mut registry);
+    flash::register_codecs(&mut registry);
+    registry
 }
 
 #[derive(PartialEq, Properties, Clone)]
@@ -37,10 +53,11 @@ impl Component for TreeNode {
     type Properties = Props;
 
     fn create(ctx: &Context<Self>) -> Self {
-        let value = ctx.props().value.clone();
         Self {
             expanded: false,
-            value,
+            value: ctx.props().value.clone(),
+            nested: None,
+            nested_decoded: false,
         }
     }
 
@@ -53,13 +70,19 @@ impl Component for TreeNode {
             }
             Msg::Toggle => {
                 self.expanded = !self.expanded;
+                if self.expanded {
+                    self.decode_nested();
+                }
                 true
             }
             Msg::Edited(v) => {
-                self.value = v.clone();
-                if let Some(x) = &ctx.props().element_callback {
-                    x.emit(Element::new(ctx.props().name.clone(), Rc::new(v)));
+                self.value = v;
+                self.nested = None;
+                self.nested_decoded = false;
+                if self.expanded {
+                    self.decode_nested();
                 }
+                self.emit_change(ctx);
                 true
             }
             Msg::ElementChange(el) => {
@@ -74,6 +97,7 @@ impl Component for TreeNode {
                         log::warn!("Unknown element change");
                     }
                 }
+                self.emit_change(ctx);
 
                 true
             }
@@ -89,6 +113,7 @@ impl Component for TreeNode {
                         log::warn!("Unknown element change for custom element");
                     }
                 }
+                self.emit_change(ctx);
 
                 true
             }
@@ -104,6 +129,25 @@ impl Component for TreeNode {
                         log::warn!("Unknown element change for custom element standard");
                     }
                 }
+                self.emit_change(ctx);
+
+                true
+            }
+            Msg::NestedElementChange(el) => {
+                let registry = nested_registry();
+                if let Some(nested) = &mut self.nested {
+                    let index = el.name.parse::<usize>().ok();
+                    if let Some(value) = index.and_then(|i| nested.amf.values.get_mut(i)) {
+                        *value = el.value;
+                    }
+                    match nested.to_value_with(&registry) {
+                        Ok(value) => self.value = value,
+                        Err(e) => log::warn!("Failed to encode nested value: {:?}", e),
+                    }
+                } else {
+                    log::warn!("Unknown element change for nested value");
+                }
+                self.emit_change(ctx);
 
                 true
             }
@@ -114,7 +158,7 @@ impl Component for TreeNode {
         let name = ctx.props().name.clone();
         let value = self.value.clone();
 
-        let icon = if TreeNode::has_children(&value) {
+        let icon = if TreeNode::has_children(&value) || self.nested.is_some() {
             if self.expanded {
                 "icon/folder-minus.svg"
             } else {
@@ -165,7 +209,7 @@ impl TreeNode {
         // Visible if no filter or if we are included in filter, also we must be visible if we have visible children
         let has_visible_children = match &ctx.props().value {
             Value::Object(_, ele, _) => ele.iter().any(|e| e.name.contains(&ctx.props().filter)),
-            Value::ECMAArray(_id, e1, e2, _) => {
+            Value::ECMAArray(_id, e1, e2, _) | Value::MixedArray(_id, e1, e2) => {
                 e2.iter().any(|e| e.name.contains(&ctx.props().filter))
                     || e1
                         .iter()
@@ -192,13 +236,31 @@ impl TreeNode {
             || (TreeNode::has_children(&ctx.props().value) && has_visible_children)
     }
 
+    /// Decode the AMF data nested inside of the value of this node, if it hasn't been already
+    fn decode_nested(&mut self) {
+        if !self.nested_decoded {
+            self.nested = NestedDecoder::new(nested_registry()).decode_value(&self.value);
+            self.nested_decoded = true;
+        }
+    }
+
+    /// Notify the parent that the value of this node has changed
+    fn emit_change(&self, ctx: &Context<Self>) {
+        if let Some(x) = &ctx.props().element_callback {
+            x.emit(Element::new(
+                ctx.props().name.clone(),
+                Rc::new(self.value.clone()),
+            ));
+        }
+    }
+
     pub fn path(&self, ctx: &Context<Self>) -> TreeNodePath {
         ctx.props().parent_path.join(ctx.props().name.clone())
     }
 
     pub fn selected(&self, ctx: &Context<Self>) -> bool {
         let selected_path = ctx.props().selection.clone().map(|s| s.path);
-        selected_path.map_or(false, |tnp| tnp.contains(self.path(ctx)))
+        selected_path.is_some_and(|tnp| tnp.contains(self.path(ctx)))
     }
 
     pub fn has_children(data: &Value) -> bool {
@@ -207,6 +269,7 @@ impl TreeNode {
             Value::Object(_, _, _)
                 | Value::StrictArray(_, _)
                 | Value::ECMAArray(_, _, _, _)
+                | Value::MixedArray(_, _, _)
                 | Value::VectorObject(_, _, _, _)
                 | Value::AMF3(_)
                 | Value::Dictionary(_, _, _)
@@ -237,14 +300,16 @@ impl TreeNode {
                     { for x.iter().enumerate().map(|(i, v)| self.view_array_element(ctx, i, v))}
                 </ul>
             },
-            Value::ECMAArray(_id, dense, assoc, _size) => html! {
-                    <ul>
-                       { for dense.iter().enumerate().map(|(i, v)| self.view_array_element(ctx, i, v))}
-                        { for assoc.iter().map(|e| html! {
-                            <TreeNode filter={ctx.props().filter.clone()} selection={ctx.props().selection.clone()} parent_path={self.path(ctx)} name={e.name.clone()} value={e.value.deref().clone()} parent_callback={ctx.link().callback(Msg::Selection)}></TreeNode>
-                        })}
-                    </ul>
-            },
+            Value::ECMAArray(_id, dense, assoc, _) | Value::MixedArray(_id, dense, assoc) => {
+                html! {
+                        <ul>
+                           { for dense.iter().enumerate().map(|(i, v)| self.view_array_element(ctx, i, v))}
+                            { for assoc.iter().map(|e| html! {
+                                <TreeNode filter={ctx.props().filter.clone()} selection={ctx.props().selection.clone()} parent_path={self.path(ctx)} name={e.name.clone()} value={e.value.deref().clone()} parent_callback={ctx.link().callback(Msg::Selection)}></TreeNode>
+                            })}
+                        </ul>
+                }
+            }
             Value::VectorObject(_id, children, _name, _fixed_len) => html! {
                 <ul>
                    { for children.iter().enumerate().map(|(i, v)| self.view_array_element(ctx, i, v))}
@@ -284,6 +349,16 @@ impl TreeNode {
                     </li>
                 </ul>
             },
+            Value::String(_) | Value::ByteArray(_) => match &self.nested {
+                Some(nested) => html! {
+                    <ul>
+                        { for nested.amf.values.iter().enumerate().map(|(i, v)| html! {
+                            <TreeNode element_callback={ctx.link().callback(Msg::NestedElementChange)} filter={ctx.props().filter.clone()} selection={ctx.props().selection.clone()} parent_path={self.path(ctx)} name={format!("{}", i)} value={v.deref().clone()} parent_callback={ctx.link().callback(Msg::Selection)}></TreeNode>
+                        })}
+                    </ul>
+                },
+                None => html! {},
+            },
             _ => html! {},
         }
     }